@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct VshConfig {
     /// Default syntax preference
     pub default_syntax: Option<String>,
@@ -16,6 +17,10 @@ pub struct VshConfig {
 
     /// Color scheme
     pub color_scheme: Option<String>,
+
+    /// What a glob matching nothing does: "error", or "pass" to keep the
+    /// pattern as bash does
+    pub glob_no_match: Option<String>,
 }
 
 impl Default for VshConfig {
@@ -27,6 +32,7 @@ impl Default for VshConfig {
             explain_errors: true,
             prompt: Some("vsh$ ".to_string()),
             color_scheme: Some("default".to_string()),
+            glob_no_match: Some("error".to_string()),
         }
     }
 }
@@ -79,6 +85,13 @@ mod tests {
         assert_eq!(config.default_syntax, Some("adaptive".to_string()));
     }
 
+    #[test]
+    fn test_partial_config_keeps_defaults() {
+        let config: VshConfig = toml::from_str("glob_no_match = \"pass\"").unwrap();
+        assert_eq!(config.glob_no_match, Some("pass".to_string()));
+        assert!(config.show_suggestions);
+    }
+
     #[test]
     fn test_serialize_config() {
         let config = VshConfig::default();
@@ -7,6 +7,10 @@ pub enum VshError {
     #[error("Parse error: {0}")]
     ParseError(String),
 
+    /// Input ended inside a block or quote; more lines may complete it
+    #[error("Parse error: {0}")]
+    Incomplete(String),
+
     #[error("Execution error: {0}")]
     ExecutionError(String),
 
@@ -19,6 +23,10 @@ pub enum VshError {
     #[error("Invalid syntax: {0}")]
     InvalidSyntax(String),
 
+    /// A value of the wrong type, as in `$((3 + "abc"))`
+    #[error("Type error: {0}")]
+    TypeError(String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
@@ -30,6 +38,22 @@ pub enum VshError {
 }
 
 impl VshError {
+    /// The exit status a command failing with this error ends with: 1 for
+    /// a general failure, 2 for input that doesn't parse, 127 for an
+    /// unknown command as in bash, and a code of its own for the rest
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            VshError::ExecutionError(_) | VshError::Other(_) => 1,
+            VshError::ParseError(_) | VshError::Incomplete(_) => 2,
+            VshError::InvalidSyntax(_) => 3,
+            VshError::FileNotFound(_) => 4,
+            VshError::PermissionDenied(_) => 5,
+            VshError::IoError(_) => 6,
+            VshError::TypeError(_) => 7,
+            VshError::UnknownCommand(_) => 127,
+        }
+    }
+
     pub fn with_suggestion(&self) -> String {
         match self {
             VshError::UnknownCommand(_cmd) => {
@@ -0,0 +1,224 @@
+use crate::error::{Result, VshError};
+use crate::parser::{Quote, Token};
+
+/// One character of a word and how it was quoted
+type QuotedChar = (char, Quote);
+
+/// Most items a `{1..n}` sequence may have, since every item becomes a word
+const MAX_SEQUENCE: u64 = 1_000_000;
+
+/// Expand `{a,b,c}` alternatives and `{1..5}` / `{a..e}` sequences, as in
+/// `file{1..3}.txt` or `{src,tests}/*.rs`. Quoted braces, and braces with
+/// neither a comma nor a sequence inside (like `{name}`), stay as they are.
+pub fn expand(word: &Token) -> Result<Vec<Token>> {
+    let chars: Vec<QuotedChar> = word
+        .parts
+        .iter()
+        .flat_map(|(text, quote)| text.chars().map(move |ch| (ch, *quote)))
+        .collect();
+
+    Ok(match expand_chars(&chars)? {
+        Some(words) => words
+            .into_iter()
+            .filter(|chars| !chars.is_empty())
+            .map(to_token)
+            .collect(),
+        None => vec![word.clone()],
+    })
+}
+
+/// Every expansion of the first group that expands, or None if none does
+fn expand_chars(chars: &[QuotedChar]) -> Result<Option<Vec<Vec<QuotedChar>>>> {
+    let mut first = None;
+    for open in (0..chars.len()).filter(|&i| chars[i] == ('{', Quote::Bare)) {
+        if let Some((close, alternatives)) = group(chars, open)? {
+            first = Some((open, close, alternatives));
+            break;
+        }
+    }
+    let Some((open, close, alternatives)) = first else {
+        return Ok(None);
+    };
+
+    let mut words = Vec::new();
+    for alternative in alternatives {
+        let mut word = chars[..open].to_vec();
+        word.extend(alternative);
+        word.extend_from_slice(&chars[close + 1..]);
+
+        // Nested and later groups expand too
+        match expand_chars(&word)? {
+            Some(expanded) => words.extend(expanded),
+            None => words.push(word),
+        }
+    }
+    Ok(Some(words))
+}
+
+/// The group starting at `open`: where it closes and its alternatives
+fn group(chars: &[QuotedChar], open: usize) -> Result<Option<(usize, Vec<Vec<QuotedChar>>)>> {
+    let mut depth = 0;
+    let mut alternatives = vec![Vec::new()];
+
+    for (i, &(ch, quote)) in chars.iter().enumerate().skip(open + 1) {
+        let bare = quote == Quote::Bare;
+        match ch {
+            '{' if bare => depth += 1,
+            '}' if bare && depth > 0 => depth -= 1,
+            '}' if bare => {
+                if alternatives.len() > 1 {
+                    return Ok(Some((i, alternatives)));
+                }
+                let inside: String = chars[open + 1..i].iter().map(|(ch, _)| ch).collect();
+                let all_bare = chars[open + 1..i].iter().all(|(_, q)| *q == Quote::Bare);
+                let Some(items) = all_bare.then(|| sequence(&inside)).flatten() else {
+                    return Ok(None);
+                };
+                let items = items?
+                    .into_iter()
+                    .map(|item| item.chars().map(|ch| (ch, Quote::Bare)).collect())
+                    .collect();
+                return Ok(Some((i, items)));
+            }
+            ',' if bare && depth == 0 => {
+                alternatives.push(Vec::new());
+                continue;
+            }
+            _ => {}
+        }
+        if let Some(alternative) = alternatives.last_mut() {
+            alternative.push((ch, quote));
+        }
+    }
+
+    Ok(None)
+}
+
+/// The items of `1..5`, `10..0..2` or `a..e`; `None` if the text isn't a
+/// sequence
+fn sequence(text: &str) -> Option<Result<Vec<String>>> {
+    let bounds: Vec<&str> = text.split("..").collect();
+    let (start, end, step) = match bounds.as_slice() {
+        [start, end] => (*start, *end, 1),
+        [start, end, step] => (
+            *start,
+            *end,
+            step.parse::<i64>().ok()?.checked_abs()?.max(1),
+        ),
+        _ => return None,
+    };
+
+    if let (Ok(start), Ok(end)) = (start.parse::<i64>(), end.parse::<i64>()) {
+        return Some(
+            steps(text, start, end, step).map(|numbers| numbers.map(|n| n.to_string()).collect()),
+        );
+    }
+
+    let (mut a, mut b) = (start.chars(), end.chars());
+    match (a.next(), a.next(), b.next(), b.next()) {
+        (Some(start), None, Some(end), None) if start.is_ascii() && end.is_ascii() => Some(
+            steps(text, start as i64, end as i64, step)
+                .map(|numbers| numbers.map(|n| char::from(n as u8).to_string()).collect()),
+        ),
+        _ => None,
+    }
+}
+
+/// Count from `start` to `end` (both included) in either direction
+fn steps(text: &str, start: i64, end: i64, step: i64) -> Result<impl Iterator<Item = i64>> {
+    let step = step as u64;
+    let count = start.abs_diff(end) / step;
+    if count >= MAX_SEQUENCE {
+        return Err(VshError::InvalidSyntax(format!(
+            "Sequence {{{}}} has more than {} items",
+            text, MAX_SEQUENCE
+        )));
+    }
+
+    // Every item lies between `start` and `end`, so none of this overflows
+    Ok((0..=count).filter_map(move |i| {
+        let offset = i.checked_mul(step)?;
+        if start <= end {
+            start.checked_add_unsigned(offset)
+        } else {
+            start.checked_sub_unsigned(offset)
+        }
+    }))
+}
+
+/// Turn characters back into a word, keeping their quoting
+fn to_token(chars: Vec<QuotedChar>) -> Token {
+    let parts = chars
+        .into_iter()
+        .map(|(ch, quote)| (ch.to_string(), quote))
+        .collect();
+    Token::from_parts(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tokenize;
+
+    fn words(input: &str) -> Vec<String> {
+        let token = &tokenize(input).unwrap()[0];
+        expand(token)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.value)
+            .collect()
+    }
+
+    #[test]
+    fn test_alternatives() {
+        assert_eq!(words("{a,b,c}.txt"), vec!["a.txt", "b.txt", "c.txt"]);
+        assert_eq!(words("x{1,2}{a,b}"), vec!["x1a", "x1b", "x2a", "x2b"]);
+        assert_eq!(
+            words("{src,tests/{unit,e2e}}"),
+            vec!["src", "tests/unit", "tests/e2e"]
+        );
+        assert_eq!(words("file{,.bak}"), vec!["file", "file.bak"]);
+    }
+
+    #[test]
+    fn test_sequences() {
+        assert_eq!(words("v{1..3}"), vec!["v1", "v2", "v3"]);
+        assert_eq!(words("{3..1}"), vec!["3", "2", "1"]);
+        assert_eq!(words("{0..10..5}"), vec!["0", "5", "10"]);
+        assert_eq!(words("{a..c}"), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_huge_sequences() {
+        let expand_word = |input: &str| expand(&tokenize(input).unwrap()[0]);
+
+        for huge in [
+            "x{1..1000000000}",
+            "{0..1000000}",
+            "{-9223372036854775808..9223372036854775807}",
+        ] {
+            assert!(matches!(expand_word(huge), Err(VshError::InvalidSyntax(_))));
+        }
+        assert_eq!(
+            words("{-9223372036854775808..9223372036854775807..9223372036854775807}"),
+            vec!["-9223372036854775808", "-1", "9223372036854775806"]
+        );
+        assert_eq!(
+            words("{9223372036854775807..9223372036854775806}"),
+            vec!["9223372036854775807", "9223372036854775806"]
+        );
+        // A step too big to have a size is not a sequence
+        assert_eq!(
+            words("{1..2..-9223372036854775808}"),
+            vec!["{1..2..-9223372036854775808}"]
+        );
+    }
+
+    #[test]
+    fn test_left_alone() {
+        assert_eq!(words("{name}"), vec!["{name}"]);
+        assert_eq!(words("\"{a,b}\""), vec!["{a,b}"]);
+        assert_eq!(words("{a,b"), vec!["{a,b"]);
+        assert_eq!(words("{}"), vec!["{}"]);
+    }
+}
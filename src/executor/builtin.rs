@@ -1,101 +1,193 @@
 use crate::error::{Result, VshError};
+use crate::executor::content;
+use crate::executor::copy::{path_error, Copier, CopyOptions, Overwrite};
+use crate::executor::find::{FindOptions, Finder};
+use crate::executor::list::{self, ListOptions};
+use crate::executor::navigate;
+use crate::executor::report_error;
 use crate::parser::{Command, Intent};
 use colored::*;
+use nix::sys::stat::{utimensat, UtimensatFlags};
+use nix::sys::time::TimeSpec;
 use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 
-/// Execute a built-in command
-pub fn execute_builtin(cmd: Command) -> Result<()> {
+/// Execute a built-in command, writing its output to `out`, and return its
+/// exit status
+pub fn execute_builtin(cmd: Command, out: &mut dyn Write) -> Result<i32> {
     match cmd.intent {
-        Intent::Copy => execute_copy(&cmd),
-        Intent::Move => execute_move(&cmd),
-        Intent::Remove => execute_remove(&cmd),
-        Intent::List => execute_list(&cmd),
-        Intent::ChangeDirectory => execute_cd(&cmd),
-    }
+        Intent::Remove => return execute_remove(&cmd, out),
+        Intent::Copy => execute_copy(&cmd, out),
+        Intent::Move => execute_move(&cmd, out),
+        Intent::List => execute_list(&cmd, out),
+        Intent::ChangeDirectory => navigate::execute_cd(&cmd, out),
+        Intent::MakeDirectory => execute_make_directory(&cmd, out),
+        Intent::Find => execute_find(&cmd, out),
+        Intent::Show | Intent::Head | Intent::Tail => content::execute_show(&cmd, out),
+        Intent::Touch => execute_touch(&cmd, out),
+        Intent::Echo => execute_echo(&cmd.args.extra, out),
+        Intent::External(program) => Err(VshError::ExecutionError(format!(
+            "'{}' is not a built-in command",
+            program
+        ))),
+        // Everything else needs the shell's own state
+        intent => Err(VshError::ExecutionError(format!(
+            "'{}' is handled by the shell itself",
+            format!("{:?}", intent).to_lowercase()
+        ))),
+    }?;
+    Ok(0)
 }
 
 /// Execute copy command
-fn execute_copy(cmd: &Command) -> Result<()> {
-    let source = cmd
-        .args
-        .source
-        .as_ref()
-        .ok_or_else(|| VshError::InvalidSyntax("Missing source".to_string()))?;
-
+fn execute_copy(cmd: &Command, out: &mut dyn Write) -> Result<()> {
     let dest = cmd
         .args
         .destination
         .as_ref()
         .ok_or_else(|| VshError::InvalidSyntax("Missing destination".to_string()))?;
 
+    let options = CopyOptions::from_flags(&cmd.args.flags)?;
+    check_destination(&cmd.args.sources, dest)?;
+
+    for_each_source(&cmd.args.sources, "copied", out, |source, out| {
+        copy_one(source, dest, options, out)
+    })
+}
+
+/// Copy one source to (or into) `dest`
+fn copy_one(source: &str, dest: &str, options: CopyOptions, out: &mut dyn Write) -> Result<()> {
     let source_path = Path::new(source);
     let dest_path = Path::new(dest);
 
-    // Check if source exists
-    if !source_path.exists() {
-        return Err(VshError::FileNotFound(source.clone()));
+    // Check if source exists (a dangling symlink still counts)
+    if source_path.symlink_metadata().is_err() {
+        return Err(VshError::FileNotFound(source.to_string()));
     }
 
-    // Check if source is a file or directory
-    if source_path.is_file() {
-        // Determine final destination path
-        let final_dest =
-            if dest_path.is_dir() {
-                // Copy into directory with same filename
-                dest_path.join(source_path.file_name().ok_or_else(|| {
-                    VshError::InvalidSyntax("Invalid source filename".to_string())
-                })?)
-            } else {
-                dest_path.to_path_buf()
-            };
-
-        // Perform the copy
-        fs::copy(source_path, &final_dest).map_err(|e| {
-            if e.kind() == std::io::ErrorKind::PermissionDenied {
-                VshError::PermissionDenied(source.clone())
-            } else {
-                VshError::IoError(e)
-            }
-        })?;
+    let is_dir = source_path.is_dir();
+    if is_dir && !options.recursive {
+        return Err(VshError::ExecutionError(format!(
+            "'{}' is a directory; use 'cp -r' or 'copy {} recursively to {}'",
+            source, source, dest
+        )));
+    }
 
-        println!(
+    // Copying into a directory keeps the source's name
+    let final_dest = if dest_path.is_dir() {
+        dest_path.join(
+            source_path
+                .file_name()
+                .ok_or_else(|| VshError::InvalidSyntax("Invalid source filename".to_string()))?,
+        )
+    } else {
+        dest_path.to_path_buf()
+    };
+    check_not_inside(source_path, &final_dest, is_dir)?;
+
+    let mut copier = Copier::new(options, out);
+    copier.copy(source_path, &final_dest)?;
+    let (copied, skipped) = (copier.copied, copier.skipped);
+
+    if is_dir {
+        writeln!(
+            out,
+            "{} {} {} {}",
+            "✓".green().bold(),
+            "Copied".green(),
+            source,
+            format!(
+                "→ {} ({} copied, {} skipped)",
+                final_dest.display(),
+                copied,
+                skipped
+            )
+            .bright_black()
+        )?;
+    } else if copied > 0 {
+        writeln!(
+            out,
             "{} {} {} {}",
             "✓".green().bold(),
             "Copied".green(),
             source,
             format!("→ {}", final_dest.display()).bright_black()
-        );
-    } else if source_path.is_dir() {
-        // For now, don't support recursive copy in MVP
-        return Err(VshError::ExecutionError(
-            "Directory copy not yet implemented. Use -r flag (coming soon)".to_string(),
-        ));
+        )?;
     }
 
     Ok(())
 }
 
-/// Execute move command
-fn execute_move(cmd: &Command) -> Result<()> {
-    let source = cmd
-        .args
-        .source
-        .as_ref()
-        .ok_or_else(|| VshError::InvalidSyntax("Missing source".to_string()))?;
+/// Refuse to copy a file onto itself, or a directory into itself, which
+/// would truncate the file or never finish
+fn check_not_inside(source: &Path, dest: &Path, is_dir: bool) -> Result<()> {
+    let Ok(source_real) = fs::canonicalize(source) else {
+        return Ok(());
+    };
+    let dest_real = match fs::canonicalize(dest) {
+        Ok(real) => real,
+        Err(_) => match (dest.parent(), dest.file_name()) {
+            (Some(parent), Some(name)) => {
+                let parent = if parent.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    parent
+                };
+                match fs::canonicalize(parent) {
+                    Ok(parent) => parent.join(name),
+                    Err(_) => return Ok(()),
+                }
+            }
+            _ => return Ok(()),
+        },
+    };
+
+    if dest_real == source_real {
+        return Err(VshError::ExecutionError(format!(
+            "'{}' and '{}' are the same file",
+            source.display(),
+            dest.display()
+        )));
+    }
+    if is_dir && dest_real.starts_with(&source_real) {
+        return Err(VshError::ExecutionError(format!(
+            "Can't copy '{}' into itself",
+            source.display()
+        )));
+    }
+    Ok(())
+}
 
+/// Execute move command. Across filesystems, where a rename is impossible,
+/// the source is copied, checked, and only then removed.
+fn execute_move(cmd: &Command, out: &mut dyn Write) -> Result<()> {
     let dest = cmd
         .args
         .destination
         .as_ref()
         .ok_or_else(|| VshError::InvalidSyntax("Missing destination".to_string()))?;
 
+    let options = CopyOptions {
+        recursive: true,
+        ..CopyOptions::from_flags(&cmd.args.flags)?
+    };
+    check_destination(&cmd.args.sources, dest)?;
+
+    for_each_source(&cmd.args.sources, "moved", out, |source, out| {
+        move_one(source, dest, options, out)
+    })
+}
+
+/// Move one source to (or into) `dest`
+fn move_one(source: &str, dest: &str, options: CopyOptions, out: &mut dyn Write) -> Result<()> {
     let source_path = Path::new(source);
     let dest_path = Path::new(dest);
 
-    if !source_path.exists() {
-        return Err(VshError::FileNotFound(source.clone()));
-    }
+    let Ok(source_meta) = source_path.symlink_metadata() else {
+        return Err(VshError::FileNotFound(source.to_string()));
+    };
 
     // Determine final destination
     let final_dest = if dest_path.is_dir() {
@@ -108,141 +200,381 @@ fn execute_move(cmd: &Command) -> Result<()> {
         dest_path.to_path_buf()
     };
 
+    let mut copier = Copier::new(options, out);
+
+    // Replacing a file follows the same policy as copy
+    if fs::symlink_metadata(&final_dest).is_ok_and(|m| !m.is_dir()) {
+        if !copier.may_replace(source_path, &final_dest, &source_meta)? {
+            return Ok(());
+        }
+        copier.options.overwrite = Overwrite::Always;
+    }
+
     // Perform the move
-    fs::rename(source_path, &final_dest)?;
+    let copied = match fs::rename(source_path, &final_dest) {
+        Ok(()) => false,
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            copier.move_across_devices(source_path, &final_dest)?;
+            true
+        }
+        Err(e) => return Err(path_error(source_path, e)),
+    };
 
-    println!(
+    writeln!(
+        out,
         "{} {} {} {}",
         "✓".green().bold(),
         "Moved".green(),
         source,
         format!("→ {}", final_dest.display()).bright_black()
-    );
+    )?;
+    if copied {
+        writeln!(
+            out,
+            "  {}",
+            "(another filesystem: copied, verified, then removed the original)".bright_black()
+        )?;
+    }
 
     Ok(())
 }
 
-/// Execute remove command
-fn execute_remove(cmd: &Command) -> Result<()> {
-    let source = cmd
-        .args
-        .source
-        .as_ref()
-        .ok_or_else(|| VshError::InvalidSyntax("Missing file to remove".to_string()))?;
+/// Execute remove command. Answering no to the question fails with status 1,
+/// so `remove x && echo gone` says nothing.
+fn execute_remove(cmd: &Command, out: &mut dyn Write) -> Result<i32> {
+    let sources = &cmd.args.sources;
+    if sources.is_empty() {
+        return Err(VshError::InvalidSyntax(
+            "Missing file to remove".to_string(),
+        ));
+    }
 
-    let path = Path::new(source);
+    let existing: Vec<String> = sources
+        .iter()
+        .filter(|source| Path::new(source).symlink_metadata().is_ok())
+        .cloned()
+        .collect();
 
-    if !path.exists() {
-        return Err(VshError::FileNotFound(source.clone()));
+    // Forced (`-f`, `-rf`, `-fr`, `--force`): no question, and as with
+    // `rm -f` a missing file is nothing to do
+    let force = cmd
+        .args
+        .flags
+        .iter()
+        .any(|flag| matches!(flag.as_str(), "-f" | "-rf" | "-fr" | "--force"));
+    if force {
+        if !existing.is_empty() {
+            for_each_source(&existing, "removed", out, remove_one)?;
+        }
+        return Ok(0);
     }
 
-    // Safety check: ask for confirmation unless -f flag
-    let force = cmd.args.flags.contains(&"-f".to_string());
+    // Safety check: ask once for everything that exists
+    let names: Vec<String> = existing
+        .iter()
+        .map(|source| format!("'{}'", source))
+        .collect();
+    if !names.is_empty() && !confirm(&format!("Remove {}?", names.join(", ")))? {
+        writeln!(out, "{}", "Cancelled".bright_black())?;
+        return Ok(1);
+    }
 
-    if !force {
-        print!("{} Remove '{}'? [y/N]: ", "⚠".yellow(), source);
-        use std::io::{self, Write};
-        io::stdout().flush()?;
+    for_each_source(sources, "removed", out, remove_one)?;
+    Ok(0)
+}
 
-        let mut response = String::new();
-        io::stdin().read_line(&mut response)?;
+/// Remove one file, link or directory tree
+fn remove_one(source: &str, out: &mut dyn Write) -> Result<()> {
+    let path = Path::new(source);
 
-        if !response.trim().eq_ignore_ascii_case("y") {
-            println!("{}", "Cancelled".bright_black());
-            return Ok(());
-        }
-    }
+    let Ok(meta) = path.symlink_metadata() else {
+        return Err(VshError::FileNotFound(source.to_string()));
+    };
 
-    // Perform the removal
-    if path.is_file() {
-        fs::remove_file(path)?;
-    } else if path.is_dir() {
-        fs::remove_dir_all(path)?;
+    // Perform the removal (a link goes, not what it points to)
+    if meta.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
     }
+    .map_err(|e| path_error(path, e))?;
 
-    println!("{} {} {}", "✓".green().bold(), "Removed".green(), source);
+    writeln!(
+        out,
+        "{} {} {}",
+        "✓".green().bold(),
+        "Removed".green(),
+        source
+    )?;
 
     Ok(())
 }
 
-/// Execute list command
-fn execute_list(cmd: &Command) -> Result<()> {
-    let path = cmd.args.path.as_deref().unwrap_or(".");
+/// Execute make directory command. With both, `create directory notes in
+/// projects` makes `projects/notes`.
+fn execute_make_directory(cmd: &Command, out: &mut dyn Write) -> Result<()> {
+    let mut parents = false;
+    let mut mode = None;
+    for flag in &cmd.args.flags {
+        match flag.split_once('=') {
+            Some(("--mode", value)) => mode = Some(parse_mode(value)?),
+            None if flag == "--parents" || flag == "-p" => parents = true,
+            _ => {
+                return Err(VshError::InvalidSyntax(format!(
+                    "Unknown option '{}'",
+                    flag
+                )))
+            }
+        }
+    }
 
-    let dir_path = Path::new(path);
+    let dirs: Vec<String> = match &cmd.args.path {
+        Some(path) if cmd.args.sources.is_empty() => vec![path.clone()],
+        Some(path) => cmd
+            .args
+            .sources
+            .iter()
+            .map(|dir| Path::new(path).join(dir).to_string_lossy().into_owned())
+            .collect(),
+        None => cmd.args.sources.clone(),
+    };
 
-    if !dir_path.exists() {
-        return Err(VshError::FileNotFound(path.to_string()));
-    }
+    for_each_source(&dirs, "created", out, |dir, out| {
+        make_directory(dir, parents, mode, out)
+    })
+}
 
-    if !dir_path.is_dir() {
+/// Create one directory, and with `parents` any missing ones above it
+fn make_directory(dir: &str, parents: bool, mode: Option<u32>, out: &mut dyn Write) -> Result<()> {
+    let path = Path::new(dir);
+
+    if path.is_dir() && parents {
+        writeln!(out, "{} {} already exists", "✓".green().bold(), dir)?;
+        return Ok(());
+    }
+    if path.symlink_metadata().is_ok() {
         return Err(VshError::ExecutionError(format!(
-            "'{}' is not a directory",
-            path
+            "'{}' already exists",
+            dir
         )));
     }
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        if !parents && !parent.is_dir() {
+            return Err(VshError::ExecutionError(format!(
+                "'{}' does not exist; use 'mkdir -p {}' or 'create directory {} with parents'",
+                parent.display(),
+                dir,
+                dir
+            )));
+        }
+    }
 
-    let entries = fs::read_dir(dir_path)?;
+    fs::DirBuilder::new()
+        .recursive(parents)
+        .create(path)
+        .map_err(|e| path_error(path, e))?;
+    // Set the mode exactly, rather than as narrowed by the umask
+    if let Some(mode) = mode {
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))
+            .map_err(|e| path_error(path, e))?;
+    }
+
+    writeln!(
+        out,
+        "{} {} {}",
+        "✓".green().bold(),
+        "Created directory".green(),
+        dir
+    )?;
 
-    println!("{}", format!("Contents of {}:", path).bright_cyan());
-    println!();
+    Ok(())
+}
 
-    for entry in entries {
-        let entry = entry?;
-        let file_name = entry.file_name();
-        let file_name_str = file_name.to_string_lossy();
+/// An octal mode like `755` or `0700`
+fn parse_mode(value: &str) -> Result<u32> {
+    u32::from_str_radix(value, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+        .ok_or_else(|| {
+            VshError::InvalidSyntax(format!(
+                "Invalid mode '{}': use octal digits like 755",
+                value
+            ))
+        })
+}
 
-        let metadata = entry.metadata()?;
+/// Execute touch command: create each file, or bring its modification time
+/// up to now if it's already there
+fn execute_touch(cmd: &Command, out: &mut dyn Write) -> Result<()> {
+    if let Some(flag) = cmd.args.flags.first() {
+        return Err(VshError::InvalidSyntax(format!(
+            "Unknown option '{}'",
+            flag
+        )));
+    }
 
-        if metadata.is_dir() {
-            println!("  📁 {}/", file_name_str.bright_blue());
+    for_each_source(&cmd.args.sources, "touched", out, |file, out| {
+        let path = Path::new(file);
+        if path.symlink_metadata().is_ok() {
+            let now = TimeSpec::new(0, libc::UTIME_NOW);
+            utimensat(None, path, &now, &now, UtimensatFlags::FollowSymlink)
+                .map_err(|e| path_error(path, io::Error::from(e)))?;
+            writeln!(out, "{} {} {}", "✓".green().bold(), "Updated".green(), file)?;
         } else {
-            println!("  📄 {}", file_name_str);
+            fs::File::create(path).map_err(|e| path_error(path, e))?;
+            writeln!(
+                out,
+                "{} {} {}",
+                "✓".green().bold(),
+                "Created file".green(),
+                file
+            )?;
         }
-    }
+        Ok(())
+    })
+}
 
-    println!();
+/// Execute echo command. Leading `-n` leaves out the newline and `-e`
+/// turns escapes like `\n` and `\t` into the characters.
+fn execute_echo(words: &[String], out: &mut dyn Write) -> Result<()> {
+    let mut newline = true;
+    let mut escapes = false;
+    let mut words = words;
+
+    while let Some((flag, rest)) = words.split_first() {
+        let letters = flag.strip_prefix('-').unwrap_or_default();
+        if letters.is_empty() || !letters.chars().all(|c| matches!(c, 'n' | 'e' | 'E')) {
+            break;
+        }
+        for letter in letters.chars() {
+            match letter {
+                'n' => newline = false,
+                'e' => escapes = true,
+                _ => escapes = false,
+            }
+        }
+        words = rest;
+    }
 
+    let text = words.join(" ");
+    let text = if escapes { unescape(&text) } else { text };
+    write!(out, "{}{}", text, if newline { "\n" } else { "" })?;
     Ok(())
 }
 
-/// Execute change directory command
-fn execute_cd(cmd: &Command) -> Result<()> {
-    let path = cmd.args.path.as_deref().unwrap_or("~");
+/// Turn `\n`, `\t`, `\\` and the like into the characters they stand for
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
 
-    // Expand ~ to home directory
-    let expanded_path = if path.starts_with('~') {
-        if let Some(home) = dirs::home_dir() {
-            path.replacen('~', &home.to_string_lossy(), 1)
-        } else {
-            path.to_string()
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
         }
-    } else {
-        path.to_string()
-    };
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('e') => result.push('\x1b'),
+            Some('0') => result.push('\0'),
+            Some('\\') => result.push('\\'),
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            }
+            None => result.push('\\'),
+        }
+    }
 
-    let target_path = Path::new(&expanded_path);
+    result
+}
 
-    if !target_path.exists() {
-        return Err(VshError::FileNotFound(expanded_path.clone()));
+/// Several sources have to go into a directory
+fn check_destination(sources: &[String], dest: &str) -> Result<()> {
+    if sources.len() > 1 && !Path::new(dest).is_dir() {
+        return Err(VshError::ExecutionError(format!(
+            "'{}' is not a directory; several sources can only go into one",
+            dest
+        )));
     }
+    Ok(())
+}
 
-    if !target_path.is_dir() {
+/// Run `action` on each source. With one source its error is the command's
+/// error; with several, each failure is reported as it happens, the rest
+/// still run, and the command fails at the end if any did.
+fn for_each_source(
+    sources: &[String],
+    done: &str,
+    out: &mut dyn Write,
+    mut action: impl FnMut(&str, &mut dyn Write) -> Result<()>,
+) -> Result<()> {
+    if let [source] = sources {
+        return action(source, out);
+    }
+
+    let failed = sources
+        .iter()
+        .filter(|source| action(source, out).map_err(|e| report_error(&e)).is_err())
+        .count();
+
+    if failed > 0 {
         return Err(VshError::ExecutionError(format!(
-            "'{}' is not a directory",
-            expanded_path
+            "{} of {} items failed",
+            failed,
+            sources.len()
         )));
     }
+    writeln!(
+        out,
+        "{}",
+        format!("{} items {}", sources.len(), done).bright_black()
+    )?;
+    Ok(())
+}
 
-    // Change directory
-    std::env::set_current_dir(target_path)?;
+/// Ask a yes/no question; anything but "y" is a no. The prompt goes to
+/// stderr so it never ends up in a pipe or file.
+pub fn confirm(question: &str) -> Result<bool> {
+    eprint!("{} {} [y/N]: ", "⚠".yellow(), question);
+    io::stderr().flush()?;
 
-    println!(
-        "{} Changed directory to {}",
-        "✓".green().bold(),
-        target_path.display()
-    );
+    let mut response = String::new();
+    io::stdin().read_line(&mut response)?;
+
+    Ok(response.trim().eq_ignore_ascii_case("y"))
+}
 
+/// Execute list command
+fn execute_list(cmd: &Command, out: &mut dyn Write) -> Result<()> {
+    let path = cmd.args.path.as_deref().unwrap_or(".");
+    let options = ListOptions::from_flags(&cmd.args.flags)?;
+    list::list(path, &options, out)
+}
+
+/// Execute find command: every matching path below the starting ones, one
+/// a line, so the output can go through a pipe
+fn execute_find(cmd: &Command, out: &mut dyn Write) -> Result<()> {
+    let options = FindOptions::from_flags(&cmd.args.flags)?;
+    let mut roots: Vec<&str> = cmd
+        .args
+        .sources
+        .iter()
+        .chain(&cmd.args.path)
+        .map(|root| match root.as_str() {
+            "current-directory" | "here" => ".",
+            root => root,
+        })
+        .collect();
+    if roots.is_empty() {
+        roots.push(".");
+    }
+
+    let mut finder = Finder::new(options, out);
+    for root in roots {
+        finder.find(root)?;
+    }
     Ok(())
 }
 
@@ -265,16 +597,19 @@ mod tests {
         let cmd = Command {
             intent: Intent::Copy,
             args: CommandArgs {
-                source: Some(source_path.to_string_lossy().to_string()),
+                sources: vec![source_path.to_string_lossy().to_string()],
                 destination: Some(dest_path.to_string_lossy().to_string()),
                 path: None,
+                pattern: None,
                 flags: vec![],
                 extra: vec![],
             },
             syntax_used: SyntaxStyle::Terse,
+            redirects: vec![],
+            env: vec![],
         };
 
-        execute_copy(&cmd).unwrap();
+        execute_copy(&cmd, &mut std::io::sink()).unwrap();
 
         assert!(dest_path.exists());
     }
@@ -284,16 +619,52 @@ mod tests {
         let cmd = Command {
             intent: Intent::Copy,
             args: CommandArgs {
-                source: Some("nonexistent.txt".to_string()),
+                sources: vec!["nonexistent.txt".to_string()],
                 destination: Some("dest.txt".to_string()),
                 path: None,
+                pattern: None,
                 flags: vec![],
                 extra: vec![],
             },
             syntax_used: SyntaxStyle::Terse,
+            redirects: vec![],
+            env: vec![],
         };
 
-        let result = execute_copy(&cmd);
+        let result = execute_copy(&cmd, &mut std::io::sink());
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_echo() {
+        let echo = |words: &[&str]| {
+            let words: Vec<String> = words.iter().map(|w| w.to_string()).collect();
+            let mut out = Vec::new();
+            execute_echo(&words, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(echo(&["hello", "world"]), "hello world\n");
+        assert_eq!(echo(&["-n", "a"]), "a");
+        assert_eq!(echo(&["-e", "a\\tb\\\\"]), "a\tb\\\n");
+        assert_eq!(echo(&["-x", "a\\t"]), "-x a\\t\n");
+        assert_eq!(echo(&[]), "\n");
+    }
+
+    #[test]
+    fn test_make_directory() {
+        let dir = tempdir().unwrap();
+        let nested = dir.path().join("a/b");
+        let nested = nested.to_string_lossy();
+        let sink = &mut std::io::sink();
+
+        assert!(make_directory(&nested, false, None, sink).is_err());
+        make_directory(&nested, true, Some(0o700), sink).unwrap();
+        let mode = fs::metadata(&*nested).unwrap().permissions().mode();
+        assert_eq!(mode & 0o7777, 0o700);
+
+        // Already there: fine with parents, an error without
+        make_directory(&nested, true, None, sink).unwrap();
+        assert!(make_directory(&nested, false, None, sink).is_err());
+        assert!(parse_mode("789").is_err());
+    }
 }
@@ -0,0 +1,115 @@
+use crate::error::{Result, VshError};
+use crate::executor::value::binary;
+use crate::executor::Value;
+use crate::parser::{BinaryOp, Command, Intent};
+use std::fs;
+use std::path::Path;
+
+/// Evaluate a predicate: status 0 when it holds and 1 when it doesn't
+pub fn check(cmd: &Command) -> Result<i32> {
+    let args = &cmd.args.extra;
+
+    let holds = match &cmd.intent {
+        Intent::FileExists => Path::new(single_arg(args, "file-exists")?).is_file(),
+        Intent::DirectoryExists => Path::new(single_arg(args, "directory-exists")?).is_dir(),
+        Intent::IsEmpty => args.iter().all(|arg| is_empty(arg)),
+        Intent::Compare => match args.as_slice() {
+            [lhs, operator, rhs] => compare(lhs, operator, rhs)?,
+            _ => {
+                return Err(VshError::InvalidSyntax(
+                    "compare needs two values and an operator, like: compare $count > 10"
+                        .to_string(),
+                ))
+            }
+        },
+        intent => {
+            return Err(VshError::ExecutionError(format!(
+                "'{:?}' is not a condition",
+                intent
+            )))
+        }
+    };
+
+    Ok(if holds { 0 } else { 1 })
+}
+
+fn single_arg<'a>(args: &'a [String], name: &str) -> Result<&'a str> {
+    match args {
+        [arg] => Ok(arg),
+        _ => Err(VshError::InvalidSyntax(format!(
+            "{} needs exactly one path",
+            name
+        ))),
+    }
+}
+
+/// An empty file, an empty directory, or (for anything else) an empty string
+fn is_empty(value: &str) -> bool {
+    match fs::metadata(value) {
+        Ok(meta) if meta.is_dir() => fs::read_dir(value).is_ok_and(|mut d| d.next().is_none()),
+        Ok(meta) => meta.len() == 0,
+        Err(_) => value.is_empty(),
+    }
+}
+
+/// Compare two values by their types, as `$(( ))` does: numbers with
+/// numbers and text with text. Ordering a number against text is a type
+/// error; they are never equal.
+fn compare(lhs: &str, operator: &str, rhs: &str) -> Result<bool> {
+    let op = match operator {
+        "==" | "=" | "equals" => BinaryOp::Equal,
+        "!=" | "not-equals" => BinaryOp::NotEqual,
+        ">" | "greater-than" => BinaryOp::Greater,
+        "<" | "less-than" => BinaryOp::Less,
+        ">=" | "at-least" => BinaryOp::GreaterEqual,
+        "<=" | "at-most" => BinaryOp::LessEqual,
+        _ => {
+            return Err(VshError::InvalidSyntax(format!(
+                "'{}' is not a comparison",
+                operator
+            )))
+        }
+    };
+
+    let holds = binary(op, Value::infer(lhs), Value::infer(rhs))?;
+    Ok(holds == Value::Boolean(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_compare_numbers_and_text() {
+        assert!(compare("10", ">", "9").unwrap());
+        assert!(compare("10", "equals", "10.0").unwrap());
+        assert!(compare("apple", "<", "banana").unwrap());
+        assert!(matches!(
+            compare("10", ">", "9x"),
+            Err(VshError::TypeError(_))
+        ));
+        assert!(compare("abc", ">", "3")
+            .unwrap_err()
+            .to_string()
+            .contains("cannot compare string 'abc' with number 3"));
+        assert!(compare("abc", "!=", "3").unwrap());
+        assert!(compare("a", "!=", "b").unwrap());
+        assert!(compare("1", "~", "2").is_err());
+    }
+
+    #[test]
+    fn test_is_empty() {
+        let dir = tempdir().unwrap();
+        let dir_path = dir.path().to_string_lossy().to_string();
+        assert!(is_empty(&dir_path));
+
+        let file = dir.path().join("empty.txt");
+        fs::write(&file, "").unwrap();
+        assert!(is_empty(&file.to_string_lossy()));
+        assert!(!is_empty(&dir_path));
+
+        assert!(is_empty(""));
+        assert!(!is_empty("some text"));
+    }
+}
@@ -0,0 +1,212 @@
+use crate::error::{Result, VshError};
+use crate::executor::copy::path_error;
+use crate::executor::report_error;
+use crate::parser::{Command, Intent};
+use colored::*;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+/// How many lines `head` and `tail` show unless told otherwise
+const DEFAULT_LINES: usize = 10;
+
+/// Which lines of a file to show
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lines {
+    All,
+    First(usize),
+    Last(usize),
+    /// From this line (counting from 1) to the end, as in `tail -n +5`
+    From(usize),
+}
+
+impl Lines {
+    /// `10` for the first or last ten lines, `+5` to start at line five
+    fn parse(value: &str, last: bool) -> Result<Self> {
+        let invalid = || VshError::InvalidSyntax(format!("Invalid number of lines '{}'", value));
+        match value.strip_prefix('+') {
+            Some(start) if last => Ok(Lines::From(start.parse().map_err(|_| invalid())?)),
+            _ => {
+                let count = value.parse().map_err(|_| invalid())?;
+                Ok(if last {
+                    Lines::Last(count)
+                } else {
+                    Lines::First(count)
+                })
+            }
+        }
+    }
+}
+
+/// What `show`, `head` or `tail` prints, from the intent and its flags
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShowOptions {
+    pub lines: Lines,
+    /// Put line numbers in front
+    pub number: bool,
+}
+
+impl ShowOptions {
+    pub fn from_command(intent: &Intent, flags: &[String]) -> Result<Self> {
+        let tail = *intent == Intent::Tail;
+        let mut options = ShowOptions {
+            lines: match intent {
+                Intent::Head => Lines::First(DEFAULT_LINES),
+                Intent::Tail => Lines::Last(DEFAULT_LINES),
+                _ => Lines::All,
+            },
+            number: false,
+        };
+
+        for flag in flags {
+            match flag.split_once('=') {
+                Some(("--lines", value)) if *intent != Intent::Show => {
+                    options.lines = Lines::parse(value, tail)?
+                }
+                Some(("--first", value)) => options.lines = Lines::parse(value, false)?,
+                Some(("--last", value)) => options.lines = Lines::parse(value, true)?,
+                None if flag == "-n" || flag == "--number" => options.number = true,
+                // `head -5`
+                None if *intent != Intent::Show && flag[1..].parse::<usize>().is_ok() => {
+                    options.lines = Lines::parse(&flag[1..], tail)?
+                }
+                _ => {
+                    return Err(VshError::InvalidSyntax(format!(
+                        "Unknown option '{}'",
+                        flag
+                    )))
+                }
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Print files (or stdin, with none) one after the other. With several,
+/// `head` and `tail` put each file's name above its lines.
+pub fn execute_show(cmd: &Command, out: &mut dyn Write) -> Result<()> {
+    let options = ShowOptions::from_command(&cmd.intent, &cmd.args.flags)?;
+    let files = &cmd.args.sources;
+
+    if files.is_empty() {
+        return show(&mut io::stdin().lock(), &options, out);
+    }
+    if let [file] = files.as_slice() {
+        return show_file(file, &options, out);
+    }
+
+    let headers = options.lines != Lines::All;
+    let mut failed = 0;
+    for (i, file) in files.iter().enumerate() {
+        if headers {
+            let gap = if i > 0 { "\n" } else { "" };
+            writeln!(out, "{}{}", gap, format!("==> {} <==", file).bright_cyan())?;
+        }
+        if let Err(e) = show_file(file, &options, out) {
+            report_error(&e);
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        return Err(VshError::ExecutionError(format!(
+            "{} of {} files could not be read",
+            failed,
+            files.len()
+        )));
+    }
+    Ok(())
+}
+
+fn show_file(file: &str, options: &ShowOptions, out: &mut dyn Write) -> Result<()> {
+    let path = Path::new(file);
+    if path.is_dir() {
+        return Err(VshError::ExecutionError(format!(
+            "'{}' is a directory; use 'list {}' to see what's in it",
+            file, file
+        )));
+    }
+    let file = File::open(path).map_err(|e| path_error(path, e))?;
+    show(&mut BufReader::new(file), options, out)
+}
+
+/// Copy the chosen lines of `input` to `out`, byte for byte
+fn show(input: &mut dyn BufRead, options: &ShowOptions, out: &mut dyn Write) -> Result<()> {
+    if options.lines == Lines::All && !options.number {
+        io::copy(input, out)?;
+        return Ok(());
+    }
+
+    let mut kept = VecDeque::new();
+    let mut line = Vec::new();
+    let mut number = 0;
+
+    while input.read_until(b'\n', &mut line)? > 0 {
+        number += 1;
+        let line = std::mem::take(&mut line);
+        match options.lines {
+            Lines::First(count) if number > count => break,
+            Lines::From(start) if number < start => {}
+            Lines::Last(count) => {
+                kept.push_back((number, line));
+                if kept.len() > count {
+                    kept.pop_front();
+                }
+            }
+            _ => write_line(number, &line, options.number, out)?,
+        }
+    }
+
+    for (number, line) in kept {
+        write_line(number, &line, options.number, out)?;
+    }
+    Ok(())
+}
+
+fn write_line(number: usize, line: &[u8], numbered: bool, out: &mut dyn Write) -> Result<()> {
+    if numbered {
+        write!(out, "{:>6}  ", number.to_string().bright_black())?;
+    }
+    out.write_all(line)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(intent: Intent, flags: &[&str], text: &str) -> String {
+        colored::control::set_override(false);
+        let flags: Vec<String> = flags.iter().map(|f| f.to_string()).collect();
+        let options = ShowOptions::from_command(&intent, &flags).unwrap();
+        let mut out = Vec::new();
+        show(&mut text.as_bytes(), &options, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_first_and_last_lines() {
+        let text: String = (1..=12).map(|n| format!("{}\n", n)).collect();
+        assert_eq!(lines(Intent::Show, &[], &text), text);
+        assert_eq!(lines(Intent::Head, &["--lines=2"], &text), "1\n2\n");
+        assert_eq!(lines(Intent::Head, &["-3"], &text), "1\n2\n3\n");
+        assert_eq!(lines(Intent::Tail, &["--lines=2"], &text), "11\n12\n");
+        assert_eq!(lines(Intent::Tail, &["--lines=+11"], &text), "11\n12\n");
+        assert_eq!(lines(Intent::Tail, &[], &text).lines().count(), 10);
+        assert_eq!(lines(Intent::Show, &["--first=1"], &text), "1\n");
+        // A last line without a newline stays that way
+        assert_eq!(lines(Intent::Tail, &["--lines=1"], "a\nb"), "b");
+    }
+
+    #[test]
+    fn test_numbered_lines() {
+        assert_eq!(
+            lines(Intent::Show, &["-n"], "a\nb\n"),
+            "     1  a\n     2  b\n"
+        );
+        let flags = vec!["--lines=x".to_string()];
+        assert!(ShowOptions::from_command(&Intent::Head, &flags).is_err());
+    }
+}
@@ -0,0 +1,240 @@
+use crate::error::{Result, VshError};
+use crate::executor::expand::{expand_words, NoMatch};
+use crate::executor::{report_error, Shell, Value};
+use crate::parser::{ForLoop, IfBlock, Statement, Token, WhileLoop};
+use nix::sys::signal::Signal;
+
+/// Status of a command stopped with Ctrl-C, which also stops any loop
+const INTERRUPTED: i32 = 128 + Signal::SIGINT as i32;
+
+/// Most numbers a `for` range may count through, since the items are
+/// collected up front
+const MAX_RANGE: u64 = 1_000_000;
+
+/// A `break` or `continue` on its way out to the innermost loop, or a
+/// `return` on its way out of a function
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flow {
+    Break,
+    Continue,
+    Return(i32),
+    /// Functions nested too deeply; every call gives up
+    Unwind,
+}
+
+impl Shell {
+    /// Run statements in order and return the status of the last one. An
+    /// `exit` stops everything that follows, and a `break` or `continue`
+    /// everything up to the end of its loop's body.
+    pub fn execute_block(&mut self, statements: &[Statement]) -> i32 {
+        let mut status = 0;
+
+        for statement in statements {
+            status = self.execute_statement(statement);
+            self.last_status = status;
+
+            if let Some(exit_status) = self.exit_status {
+                return exit_status;
+            }
+            if self.flow.is_some() {
+                break;
+            }
+        }
+
+        status
+    }
+
+    fn execute_statement(&mut self, statement: &Statement) -> i32 {
+        match statement {
+            Statement::Commands(list) => self.execute_list(list),
+            Statement::If(block) => self.execute_if(block),
+            Statement::For(for_loop) => self.execute_for(for_loop),
+            Statement::While(while_loop) => self.execute_while(while_loop),
+            Statement::Function(function) => {
+                self.functions
+                    .insert(function.name.clone(), function.clone());
+                0
+            }
+        }
+    }
+
+    /// Run the body of the first branch whose condition succeeds, or the
+    /// `else` body. With no branch taken the status is 0, as in bash.
+    fn execute_if(&mut self, block: &IfBlock) -> i32 {
+        for (condition, body) in &block.branches {
+            let status = self.execute_list(condition);
+
+            if let Some(exit_status) = self.exit_status {
+                return exit_status;
+            }
+            if status == 0 {
+                return self.execute_block(body);
+            }
+        }
+
+        self.execute_block(&block.otherwise)
+    }
+
+    /// Run the body once per item, with the loop variable set to the item
+    fn execute_for(&mut self, for_loop: &ForLoop) -> i32 {
+        let items = match self.loop_items(&for_loop.items) {
+            Ok(items) => items,
+            Err(e) => {
+                report_error(&e);
+                return e.exit_code();
+            }
+        };
+        let mut status = 0;
+
+        self.loop_depth += 1;
+        for item in items {
+            self.set_variable(for_loop.variable.clone(), item);
+            status = self.execute_block(&for_loop.body);
+            if self.loop_should_stop(status) {
+                break;
+            }
+        }
+        self.loop_depth -= 1;
+
+        status
+    }
+
+    /// Run the body for as long as the condition succeeds
+    fn execute_while(&mut self, while_loop: &WhileLoop) -> i32 {
+        let mut status = 0;
+
+        self.loop_depth += 1;
+        loop {
+            let condition = self.execute_list(&while_loop.condition);
+            if condition != 0 || self.exit_status.is_some() || self.flow.is_some() {
+                break;
+            }
+
+            status = self.execute_block(&while_loop.body);
+            if self.loop_should_stop(status) {
+                break;
+            }
+        }
+        self.loop_depth -= 1;
+
+        status
+    }
+
+    /// Check, after one pass through a loop's body, whether the loop is over
+    fn loop_should_stop(&mut self, status: i32) -> bool {
+        match self.flow {
+            Some(Flow::Break) => {
+                self.flow = None;
+                true
+            }
+            Some(Flow::Continue) => {
+                self.flow = None;
+                false
+            }
+            // Leaving the function leaves the loop too
+            Some(Flow::Return(_) | Flow::Unwind) => true,
+            None => self.exit_status.is_some() || status == INTERRUPTED,
+        }
+    }
+
+    /// Expand the items of a `for` loop: `1..10` counts (both ends
+    /// included), `*.txt` becomes the matching files (none if nothing
+    /// matches), braces and `~` expand as in any command, and a variable
+    /// holding a list gives its items
+    fn loop_items(&self, items: &[Token]) -> Result<Vec<String>> {
+        let mut values = Vec::new();
+
+        for item in items {
+            if let Some(list) = self.list_variable(item) {
+                values.extend(list.iter().map(Value::to_string));
+                continue;
+            }
+
+            for word in self.expand_tokens(std::slice::from_ref(item))? {
+                if let Some(range) = (!word.is_quoted).then(|| range(&word.value)).flatten() {
+                    values.extend(range?);
+                } else {
+                    let words = expand_words(vec![word], NoMatch::Drop)?;
+                    values.extend(words.into_iter().map(|word| word.value));
+                }
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// The items of `$name` when the variable holds a list like `[1, 2]`
+    fn list_variable(&self, item: &Token) -> Option<Vec<Value>> {
+        if item.is_quoted || !item.value.starts_with('$') {
+            return None;
+        }
+        match Value::infer(&self.expand_string(item).ok()?) {
+            Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// The numbers of a range like `1..10` or `10..1`, both ends included;
+/// `None` if the word isn't a range
+fn range(word: &str) -> Option<Result<Vec<String>>> {
+    let (start, end) = word.split_once("..")?;
+    let (start, end): (i64, i64) = (start.parse().ok()?, end.parse().ok()?);
+
+    if start.abs_diff(end) >= MAX_RANGE {
+        return Some(Err(VshError::InvalidSyntax(format!(
+            "Range {} has more than {} numbers",
+            word, MAX_RANGE
+        ))));
+    }
+
+    let numbers: Vec<i64> = if start <= end {
+        (start..=end).collect()
+    } else {
+        (end..=start).rev().collect()
+    };
+    Some(Ok(numbers.iter().map(|n| n.to_string()).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranges() {
+        assert_eq!(range("1..3").unwrap().unwrap(), vec!["1", "2", "3"]);
+        assert_eq!(range("2..-1").unwrap().unwrap(), vec!["2", "1", "0", "-1"]);
+        assert!(range("a..b").is_none());
+        assert!(range("1.5").is_none());
+
+        assert_eq!(range("1..1000000").unwrap().unwrap().len(), 1_000_000);
+        for huge in [
+            "1..1000001",
+            "1..9999999999999",
+            "-9223372036854775808..9223372036854775807",
+        ] {
+            assert!(matches!(range(huge), Some(Err(VshError::InvalidSyntax(_)))));
+        }
+    }
+
+    #[test]
+    fn test_break_and_continue() {
+        let mut shell = Shell::new();
+        let source = "total = \"\"\n\
+                      for i in 1..10 do\n\
+                      \x20 if $i == 3 then continue\n\
+                      \x20 if $i > 5 then break\n\
+                      \x20 total = \"$total $i\"\n\
+                      end";
+        shell.run_source(source, "test.vsh").unwrap();
+        assert_eq!(shell.variables["total"], " 1 2 4 5");
+        assert_eq!(shell.variables["i"], "6");
+    }
+
+    #[test]
+    fn test_break_outside_a_loop_fails() {
+        let mut shell = Shell::new();
+        assert_eq!(shell.run_line("break").unwrap(), 3);
+        assert_eq!(shell.flow, None);
+    }
+}
@@ -0,0 +1,447 @@
+use crate::error::{Result, VshError};
+use crate::executor::builtin::confirm;
+use colored::*;
+use nix::sys::stat::{utimensat, UtimensatFlags};
+use nix::sys::time::TimeSpec;
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::{symlink, MetadataExt};
+use std::path::Path;
+
+/// What to do when a destination file already exists
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overwrite {
+    /// Ask before replacing each file (the default)
+    Ask,
+    Skip,
+    Always,
+    /// Only replace files older than the source
+    NewerOnly,
+}
+
+impl Overwrite {
+    fn parse(policy: &str) -> Result<Self> {
+        match policy {
+            "ask" => Ok(Overwrite::Ask),
+            "skip" | "never" => Ok(Overwrite::Skip),
+            "overwrite" | "always" => Ok(Overwrite::Always),
+            "newer-only" | "newer" => Ok(Overwrite::NewerOnly),
+            _ => Err(VshError::InvalidSyntax(format!(
+                "'{}' is not an overwrite policy (use ask, skip, overwrite or newer-only)",
+                policy
+            ))),
+        }
+    }
+}
+
+/// How a copy (or a move that has to copy) treats directories, symlinks and
+/// existing files
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CopyOptions {
+    pub recursive: bool,
+    /// Copy what symlinks point to instead of the links themselves
+    pub follow_links: bool,
+    pub overwrite: Overwrite,
+}
+
+impl CopyOptions {
+    /// Read options from a command's flags: `-r`/`--recursive`,
+    /// `-L`/`--follow-links`, `-P` (copy links), `-i` (ask), `-n` (skip),
+    /// `-f` (overwrite), `-u` (newer-only) and
+    /// `--overwrite=ask|skip|overwrite|newer-only`. Short flags combine, as
+    /// in `-rn`.
+    pub fn from_flags(flags: &[String]) -> Result<Self> {
+        let mut options = CopyOptions {
+            recursive: false,
+            follow_links: false,
+            overwrite: Overwrite::Ask,
+        };
+
+        for flag in flags {
+            if let Some(long) = flag.strip_prefix("--") {
+                match long.split_once('=') {
+                    Some(("overwrite", policy)) => options.overwrite = Overwrite::parse(policy)?,
+                    Some(_) => return Err(unknown_option(flag)),
+                    None => options.set(long, flag)?,
+                }
+            } else {
+                for short in flag.chars().skip(1) {
+                    options.set(&short.to_string(), flag)?;
+                }
+            }
+        }
+
+        Ok(options)
+    }
+
+    fn set(&mut self, option: &str, flag: &str) -> Result<()> {
+        match option {
+            "r" | "R" | "recursive" => self.recursive = true,
+            "L" | "follow-links" | "dereference" => self.follow_links = true,
+            "P" | "no-dereference" => self.follow_links = false,
+            "i" | "interactive" => self.overwrite = Overwrite::Ask,
+            "n" | "no-clobber" => self.overwrite = Overwrite::Skip,
+            "f" | "force" => self.overwrite = Overwrite::Always,
+            "u" | "update" => self.overwrite = Overwrite::NewerOnly,
+            _ => return Err(unknown_option(flag)),
+        }
+        Ok(())
+    }
+}
+
+fn unknown_option(flag: &str) -> VshError {
+    VshError::InvalidSyntax(format!("Unknown option '{}'", flag))
+}
+
+/// Copies files and whole trees, keeping permissions and modification times
+pub struct Copier<'a> {
+    pub options: CopyOptions,
+    out: &'a mut dyn Write,
+    /// Files and links written so far
+    pub copied: usize,
+    /// Existing files left alone because of the overwrite policy
+    pub skipped: usize,
+}
+
+impl<'a> Copier<'a> {
+    pub fn new(options: CopyOptions, out: &'a mut dyn Write) -> Self {
+        Self {
+            options,
+            out,
+            copied: 0,
+            skipped: 0,
+        }
+    }
+
+    /// Copy `source` to exactly `dest` (not into it). A directory is merged
+    /// into an existing directory of the same name.
+    pub fn copy(&mut self, source: &Path, dest: &Path) -> Result<()> {
+        let meta = if self.options.follow_links {
+            fs::metadata(source)
+        } else {
+            fs::symlink_metadata(source)
+        }
+        .map_err(|e| path_error(source, e))?;
+
+        if meta.is_dir() {
+            self.copy_dir(source, dest, &meta)
+        } else if meta.file_type().is_symlink() {
+            self.copy_link(source, dest)
+        } else {
+            self.copy_file(source, dest, &meta)
+        }
+    }
+
+    fn copy_dir(&mut self, source: &Path, dest: &Path, meta: &Metadata) -> Result<()> {
+        match fs::symlink_metadata(dest) {
+            Ok(existing) if existing.is_dir() => {}
+            Ok(_) => {
+                return Err(VshError::ExecutionError(format!(
+                    "Can't replace '{}' with a directory",
+                    dest.display()
+                )))
+            }
+            Err(_) => fs::create_dir(dest).map_err(|e| path_error(dest, e))?,
+        }
+
+        let mut entries = fs::read_dir(source)
+            .and_then(|entries| entries.collect::<io::Result<Vec<_>>>())
+            .map_err(|e| path_error(source, e))?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            self.copy(&entry.path(), &dest.join(entry.file_name()))?;
+        }
+
+        // Only now, so writing the contents neither fails on a read-only
+        // directory nor bumps its modification time
+        fs::set_permissions(dest, meta.permissions()).map_err(|e| path_error(dest, e))?;
+        copy_times(meta, dest)
+    }
+
+    fn copy_file(&mut self, source: &Path, dest: &Path, meta: &Metadata) -> Result<()> {
+        if !self.may_replace(source, dest, meta)? {
+            return Ok(());
+        }
+
+        // Copying onto a symlink would write to wherever it points
+        if fs::symlink_metadata(dest).is_ok_and(|m| m.file_type().is_symlink()) {
+            fs::remove_file(dest).map_err(|e| path_error(dest, e))?;
+        }
+
+        // fs::copy brings the permissions along
+        fs::copy(source, dest).map_err(|e| path_error(source, e))?;
+        copy_times(meta, dest)?;
+        self.copied += 1;
+        Ok(())
+    }
+
+    fn copy_link(&mut self, source: &Path, dest: &Path) -> Result<()> {
+        let target = fs::read_link(source).map_err(|e| path_error(source, e))?;
+        let meta = fs::symlink_metadata(source).map_err(|e| path_error(source, e))?;
+
+        if !self.may_replace(source, dest, &meta)? {
+            return Ok(());
+        }
+        match fs::symlink_metadata(dest) {
+            Ok(existing) if existing.is_dir() => {
+                return Err(VshError::ExecutionError(format!(
+                    "Can't replace directory '{}' with a link",
+                    dest.display()
+                )))
+            }
+            Ok(_) => fs::remove_file(dest).map_err(|e| path_error(dest, e))?,
+            Err(_) => {}
+        }
+
+        symlink(&target, dest).map_err(|e| path_error(dest, e))?;
+        copy_times(&meta, dest)?;
+        self.copied += 1;
+        Ok(())
+    }
+
+    /// Move `source` to `dest` on another filesystem, where a rename can't:
+    /// copy it, check the copy matches, then remove the original. The
+    /// original stays if anything was skipped or doesn't match.
+    pub fn move_across_devices(&mut self, source: &Path, dest: &Path) -> Result<()> {
+        self.copy(source, dest)?;
+
+        if self.skipped > 0 {
+            return Err(VshError::ExecutionError(format!(
+                "Kept '{}' because {} of its files were skipped",
+                source.display(),
+                self.skipped
+            )));
+        }
+        verify_copy(source, dest)?;
+
+        if fs::symlink_metadata(source).is_ok_and(|m| m.is_dir()) {
+            fs::remove_dir_all(source)
+        } else {
+            fs::remove_file(source)
+        }
+        .map_err(|e| path_error(source, e))
+    }
+
+    /// Check the overwrite policy for an existing destination, reporting
+    /// any file it leaves alone
+    pub fn may_replace(&mut self, source: &Path, dest: &Path, meta: &Metadata) -> Result<bool> {
+        let Ok(existing) = fs::symlink_metadata(dest) else {
+            return Ok(true);
+        };
+
+        let (replace, reason) = match self.options.overwrite {
+            Overwrite::Always => (true, ""),
+            Overwrite::Skip => (false, "already exists"),
+            Overwrite::NewerOnly => (modified(meta) > modified(&existing), "not newer"),
+            Overwrite::Ask => (
+                confirm(&format!("Overwrite '{}'?", dest.display()))?,
+                "kept",
+            ),
+        };
+
+        if !replace {
+            self.skipped += 1;
+            writeln!(
+                self.out,
+                "{} {} {}",
+                "-".bright_black(),
+                format!("Skipped {}", source.display()).bright_black(),
+                format!("({})", reason).bright_black()
+            )?;
+        }
+        Ok(replace)
+    }
+}
+
+/// Check that `dest` holds the same tree as `source`: the same files with
+/// the same contents, and links pointing to the same places
+fn verify_copy(source: &Path, dest: &Path) -> Result<()> {
+    let meta = fs::symlink_metadata(source).map_err(|e| path_error(source, e))?;
+
+    let matches = if meta.is_dir() {
+        let entries = fs::read_dir(source).map_err(|e| path_error(source, e))?;
+        for entry in entries {
+            let entry = entry?;
+            verify_copy(&entry.path(), &dest.join(entry.file_name()))?;
+        }
+        dest.is_dir()
+    } else if meta.file_type().is_symlink() {
+        fs::read_link(dest).ok() == Some(fs::read_link(source)?)
+    } else {
+        same_contents(source, dest).unwrap_or(false)
+    };
+
+    if matches {
+        Ok(())
+    } else {
+        Err(VshError::ExecutionError(format!(
+            "The copy at '{}' doesn't match '{}'; the original was kept",
+            dest.display(),
+            source.display()
+        )))
+    }
+}
+
+fn same_contents(a: &Path, b: &Path) -> io::Result<bool> {
+    let (mut a, mut b) = (File::open(a)?, File::open(b)?);
+    if a.metadata()?.len() != b.metadata()?.len() {
+        return Ok(false);
+    }
+
+    let (mut chunk_a, mut chunk_b) = (vec![0; 64 * 1024], vec![0; 64 * 1024]);
+    loop {
+        let n = a.read(&mut chunk_a)?;
+        if n == 0 {
+            return Ok(true);
+        }
+        b.read_exact(&mut chunk_b[..n])?;
+        if chunk_a[..n] != chunk_b[..n] {
+            return Ok(false);
+        }
+    }
+}
+
+/// A modification time precise enough to compare
+fn modified(meta: &Metadata) -> (i64, i64) {
+    (meta.mtime(), meta.mtime_nsec())
+}
+
+/// Give `dest` the access and modification times in `meta`, without
+/// following a symlink
+fn copy_times(meta: &Metadata, dest: &Path) -> Result<()> {
+    let accessed = TimeSpec::new(meta.atime(), meta.atime_nsec());
+    let modified = TimeSpec::new(meta.mtime(), meta.mtime_nsec());
+
+    utimensat(
+        None,
+        dest,
+        &accessed,
+        &modified,
+        UtimensatFlags::NoFollowSymlink,
+    )
+    .map_err(|e| path_error(dest, io::Error::from(e)))
+}
+
+/// Map an IO failure on `path` onto the friendliest error we have
+pub fn path_error(path: &Path, e: io::Error) -> VshError {
+    match e.kind() {
+        io::ErrorKind::NotFound => VshError::FileNotFound(path.display().to_string()),
+        io::ErrorKind::PermissionDenied => VshError::PermissionDenied(path.display().to_string()),
+        _ => VshError::IoError(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::tempdir;
+
+    fn flags(input: &[&str]) -> Vec<String> {
+        input.iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn test_options_from_flags() {
+        let options = CopyOptions::from_flags(&flags(&["-rn", "--follow-links"])).unwrap();
+        assert!(options.recursive);
+        assert!(options.follow_links);
+        assert_eq!(options.overwrite, Overwrite::Skip);
+
+        let options = CopyOptions::from_flags(&flags(&["--overwrite=newer-only"])).unwrap();
+        assert_eq!(options.overwrite, Overwrite::NewerOnly);
+        assert!(!options.recursive);
+
+        assert!(CopyOptions::from_flags(&flags(&["-x"])).is_err());
+        assert!(CopyOptions::from_flags(&flags(&["--overwrite=maybe"])).is_err());
+    }
+
+    #[test]
+    fn test_copy_tree_keeps_modes_times_and_links() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("source");
+        fs::create_dir_all(source.join("sub")).unwrap();
+        fs::write(source.join("sub/run.sh"), "echo hi").unwrap();
+        fs::set_permissions(source.join("sub/run.sh"), fs::Permissions::from_mode(0o750)).unwrap();
+        symlink("sub/run.sh", source.join("link")).unwrap();
+        let old = TimeSpec::new(1_000_000_000, 0);
+        utimensat(
+            None,
+            &source.join("sub"),
+            &old,
+            &old,
+            UtimensatFlags::FollowSymlink,
+        )
+        .unwrap();
+
+        let options = CopyOptions::from_flags(&flags(&["-r"])).unwrap();
+        let dest = dir.path().join("dest");
+        let mut out = Vec::new();
+        let mut copier = Copier::new(options, &mut out);
+        copier.copy(&source, &dest).unwrap();
+        assert_eq!(copier.copied, 2);
+
+        let script = fs::metadata(dest.join("sub/run.sh")).unwrap();
+        assert_eq!(script.permissions().mode() & 0o777, 0o750);
+        assert_eq!(
+            fs::metadata(dest.join("sub")).unwrap().mtime(),
+            1_000_000_000
+        );
+        assert_eq!(
+            fs::read_link(dest.join("link")).unwrap(),
+            Path::new("sub/run.sh")
+        );
+    }
+
+    #[test]
+    fn test_overwrite_policies() {
+        let dir = tempdir().unwrap();
+        let (source, dest) = (dir.path().join("new.txt"), dir.path().join("old.txt"));
+        fs::write(&source, "new").unwrap();
+        fs::write(&dest, "old").unwrap();
+        let old = TimeSpec::new(1_000_000_000, 0);
+        utimensat(None, &dest, &old, &old, UtimensatFlags::FollowSymlink).unwrap();
+
+        let copy_with = |flag: &str| {
+            let options = CopyOptions::from_flags(&flags(&[flag])).unwrap();
+            let mut out = Vec::new();
+            let mut copier = Copier::new(options, &mut out);
+            copier.copy(&source, &dest).unwrap();
+            (copier.copied, copier.skipped)
+        };
+
+        assert_eq!(copy_with("-n"), (0, 1));
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "old");
+        assert_eq!(copy_with("-u"), (1, 0));
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "new");
+        // Now both have the same time, so the copy is not newer
+        assert_eq!(copy_with("-u"), (0, 1));
+        assert_eq!(copy_with("-f"), (1, 0));
+    }
+
+    #[test]
+    fn test_move_across_devices_verifies_then_removes() {
+        let dir = tempdir().unwrap();
+        let source = dir.path().join("project");
+        fs::create_dir_all(source.join("src")).unwrap();
+        fs::write(source.join("src/main.rs"), "fn main() {}").unwrap();
+
+        let dest = dir.path().join("moved");
+        let options = CopyOptions::from_flags(&flags(&["-r"])).unwrap();
+        let mut out = Vec::new();
+        Copier::new(options, &mut out)
+            .move_across_devices(&source, &dest)
+            .unwrap();
+        assert!(!source.exists());
+        assert_eq!(
+            fs::read_to_string(dest.join("src/main.rs")).unwrap(),
+            "fn main() {}"
+        );
+
+        fs::write(dir.path().join("a.txt"), "aaa").unwrap();
+        fs::write(dir.path().join("b.txt"), "abc").unwrap();
+        assert!(!same_contents(&dir.path().join("a.txt"), &dir.path().join("b.txt")).unwrap());
+        assert!(verify_copy(&dir.path().join("a.txt"), &dir.path().join("b.txt")).is_err());
+    }
+}
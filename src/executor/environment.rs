@@ -0,0 +1,184 @@
+use crate::error::{Result, VshError};
+use crate::executor::Shell;
+use crate::parser::is_identifier;
+use std::env;
+use std::ffi::OsString;
+use std::io::Write;
+
+impl Shell {
+    /// Put variables in the environment every program started afterwards
+    /// inherits: `export NAME=value`, `export NAME as value`, or
+    /// `export NAME` for an existing shell variable. With no arguments, print
+    /// the environment.
+    pub(crate) fn export(&mut self, words: &[String], out: &mut dyn Write) -> Result<i32> {
+        if words.is_empty() {
+            return print_environment(out);
+        }
+
+        let mut words = words.iter().peekable();
+        while let Some(word) = words.next() {
+            let (name, value) = match word.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None if words.next_if(|w| *w == "as" || *w == "=").is_some() => {
+                    let value = words.next().ok_or_else(|| {
+                        VshError::InvalidSyntax(format!("Missing value for '{}'", word))
+                    })?;
+                    (word.as_str(), Some(value.clone()))
+                }
+                None => (word.as_str(), None),
+            };
+
+            check_name(name)?;
+
+            // An exported shell variable lives on in the environment only
+            let shell_value = self.variables.remove(name);
+            if let Some(value) = value.or(shell_value) {
+                env::set_var(name, value);
+            }
+        }
+
+        Ok(0)
+    }
+
+    /// Forget shell and environment variables
+    pub(crate) fn unset(&mut self, names: &[String]) -> Result<i32> {
+        if names.is_empty() {
+            return Err(VshError::InvalidSyntax(
+                "Missing variable to unset".to_string(),
+            ));
+        }
+
+        for name in names {
+            check_name(name)?;
+            // A local hides the global of the same name, which stays set
+            if let Some(frame) = self.locals.last_mut() {
+                if frame.remove(name).is_some() {
+                    continue;
+                }
+            }
+            self.variables.remove(name);
+            self.declared.remove(name);
+            env::remove_var(name);
+        }
+
+        Ok(0)
+    }
+}
+
+/// The `NAME=value` prefixes of a command run inside the shell, like a
+/// built-in or a function: they stay in the environment until dropped,
+/// when the previous values come back
+pub(crate) struct ScopedEnv {
+    saved: Vec<(String, Option<OsString>)>,
+}
+
+impl ScopedEnv {
+    pub(crate) fn set(vars: &[(String, String)]) -> Self {
+        let mut saved = Vec::with_capacity(vars.len());
+        for (name, value) in vars {
+            saved.push((name.clone(), env::var_os(name)));
+            env::set_var(name, value);
+        }
+        ScopedEnv { saved }
+    }
+}
+
+impl Drop for ScopedEnv {
+    fn drop(&mut self) {
+        for (name, value) in self.saved.drain(..).rev() {
+            match value {
+                Some(value) => env::set_var(name, value),
+                None => env::remove_var(name),
+            }
+        }
+    }
+}
+
+/// Print every environment variable as `NAME=value`, sorted by name
+pub(crate) fn print_environment(out: &mut dyn Write) -> Result<i32> {
+    let mut vars: Vec<_> = env::vars_os().collect();
+    vars.sort();
+
+    for (name, value) in vars {
+        writeln!(
+            out,
+            "{}={}",
+            name.to_string_lossy(),
+            value.to_string_lossy()
+        )?;
+    }
+
+    Ok(0)
+}
+
+fn check_name(name: &str) -> Result<()> {
+    if is_identifier(name) {
+        Ok(())
+    } else {
+        Err(VshError::InvalidSyntax(format!(
+            "'{}' is not a valid variable name",
+            name
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(input: &[&str]) -> Vec<String> {
+        input.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_export_forms() {
+        let mut shell = Shell::new();
+        shell
+            .variables
+            .insert("VSH_TEST_SHELL_VAR".to_string(), "moved".to_string());
+
+        let args = words(&[
+            "VSH_TEST_A=1",
+            "VSH_TEST_B",
+            "as",
+            "two words",
+            "VSH_TEST_SHELL_VAR",
+        ]);
+        shell.export(&args, &mut std::io::sink()).unwrap();
+
+        assert_eq!(env::var("VSH_TEST_A").unwrap(), "1");
+        assert_eq!(env::var("VSH_TEST_B").unwrap(), "two words");
+        assert_eq!(env::var("VSH_TEST_SHELL_VAR").unwrap(), "moved");
+        assert!(shell.variables.is_empty());
+
+        shell.unset(&words(&["VSH_TEST_A"])).unwrap();
+        assert!(env::var("VSH_TEST_A").is_err());
+    }
+
+    #[test]
+    fn test_scoped_env() {
+        env::set_var("VSH_TEST_SCOPED_OLD", "old");
+        let vars = [
+            ("VSH_TEST_SCOPED_OLD", "new"),
+            ("VSH_TEST_SCOPED_NEW", "1"),
+            ("VSH_TEST_SCOPED_NEW", "2"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+
+        let scoped = ScopedEnv::set(&vars);
+        assert_eq!(env::var("VSH_TEST_SCOPED_OLD").unwrap(), "new");
+        assert_eq!(env::var("VSH_TEST_SCOPED_NEW").unwrap(), "2");
+
+        drop(scoped);
+        assert_eq!(env::var("VSH_TEST_SCOPED_OLD").unwrap(), "old");
+        assert!(env::var("VSH_TEST_SCOPED_NEW").is_err());
+    }
+
+    #[test]
+    fn test_invalid_names() {
+        let mut shell = Shell::new();
+        let result = shell.export(&words(&["1BAD=x"]), &mut std::io::sink());
+        assert!(matches!(result, Err(VshError::InvalidSyntax(_))));
+        assert!(shell.unset(&[]).is_err());
+    }
+}
@@ -0,0 +1,516 @@
+use crate::error::{Result, VshError};
+use crate::executor::job::JobTable;
+use crate::executor::process::{self, ChildIo, ForegroundGuard};
+use crate::executor::{brace, glob, report_error, Shell};
+use crate::parser::{is_identifier, parse_expression, read_substitution, Quote, Token};
+use nix::unistd::User;
+use std::env;
+use std::io::Read;
+use std::iter::Peekable;
+use std::str::Chars;
+
+impl Shell {
+    /// Expand parameters, variables and commands (`$1`, `$@`, `$name`,
+    /// `${name}`, `{name}`, `$(command)`, ...) in every token. Single-quoted text is left alone, bare
+    /// results are split into words like bash does, and double-quoted
+    /// results stay part of a single word.
+    pub fn expand_tokens(&self, tokens: &[Token]) -> Result<Vec<Token>> {
+        let mut expanded = Vec::with_capacity(tokens.len());
+
+        for token in tokens {
+            // "$@" keeps every argument a separate word, quoted or not
+            if token.value == "$@" && token.parts.iter().all(|(_, q)| *q != Quote::Single) {
+                expanded.extend(self.positional.iter().map(|arg| Token::quoted(arg)));
+                continue;
+            }
+
+            self.expand_token(token, &mut expanded)?;
+        }
+
+        Ok(expanded)
+    }
+
+    /// Expand a word without splitting it, as for the value of an assignment
+    pub fn expand_string(&self, token: &Token) -> Result<String> {
+        token
+            .parts
+            .iter()
+            .map(|(text, quote)| match quote {
+                Quote::Single => Ok(text.clone()),
+                _ => Ok(self.expand_text(text)?.unwrap_or_else(|| text.clone())),
+            })
+            .collect()
+    }
+
+    /// Expand one word into `words`. Whitespace in a bare expansion splits
+    /// the word, and a word left with no text at all disappears.
+    fn expand_token(&self, token: &Token, words: &mut Vec<Token>) -> Result<()> {
+        let mut parts = Vec::with_capacity(token.parts.len());
+
+        for (text, quote) in &token.parts {
+            let expanded = match quote {
+                Quote::Single => None,
+                _ => self.expand_text(text)?,
+            };
+
+            match (expanded, quote) {
+                (Some(value), Quote::Bare) => {
+                    if value.starts_with(char::is_whitespace) {
+                        finish_word(words, &mut parts);
+                    }
+                    for (i, piece) in value.split_whitespace().enumerate() {
+                        if i > 0 {
+                            finish_word(words, &mut parts);
+                        }
+                        parts.push((piece.to_string(), Quote::Bare));
+                    }
+                    if value.ends_with(char::is_whitespace) {
+                        finish_word(words, &mut parts);
+                    }
+                }
+                (Some(value), quote) => parts.push((value, *quote)),
+                (None, quote) => parts.push((text.clone(), *quote)),
+            }
+        }
+
+        finish_word(words, &mut parts);
+        Ok(())
+    }
+
+    /// Expand the parameters in a piece of text, or None if it has none.
+    /// Only `$(( … ))` can fail: a bad expression stops the command.
+    fn expand_text(&self, text: &str) -> Result<Option<String>> {
+        if !text.contains('$') && !text.contains('{') {
+            return Ok(None);
+        }
+
+        let mut result = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            match ch {
+                '$' if chars.peek() == Some(&'(') => {
+                    let mut ahead = chars.clone();
+                    ahead.next();
+                    match read_substitution(&mut ahead) {
+                        Ok(command) => {
+                            match arithmetic(&command) {
+                                Some(expression) => {
+                                    let value = self.evaluate(&parse_expression(expression)?)?;
+                                    result.push_str(&value.to_string());
+                                }
+                                None => result.push_str(&self.command_output(&command)),
+                            }
+                            chars = ahead;
+                        }
+                        Err(_) => result.push('$'),
+                    }
+                }
+                '$' => match read_parameter_name(&mut chars) {
+                    Some(name) => result.push_str(&self.parameter(&name).unwrap_or_default()),
+                    None => result.push('$'),
+                },
+                // `{name}` only expands a variable that exists, so other
+                // braces pass through untouched
+                '{' => match self.braced_variable(&chars) {
+                    Some((value, length)) => {
+                        result.push_str(&value);
+                        chars.nth(length);
+                    }
+                    None => result.push('{'),
+                },
+                _ => result.push(ch),
+            }
+        }
+
+        Ok(Some(result))
+    }
+
+    /// Look up the `name}` following a `{`, returning its value and how many
+    /// characters to skip
+    fn braced_variable(&self, chars: &Peekable<Chars>) -> Option<(String, usize)> {
+        let name: String = chars.clone().take_while(|&c| c != '}').collect();
+        let closed = chars.clone().nth(name.chars().count()) == Some('}');
+        if !closed || !is_identifier(&name) {
+            return None;
+        }
+
+        self.variable(&name)
+            .map(|value| (value, name.chars().count()))
+    }
+
+    /// Run a command in a copy of the shell, as `$(command)` does, and
+    /// return what it printed without the trailing newlines. Built-ins,
+    /// functions and programs can all be captured; nothing the command does
+    /// to variables or the directory outlives it.
+    pub fn command_output(&self, command: &str) -> String {
+        self.capture(command).unwrap_or_else(|e| {
+            report_error(&e);
+            String::new()
+        })
+    }
+
+    fn capture(&self, command: &str) -> Result<String> {
+        let (mut reader, writer) = process::pipe()?;
+        let io = ChildIo {
+            stdout: Some(&writer),
+            ..ChildIo::default()
+        };
+        let pid = process::spawn(io, || {
+            colored::control::set_override(false);
+            let mut shell = self.clone();
+            // The jobs and the terminal stay with the real shell
+            shell.jobs = JobTable::default();
+            match shell.run_line(command) {
+                Ok(status) => shell.exit_status.unwrap_or(status),
+                Err(e) => {
+                    report_error(&e);
+                    e.exit_code()
+                }
+            }
+        })?;
+        drop(writer);
+
+        let _guard = ForegroundGuard::new();
+        let mut output = Vec::new();
+        let read = reader.read_to_end(&mut output);
+        process::wait_all(&[pid])?;
+        read?;
+
+        let mut text = String::from_utf8_lossy(&output).into_owned();
+        text.truncate(text.trim_end_matches('\n').len());
+        Ok(text)
+    }
+
+    /// Look up a parameter by name
+    pub fn parameter(&self, name: &str) -> Option<String> {
+        match name {
+            "0" => Some(self.script_name.clone()),
+            "#" => Some(self.positional.len().to_string()),
+            "?" => Some(self.last_status.to_string()),
+            "@" | "*" => Some(self.positional.join(" ")),
+            _ if is_identifier(name) => self.variable(name),
+            _ => {
+                let index: usize = name.parse().ok()?;
+                self.positional.get(index.checked_sub(1)?).cloned()
+            }
+        }
+    }
+
+    /// Look up a variable: the running function's locals first, then shell
+    /// variables, then the environment
+    pub fn variable(&self, name: &str) -> Option<String> {
+        self.locals
+            .last()
+            .and_then(|frame| frame.get(name))
+            .or_else(|| self.variables.get(name))
+            .cloned()
+            .or_else(|| env::var(name).ok())
+    }
+}
+
+/// What a glob that matches nothing turns into
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NoMatch {
+    /// Fail the command with a friendly message
+    #[default]
+    Error,
+    /// Keep the pattern as a literal word, like bash
+    PassThrough,
+    /// Nothing at all, as in a `for` loop
+    Drop,
+}
+
+impl NoMatch {
+    /// Read a setting: `error`, or `pass` to behave like bash
+    pub fn parse(setting: &str) -> Option<Self> {
+        match setting {
+            "error" => Some(NoMatch::Error),
+            "pass" | "pass-through" => Some(NoMatch::PassThrough),
+            _ => None,
+        }
+    }
+}
+
+/// Expand braces, a leading `~` and globs, in that order, in words whose
+/// variables are already expanded. Quoted text is never expanded.
+pub fn expand_words(tokens: Vec<Token>, no_match: NoMatch) -> Result<Vec<Token>> {
+    let mut words = Vec::with_capacity(tokens.len());
+
+    for token in tokens {
+        for word in brace::expand(&token)? {
+            let word = expand_tilde(word);
+
+            // `files=[a.txt, b.txt]` is a list, not a character class
+            let is_list = word
+                .key_value()
+                .is_some_and(|(_, v)| v.value.starts_with('['));
+            let paths = if is_list { None } else { glob::expand(&word) };
+
+            match paths {
+                None => words.push(word),
+                Some(paths) if !paths.is_empty() => {
+                    words.extend(paths.iter().map(|path| Token::quoted(path)))
+                }
+                Some(_) => match no_match {
+                    NoMatch::Error => {
+                        return Err(VshError::ExecutionError(format!(
+                            "No files match '{}'",
+                            word.value
+                        )))
+                    }
+                    NoMatch::PassThrough => words.push(word),
+                    NoMatch::Drop => {}
+                },
+            }
+        }
+    }
+
+    Ok(words)
+}
+
+/// Replace an unquoted `~` or `~user` at the start of a word, or right after
+/// the `=` of a named argument as in `dest=~/backups`, with the home
+/// directory. An unknown user is left alone.
+fn expand_tilde(word: Token) -> Token {
+    let Some((first, Quote::Bare)) = word.parts.first() else {
+        return word;
+    };
+
+    let start = match first.split_once('=') {
+        Some((key, value)) if is_identifier(key) && value.starts_with('~') => key.len() + 1,
+        _ if first.starts_with('~') => 0,
+        _ => return word,
+    };
+    let end = first[start..].find('/').map_or(first.len(), |i| start + i);
+
+    let user = &first[start + 1..end];
+    let home = if user.is_empty() {
+        dirs::home_dir()
+    } else {
+        User::from_name(user).ok().flatten().map(|user| user.dir)
+    };
+    let Some(home) = home else {
+        return word;
+    };
+
+    let mut parts = word.parts.clone();
+    parts[0].0 = format!("{}{}{}", &first[..start], home.display(), &first[end..]);
+    Token::from_parts(parts)
+}
+
+/// Push the pieces collected so far as a word, unless they hold no text and
+/// no quotes (an empty `""` is still a word)
+fn finish_word(words: &mut Vec<Token>, parts: &mut Vec<(String, Quote)>) {
+    let parts = std::mem::take(parts);
+    if parts
+        .iter()
+        .any(|(text, quote)| *quote != Quote::Bare || !text.is_empty())
+    {
+        words.push(Token::from_parts(parts));
+    }
+}
+
+/// The expression of `$(( … ))`, given the text inside `$( … )`: it must
+/// be wrapped in one pair of parentheses, so `$((a) | (b))` stays a command
+fn arithmetic(command: &str) -> Option<&str> {
+    let inner = command.strip_prefix('(')?.strip_suffix(')')?;
+    let mut depth = 0;
+    for c in inner.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return None,
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    Some(inner)
+}
+
+/// Read the parameter name following a `$`: `{name}`, a single digit or
+/// special character, or an identifier. Returns None for a lone `$`.
+fn read_parameter_name(chars: &mut Peekable<Chars>) -> Option<String> {
+    match *chars.peek()? {
+        '{' => {
+            // An unclosed or empty "${" is left alone
+            let rest: String = chars.clone().skip(1).take_while(|&c| c != '}').collect();
+            let closed = chars.clone().skip(1).any(|c| c == '}');
+            if !closed || rest.is_empty() {
+                return None;
+            }
+            // Consume '{', the name and '}'
+            chars.nth(rest.chars().count() + 1);
+            Some(rest)
+        }
+        c if c.is_ascii_digit() || matches!(c, '#' | '?' | '@' | '*') => {
+            chars.next();
+            Some(c.to_string())
+        }
+        c if c.is_ascii_alphabetic() || c == '_' => {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_') {
+                    break;
+                }
+                name.push(c);
+                chars.next();
+            }
+            Some(name)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tokenize;
+
+    fn token(value: &str, is_quoted: bool) -> Token {
+        if is_quoted {
+            Token::quoted(value)
+        } else {
+            Token::word(value)
+        }
+    }
+
+    fn shell_with_args(args: &[&str]) -> Shell {
+        let mut shell = Shell::new();
+        shell.script_name = "backup.vsh".to_string();
+        shell.positional = args.iter().map(|a| a.to_string()).collect();
+        shell
+    }
+
+    #[test]
+    fn test_positional_parameters() {
+        let shell = shell_with_args(&["a.txt", "backup/"]);
+        let tokens = vec![
+            token("copy", false),
+            token("$1", false),
+            token("to", false),
+            token("${2}", false),
+            token("$0 got $# args", true),
+        ];
+        let values: Vec<_> = shell
+            .expand_tokens(&tokens)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.value)
+            .collect();
+        assert_eq!(
+            values,
+            vec!["copy", "a.txt", "to", "backup/", "backup.vsh got 2 args"]
+        );
+    }
+
+    #[test]
+    fn test_all_arguments_stay_separate_words() {
+        let shell = shell_with_args(&["my file.txt", "other.txt"]);
+        let expanded = shell
+            .expand_tokens(&[token("rm", false), token("$@", true)])
+            .unwrap();
+        assert_eq!(expanded.len(), 3);
+        assert_eq!(expanded[1].value, "my file.txt");
+    }
+
+    #[test]
+    fn test_unquoted_expansion_is_split_and_missing_is_dropped() {
+        let shell = shell_with_args(&["one two"]);
+        let expanded = shell
+            .expand_tokens(&[token("$1", false), token("$3", false)])
+            .unwrap();
+        let values: Vec<_> = expanded.into_iter().map(|t| t.value).collect();
+        assert_eq!(values, vec!["one", "two"]);
+    }
+
+    #[test]
+    fn test_lone_dollar_is_literal() {
+        let shell = Shell::new();
+        let expanded = shell
+            .expand_tokens(&[token("costs $ or ${", true)])
+            .unwrap();
+        assert_eq!(expanded[0].value, "costs $ or ${");
+    }
+
+    #[test]
+    fn test_variables_respect_quoting() {
+        let mut shell = Shell::new();
+        shell
+            .variables
+            .insert("name".to_string(), "my file".to_string());
+        let tokens =
+            tokenize("echo $name \"${name}.txt\" '$name' \\$name {name} {other} x{name}").unwrap();
+        let values: Vec<_> = shell
+            .expand_tokens(&tokens)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.value)
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                "echo",
+                "my",
+                "file",
+                "my file.txt",
+                "$name",
+                "$name",
+                "my",
+                "file",
+                "{other}",
+                "xmy",
+                "file"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tilde() {
+        let home = dirs::home_dir().unwrap().display().to_string();
+        let values: Vec<_> = tokenize("~ ~/docs dest=~/b '~' x~ ~root ~no-such-user-vsh")
+            .unwrap()
+            .into_iter()
+            .map(|t| expand_tilde(t).value)
+            .collect();
+        assert_eq!(values[0], home);
+        assert_eq!(values[1], format!("{}/docs", home));
+        assert_eq!(values[2], format!("dest={}/b", home));
+        assert_eq!(values[3..5], ["~", "x~"]);
+        assert_eq!(values[5], "/root");
+        assert_eq!(values[6], "~no-such-user-vsh");
+    }
+
+    #[test]
+    fn test_no_match_policies() {
+        let pattern = || vec![Token::word("/no-such-dir-vsh/*.txt")];
+        assert!(expand_words(pattern(), NoMatch::Error).is_err());
+        assert_eq!(
+            expand_words(pattern(), NoMatch::PassThrough).unwrap().len(),
+            1
+        );
+        assert!(expand_words(pattern(), NoMatch::Drop).unwrap().is_empty());
+
+        let list = tokenize("files=[a.txt, b.txt]").unwrap();
+        assert_eq!(expand_words(list, NoMatch::Error).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let mut shell = shell_with_args(&["4"]);
+        shell.variables.insert("x".to_string(), "3".to_string());
+        let tokens = tokenize("echo $(($x + $1 * 2)) \"$(( (x + 1) / 8 ))\"").unwrap();
+        let values: Vec<_> = shell
+            .expand_tokens(&tokens)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.value)
+            .collect();
+        assert_eq!(values, vec!["echo", "11", "0.5"]);
+
+        assert_eq!(arithmetic("(1 + 2)"), Some("1 + 2"));
+        assert_eq!(arithmetic("(a) | (b)"), None);
+        assert!(shell
+            .expand_tokens(&tokenize("$((x + \"a\"))").unwrap())
+            .is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use crate::error::{Result, VshError};
+use crate::executor::process::{self, ChildIo};
+use crate::parser::{Command, Intent};
+use nix::unistd::{execve, Pid};
+use std::env;
+use std::ffi::{CString, OsStr, OsString};
+use std::os::unix::ffi::OsStringExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// Fork and exec an external program with the given streams, without waiting
+pub fn spawn_external(cmd: &Command, io: ChildIo) -> Result<Pid> {
+    let program = program_name(cmd)?;
+    let path =
+        find_program(program).ok_or_else(|| VshError::UnknownCommand(program.to_string()))?;
+
+    // Build everything the child needs before forking
+    let c_path = to_cstring(&path.to_string_lossy())?;
+    let mut argv = vec![to_cstring(program)?];
+    for arg in &cmd.args.extra {
+        argv.push(to_cstring(arg)?);
+    }
+    let envp = environment(&cmd.env)?;
+
+    process::spawn(io, || {
+        let _ = execve(&c_path, &argv, &envp);
+        // Only reached if exec failed
+        eprintln!("vsh: cannot execute '{}'", path.display());
+        126
+    })
+}
+
+/// The environment a program starts with: the shell's exported variables,
+/// plus any `NAME=value` prefixes of the command
+fn environment(overrides: &[(String, String)]) -> Result<Vec<CString>> {
+    let mut vars: Vec<(OsString, OsString)> = env::vars_os()
+        .filter(|(name, _)| !overrides.iter().any(|(n, _)| OsStr::new(n) == name))
+        .collect();
+    vars.extend(
+        overrides
+            .iter()
+            .map(|(name, value)| (name.into(), value.into())),
+    );
+
+    vars.into_iter()
+        .map(|(name, value)| {
+            let mut entry = name.into_vec();
+            entry.push(b'=');
+            entry.extend(value.into_vec());
+            CString::new(entry).map_err(|_| {
+                VshError::InvalidSyntax("Environment variables cannot contain NUL".to_string())
+            })
+        })
+        .collect()
+}
+
+/// Get the program name of an external command
+pub fn program_name(cmd: &Command) -> Result<&str> {
+    match &cmd.intent {
+        Intent::External(program) => Ok(program),
+        _ => Err(VshError::ExecutionError(
+            "Not an external command".to_string(),
+        )),
+    }
+}
+
+/// Locate a program on $PATH (names containing '/' are used as given)
+pub fn find_program(name: &str) -> Option<PathBuf> {
+    if name.contains('/') {
+        let path = PathBuf::from(name);
+        return is_executable(&path).then_some(path);
+    }
+
+    let path_var = env::var_os("PATH")?;
+    env::split_paths(&path_var)
+        .map(|dir| dir.join(name))
+        .find(|candidate| is_executable(candidate))
+}
+
+/// Check if a path is a regular file with any execute bit set
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+fn to_cstring(s: &str) -> Result<CString> {
+    CString::new(s)
+        .map_err(|_| VshError::InvalidSyntax(format!("Argument contains a NUL byte: {:?}", s)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_program_on_path() {
+        let path = find_program("sh").unwrap();
+        assert!(path.ends_with("sh"));
+    }
+
+    #[test]
+    fn test_find_missing_program() {
+        assert!(find_program("definitely-not-a-real-program-vsh").is_none());
+    }
+
+    #[test]
+    fn test_find_program_with_slash() {
+        assert!(find_program("/bin/sh").is_some());
+        assert!(find_program("./no/such/program").is_none());
+    }
+}
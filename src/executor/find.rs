@@ -0,0 +1,531 @@
+use crate::error::{Result, VshError};
+use crate::executor::copy::path_error;
+use crate::executor::glob::matches_name;
+use crate::executor::report_error;
+use std::fs::{self, Metadata};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// The kinds of entry `find` can be limited to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    File,
+    Directory,
+    Link,
+}
+
+impl Kind {
+    fn parse(word: &str) -> Result<Self> {
+        match word {
+            "f" | "file" | "files" => Ok(Kind::File),
+            "d" | "dir" | "directory" | "directories" => Ok(Kind::Directory),
+            "l" | "link" | "links" | "symlink" => Ok(Kind::Link),
+            _ => Err(VshError::InvalidSyntax(format!(
+                "Unknown type '{}' (use file, directory or link)",
+                word
+            ))),
+        }
+    }
+}
+
+/// What `find` looks for, from its flags
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FindOptions {
+    /// A wildcard pattern for the name, and whether case matters
+    pub name: Option<(String, bool)>,
+    pub kind: Option<Kind>,
+    /// Sizes in bytes, both ends included; directories never match these
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub newer_than: Option<SystemTime>,
+    pub older_than: Option<SystemTime>,
+    /// How many directories down to start and stop; 1 is what's in a
+    /// starting directory
+    pub min_depth: usize,
+    pub max_depth: Option<usize>,
+    /// Include hidden entries
+    pub hidden: bool,
+    /// Include what `.gitignore` files leave out
+    pub no_ignore: bool,
+}
+
+impl FindOptions {
+    pub fn from_flags(flags: &[String]) -> Result<Self> {
+        let mut options = FindOptions {
+            min_depth: 1,
+            ..Default::default()
+        };
+
+        for flag in flags {
+            let Some((option, value)) = flag.trim_start_matches('-').split_once('=') else {
+                match flag.as_str() {
+                    "--hidden" | "--all" | "-a" => options.hidden = true,
+                    "--no-ignore" | "--ignored" => options.no_ignore = true,
+                    _ => return Err(unknown_option(flag)),
+                }
+                continue;
+            };
+
+            match option {
+                "name" => options.name = Some((value.to_string(), true)),
+                "iname" => options.name = Some((value.to_lowercase(), false)),
+                "type" => options.kind = Some(Kind::parse(value)?),
+                "size" => options.add_size(value)?,
+                "modified" => options.add_age(value)?,
+                "min-depth" => options.min_depth = parse_depth(value)?,
+                "max-depth" => options.max_depth = Some(parse_depth(value)?),
+                _ => return Err(unknown_option(flag)),
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// `+1M` (more than), `-10k` (less than), `1k..1M` (from, to) or `2M`
+    /// (rounded up to whole units, as `find -size` does)
+    fn add_size(&mut self, value: &str) -> Result<()> {
+        if let Some((low, high)) = value.split_once("..") {
+            self.min_size = Some(parse_size(low)?.0);
+            self.max_size = Some(parse_size(high)?.0);
+        } else if let Some(size) = value.strip_prefix('+') {
+            self.min_size = Some(parse_size(size)?.0.saturating_add(1));
+        } else if let Some(size) = value.strip_prefix('-') {
+            self.max_size = Some(parse_size(size)?.0.saturating_sub(1));
+        } else {
+            let (size, unit) = parse_size(value)?;
+            self.min_size = Some(size.saturating_sub(unit) + u64::from(size > 0));
+            self.max_size = Some(size);
+        }
+        Ok(())
+    }
+
+    /// `-2d` (modified in the last two days, also just `2d`) or `+2d`
+    /// (longer ago than that)
+    fn add_age(&mut self, value: &str) -> Result<()> {
+        let (older, age) = match value.strip_prefix('+') {
+            Some(age) => (true, age),
+            None => (false, value.trim_start_matches('-')),
+        };
+        let since = SystemTime::now()
+            .checked_sub(parse_age(age)?)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        if older {
+            self.older_than = Some(since);
+        } else {
+            self.newer_than = Some(since);
+        }
+        Ok(())
+    }
+
+    /// Check an entry against everything but its depth
+    fn matches(&self, name: &str, meta: &Metadata) -> bool {
+        let kind = if meta.file_type().is_symlink() {
+            Kind::Link
+        } else if meta.is_dir() {
+            Kind::Directory
+        } else {
+            Kind::File
+        };
+
+        let name_matches = match &self.name {
+            Some((pattern, true)) => matches_name(pattern, name),
+            Some((pattern, false)) => matches_name(pattern, &name.to_lowercase()),
+            None => true,
+        };
+        let size_matches = (self.min_size.is_none() && self.max_size.is_none())
+            || (kind != Kind::Directory
+                && self.min_size.is_none_or(|min| meta.len() >= min)
+                && self.max_size.is_none_or(|max| meta.len() <= max));
+        let modified = meta.modified().ok();
+        let age_matches = self
+            .newer_than
+            .is_none_or(|since| modified.is_some_and(|time| time >= since))
+            && self
+                .older_than
+                .is_none_or(|since| modified.is_some_and(|time| time < since));
+
+        name_matches && self.kind.is_none_or(|k| k == kind) && size_matches && age_matches
+    }
+
+    /// Hidden entries are searched when asked for, or when the name pattern
+    /// itself starts with a dot
+    fn searches_hidden(&self) -> bool {
+        self.hidden || self.name.as_ref().is_some_and(|(p, _)| p.starts_with('.'))
+    }
+}
+
+fn unknown_option(flag: &str) -> VshError {
+    VshError::InvalidSyntax(format!("Unknown option '{}'", flag))
+}
+
+fn parse_depth(value: &str) -> Result<usize> {
+    value
+        .parse()
+        .map_err(|_| VshError::InvalidSyntax(format!("Invalid depth '{}'", value)))
+}
+
+/// A size like `512`, `10k`, `1.5M` or `2G`, in bytes, with its unit
+fn parse_size(value: &str) -> Result<(u64, u64)> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let unit = match unit.to_lowercase().as_str() {
+        "" | "b" | "c" => 1,
+        "k" | "kb" => 1 << 10,
+        "m" | "mb" => 1 << 20,
+        "g" | "gb" => 1 << 30,
+        "t" | "tb" => 1 << 40,
+        _ => 0,
+    };
+    // Anything from 2^64 bytes up doesn't fit
+    let bytes = number
+        .parse::<f64>()
+        .map(|number| (number * unit as f64).ceil());
+    match bytes {
+        Ok(bytes) if unit > 0 && bytes < u64::MAX as f64 => Ok((bytes as u64, unit)),
+        _ => Err(VshError::InvalidSyntax(format!(
+            "Invalid size '{}' (try 10k, 1.5M or 2G)",
+            value
+        ))),
+    }
+}
+
+/// An age like `30m`, `2h`, `3d` or `1w`; a bare number is days
+fn parse_age(value: &str) -> Result<Duration> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "" | "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => 0,
+    };
+    let total = number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(seconds));
+    match total {
+        Some(total) if seconds > 0 => Ok(Duration::from_secs(total)),
+        _ => Err(VshError::InvalidSyntax(format!(
+            "Invalid age '{}' (try 30m, 2h, 3d or 1w)",
+            value
+        ))),
+    }
+}
+
+/// One line of a `.gitignore` file
+#[derive(Debug)]
+struct IgnoreRule {
+    /// The directory holding the `.gitignore`
+    base: PathBuf,
+    /// The pattern split at slashes
+    components: Vec<String>,
+    /// Matched against the whole path below `base`, not just the name
+    anchored: bool,
+    dir_only: bool,
+    negated: bool,
+}
+
+impl IgnoreRule {
+    fn parse(base: &Path, line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let components: Vec<String> = line
+            .trim_start_matches('/')
+            .split('/')
+            .map(str::to_string)
+            .collect();
+
+        Some(IgnoreRule {
+            base: base.to_path_buf(),
+            components,
+            anchored,
+            dir_only,
+            negated,
+        })
+    }
+
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let Ok(relative) = path.strip_prefix(&self.base) else {
+            return false;
+        };
+        let names: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+
+        if self.anchored {
+            matches_components(&self.components, &names)
+        } else {
+            names
+                .last()
+                .is_some_and(|name| matches_name(&self.components[0], name))
+        }
+    }
+}
+
+/// Match path components against pattern components, where `**` stands
+/// for any number of directories
+fn matches_components(pattern: &[String], names: &[String]) -> bool {
+    match pattern.split_first() {
+        None => names.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=names.len()).any(|skip| matches_components(rest, &names[skip..]))
+        }
+        Some((first, rest)) => names.split_first().is_some_and(|(name, names)| {
+            matches_name(first, name) && matches_components(rest, names)
+        }),
+    }
+}
+
+/// Walks directories and prints what matches, one path a line
+pub struct Finder<'a> {
+    options: FindOptions,
+    /// Rules from the `.gitignore` files of the directories being walked
+    rules: Vec<IgnoreRule>,
+    out: &'a mut dyn Write,
+    /// Directories that couldn't be read
+    unreadable: usize,
+}
+
+impl<'a> Finder<'a> {
+    pub fn new(options: FindOptions, out: &'a mut dyn Write) -> Self {
+        Finder {
+            options,
+            rules: Vec::new(),
+            out,
+            unreadable: 0,
+        }
+    }
+
+    /// Search below one starting path (or check the path itself, if it's
+    /// not a directory)
+    pub fn find(&mut self, root: &str) -> Result<()> {
+        let path = Path::new(root);
+        let meta = path
+            .symlink_metadata()
+            .map_err(|_| VshError::FileNotFound(root.to_string()))?;
+
+        if !meta.is_dir() {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if self.options.matches(&name, &meta) {
+                writeln!(self.out, "{}", root)?;
+            }
+            return Ok(());
+        }
+
+        let absolute = path.canonicalize().map_err(|e| path_error(path, e))?;
+        self.rules.clear();
+        if !self.options.no_ignore {
+            self.load_repository_rules(&absolute);
+        }
+        self.walk(path, &absolute, 1);
+
+        if self.unreadable > 0 {
+            return Err(VshError::ExecutionError(format!(
+                "{} directories could not be read",
+                std::mem::take(&mut self.unreadable)
+            )));
+        }
+        Ok(())
+    }
+
+    /// Rules from the directories above `dir`, up to the top of its git
+    /// repository; outside a repository, only what's below counts
+    fn load_repository_rules(&mut self, dir: &Path) {
+        let Some(top) = dir.ancestors().skip(1).find(|d| d.join(".git").exists()) else {
+            return;
+        };
+        let mut above: Vec<&Path> = dir
+            .ancestors()
+            .skip(1)
+            .take_while(|d| d.starts_with(top))
+            .collect();
+        above.reverse();
+        for parent in above {
+            self.load_rules(parent);
+        }
+    }
+
+    fn load_rules(&mut self, dir: &Path) {
+        if let Ok(text) = fs::read_to_string(dir.join(".gitignore")) {
+            self.rules
+                .extend(text.lines().filter_map(|line| IgnoreRule::parse(dir, line)));
+        }
+    }
+
+    /// Later rules win, so a `!pattern` can take back an earlier one
+    fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(path, is_dir))
+            .is_some_and(|rule| !rule.negated)
+    }
+
+    fn walk(&mut self, dir: &Path, absolute: &Path, depth: usize) {
+        let loaded = self.rules.len();
+        if !self.options.no_ignore {
+            self.load_rules(absolute);
+        }
+
+        let mut entries: Vec<_> = match fs::read_dir(dir) {
+            Ok(entries) => entries.flatten().collect(),
+            Err(e) => {
+                report_error(&path_error(dir, e));
+                self.unreadable += 1;
+                return;
+            }
+        };
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') && !self.options.searches_hidden() || name == ".git" {
+                continue;
+            }
+            let Ok(meta) = entry.path().symlink_metadata() else {
+                continue;
+            };
+            // Links to directories aren't followed, so there are no loops
+            let is_dir = meta.is_dir();
+            let absolute = absolute.join(&name);
+            if !self.options.no_ignore && self.is_ignored(&absolute, is_dir) {
+                continue;
+            }
+
+            let path = if dir == Path::new(".") {
+                PathBuf::from(&name)
+            } else {
+                dir.join(&name)
+            };
+            if depth >= self.options.min_depth && self.options.matches(&name, &meta) {
+                // A closed pipe just means nobody wants the rest
+                if writeln!(self.out, "{}", path.display()).is_err() {
+                    break;
+                }
+            }
+            if is_dir && self.options.max_depth.is_none_or(|max| depth < max) {
+                self.walk(&path, &absolute, depth + 1);
+            }
+        }
+
+        self.rules.truncate(loaded);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn find(root: &Path, flags: &[&str]) -> Vec<String> {
+        let flags: Vec<String> = flags.iter().map(|f| f.to_string()).collect();
+        let mut out = Vec::new();
+        let mut finder = Finder::new(FindOptions::from_flags(&flags).unwrap(), &mut out);
+        finder.find(&root.to_string_lossy()).unwrap();
+        let prefix = format!("{}/", root.display());
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| line.trim_start_matches(&prefix).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_sizes_and_ages() {
+        assert_eq!(parse_size("10k").unwrap(), (10240, 1024));
+        assert_eq!(parse_size("1.5M").unwrap().0, 1572864);
+        assert!(parse_size("10q").is_err());
+        assert_eq!(parse_age("2h").unwrap(), Duration::from_secs(7200));
+        assert!(parse_age("2 days").is_err());
+
+        let options = FindOptions::from_flags(&["--size=1k..2k".to_string()]).unwrap();
+        assert_eq!(
+            (options.min_size, options.max_size),
+            (Some(1024), Some(2048))
+        );
+        assert!(FindOptions::from_flags(&["--colour".to_string()]).is_err());
+
+        // Too big to count is an error, not an overflow
+        for flag in [
+            "--size=+9999999999999999T",
+            "--size=99999999999999999G",
+            "--modified=-99999999999999999",
+            "--modified=999999999999999w",
+        ] {
+            let error = FindOptions::from_flags(&[flag.to_string()]).unwrap_err();
+            assert!(error.to_string().contains("Invalid"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_filters() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/deep")).unwrap();
+        fs::write(dir.path().join("notes.txt"), "a".repeat(2000)).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.path().join("src/deep/more.TXT"), "").unwrap();
+        fs::write(dir.path().join(".hidden.txt"), "").unwrap();
+
+        assert_eq!(find(dir.path(), &["--name=*.txt"]), vec!["notes.txt"]);
+        assert_eq!(
+            find(dir.path(), &["--iname=*.txt"]),
+            vec!["notes.txt", "src/deep/more.TXT"]
+        );
+        assert_eq!(find(dir.path(), &["--type=d"]), vec!["src", "src/deep"]);
+        assert_eq!(find(dir.path(), &["--size=+1k"]), vec!["notes.txt"]);
+        assert_eq!(
+            find(dir.path(), &["--max-depth=1", "--type=file"]),
+            vec!["notes.txt"]
+        );
+        assert_eq!(find(dir.path(), &["--name=.*"]), vec![".hidden.txt"]);
+        assert!(find(dir.path(), &["--modified=+1d"]).is_empty());
+        assert_eq!(
+            find(dir.path(), &["--modified=-1h", "--min-depth=3"]).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_gitignore() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+        fs::create_dir_all(dir.path().join("target/debug")).unwrap();
+        fs::create_dir_all(dir.path().join("src/generated")).unwrap();
+        fs::write(dir.path().join(".gitignore"), "target/\n*.log\n!keep.log\n").unwrap();
+        fs::write(dir.path().join("src/.gitignore"), "/generated\n").unwrap();
+        for file in ["a.log", "keep.log", "src/lib.rs", "src/generated/x.rs"] {
+            fs::write(dir.path().join(file), "").unwrap();
+        }
+
+        assert_eq!(
+            find(dir.path(), &["--type=file"]),
+            vec!["keep.log", "src/lib.rs"]
+        );
+        // Starting inside the repository still uses the rules above
+        let src = dir.path().join("src");
+        assert_eq!(find(&src, &["--name=*.rs"]), vec!["lib.rs"]);
+        assert_eq!(find(dir.path(), &["--no-ignore", "--name=*.log"]).len(), 2);
+    }
+}
@@ -0,0 +1,153 @@
+use crate::error::{Result, VshError};
+use crate::executor::control::Flow;
+use crate::executor::Shell;
+use crate::parser::Assignment;
+use std::collections::HashMap;
+
+/// How deeply functions may call each other before the shell gives up,
+/// well before the process itself would run out of stack
+const MAX_CALL_DEPTH: usize = 200;
+
+impl Shell {
+    /// Run a user-defined function. Its parameters (and `$1`, `$2`, …) are
+    /// bound to `args` for the length of the call; `return` ends it early.
+    pub(crate) fn call_function(&mut self, name: &str, args: &[String]) -> Result<i32> {
+        let function = self
+            .functions
+            .get(name)
+            .cloned()
+            .ok_or_else(|| VshError::UnknownCommand(name.to_string()))?;
+
+        if self.locals.len() >= MAX_CALL_DEPTH {
+            // Every call still running gives up, not just this one
+            self.flow = Some(Flow::Unwind);
+            return Err(VshError::ExecutionError(format!(
+                "'{}' nested function calls more than {} deep; stopping",
+                name, MAX_CALL_DEPTH
+            )));
+        }
+
+        let frame: HashMap<String, String> = function
+            .params
+            .iter()
+            .enumerate()
+            .map(|(i, param)| (param.clone(), args.get(i).cloned().unwrap_or_default()))
+            .collect();
+
+        let positional = std::mem::replace(&mut self.positional, args.to_vec());
+        // Loops around the call can't be broken out of from inside it
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        self.locals.push(frame);
+        self.return_value = None;
+
+        let status = self.execute_block(&function.body);
+
+        self.locals.pop();
+        self.loop_depth = loop_depth;
+        self.positional = positional;
+
+        Ok(match self.flow {
+            Some(Flow::Return(status)) => {
+                self.flow = None;
+                status
+            }
+            Some(Flow::Unwind) => {
+                if self.locals.is_empty() {
+                    self.flow = None;
+                }
+                1
+            }
+            _ => status,
+        })
+    }
+
+    /// `return`, `return 1` or `return some value`. A number from 0 to 255
+    /// is also the status, as in bash; anything else returns status 0.
+    pub(crate) fn return_from_function(&mut self, words: &[String]) -> Result<i32> {
+        if self.locals.is_empty() {
+            return Err(VshError::InvalidSyntax(
+                "'return' only works inside a function".to_string(),
+            ));
+        }
+
+        let status = match words.first() {
+            Some(first) => {
+                self.return_value = Some(words.join(" "));
+                match first.parse::<i32>() {
+                    Ok(n) if words.len() == 1 && (0..=255).contains(&n) => n,
+                    _ => 0,
+                }
+            }
+            None => 0,
+        };
+
+        self.flow = Some(Flow::Return(status));
+        Ok(status)
+    }
+
+    /// `result = add 5 3`: when an assignment's value starts with a function
+    /// name, call it and store what it returned. Returns None for an
+    /// ordinary assignment.
+    pub(crate) fn assign_call(&mut self, assignment: &Assignment) -> Option<Result<i32>> {
+        let first = assignment.value.first()?;
+        if first.is_quoted || !self.functions.contains_key(&first.value) {
+            return None;
+        }
+
+        let args: Vec<String> = match self.expand_tokens(&assignment.value[1..]) {
+            Ok(args) => args.into_iter().map(|token| token.value).collect(),
+            Err(e) => return Some(Err(e)),
+        };
+
+        let result = self.call_function(&first.value, &args).and_then(|status| {
+            let value = self.return_value.take().unwrap_or_default();
+            self.store(assignment, value)?;
+            Ok(status)
+        });
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parameters_are_local() {
+        let mut shell = Shell::new();
+        let source = "x = outer\n\
+                      function show x\n\
+                      \x20 local y = \"$x $1\"\n\
+                      \x20 return $y\n\
+                      end\n\
+                      result = show inner";
+        shell.run_source(source, "test.vsh").unwrap();
+        assert_eq!(shell.variables["result"], "inner inner");
+        assert_eq!(shell.variables["x"], "outer");
+        assert!(!shell.variables.contains_key("y"));
+        assert!(shell.locals.is_empty());
+    }
+
+    #[test]
+    fn test_return_status_and_value() {
+        let mut shell = Shell::new();
+        let source = "function check\n  return 3\n  echo never\nend\n\
+                      function name\n  return vsh shell\nend";
+        shell.run_source(source, "test.vsh").unwrap();
+        assert_eq!(shell.run_line("check").unwrap(), 3);
+        assert_eq!(shell.run_line("n = name").unwrap(), 0);
+        assert_eq!(shell.variables["n"], "vsh shell");
+        assert_eq!(shell.run_line("return").unwrap(), 3);
+    }
+
+    #[test]
+    fn test_runaway_recursion_stops() {
+        let mut shell = Shell::new();
+        shell
+            .run_source("function forever\n  forever\nend", "test.vsh")
+            .unwrap();
+        assert_eq!(shell.run_line("forever").unwrap(), 1);
+        assert_eq!(shell.flow, None);
+        assert!(shell.locals.is_empty());
+    }
+}
@@ -0,0 +1,297 @@
+use crate::parser::{Quote, Token};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One character of a pattern; quoted characters never act as wildcards
+#[derive(Debug, Clone, Copy)]
+struct PatternChar {
+    ch: char,
+    wild: bool,
+}
+
+impl PatternChar {
+    fn is(&self, ch: char) -> bool {
+        self.wild && self.ch == ch
+    }
+}
+
+/// Expand the unquoted wildcards (`*`, `?`, `[...]`, and `**` for any
+/// number of directories) in a word into the sorted paths they match.
+/// Returns None if the word has no wildcards.
+pub fn expand(word: &Token) -> Option<Vec<String>> {
+    let pattern: Vec<PatternChar> = word
+        .parts
+        .iter()
+        .flat_map(|(text, quote)| {
+            text.chars().map(move |ch| PatternChar {
+                ch,
+                wild: *quote == Quote::Bare,
+            })
+        })
+        .collect();
+
+    if !has_wildcards(&pattern) {
+        return None;
+    }
+
+    let absolute = pattern.first().is_some_and(|p| p.ch == '/');
+    let dirs_only = pattern.last().is_some_and(|p| p.ch == '/');
+
+    let mut paths = vec![if absolute {
+        PathBuf::from("/")
+    } else {
+        PathBuf::new()
+    }];
+
+    let components: Vec<&[PatternChar]> = pattern
+        .split(|p| p.ch == '/')
+        .filter(|c| !c.is_empty())
+        .collect();
+
+    for (i, component) in components.iter().enumerate() {
+        let last = i + 1 == components.len();
+        paths = paths
+            .iter()
+            .flat_map(|base| match component {
+                [a, b] if a.is('*') && b.is('*') => descendants(base, last),
+                _ => expand_component(base, component),
+            })
+            .collect();
+    }
+
+    let mut matches: Vec<String> = paths
+        .into_iter()
+        .filter(|path| !dirs_only || path.is_dir())
+        .map(|path| {
+            let mut path = path.to_string_lossy().into_owned();
+            if dirs_only {
+                path.push('/');
+            }
+            path
+        })
+        .collect();
+    matches.sort();
+
+    Some(matches)
+}
+
+/// A `[` only counts once a `]` closes it, so `[` and `files=[a,` are plain
+fn has_wildcards(pattern: &[PatternChar]) -> bool {
+    pattern.iter().enumerate().any(|(i, p)| {
+        p.is('*')
+            || p.is('?')
+            || (p.is('[')
+                && pattern
+                    .get(i + 2..)
+                    .is_some_and(|rest| rest.iter().any(|q| q.ch == ']')))
+    })
+}
+
+/// `base` and every directory below it, or with `files` everything below
+/// it, for a `**` component. Hidden entries and links to directories are
+/// left out, so a link loop can't go on forever.
+fn descendants(base: &Path, files: bool) -> Vec<PathBuf> {
+    let mut found = if files {
+        vec![]
+    } else {
+        vec![base.to_path_buf()]
+    };
+    let dir = if base.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        base
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return found;
+    };
+
+    let mut entries: Vec<_> = entries.flatten().collect();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        if name.starts_with('.') {
+            continue;
+        }
+        let path = base.join(&name);
+        let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+
+        if is_dir {
+            if files {
+                found.push(path.clone());
+            }
+            found.extend(descendants(&path, files));
+        } else if files {
+            found.push(path);
+        }
+    }
+
+    found
+}
+
+/// The entries of `base` matching one path component of a pattern
+fn expand_component(base: &Path, component: &[PatternChar]) -> Vec<PathBuf> {
+    if !has_wildcards(component) {
+        let path = base.join(component.iter().map(|p| p.ch).collect::<String>());
+        return match path.symlink_metadata() {
+            Ok(_) => vec![path],
+            Err(_) => vec![],
+        };
+    }
+
+    let dir = if base.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        base
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            // Hidden files only match a pattern that starts with a dot
+            if name.starts_with('.') && component[0].ch != '.' {
+                return None;
+            }
+            let chars: Vec<char> = name.chars().collect();
+            matches(component, &chars).then(|| base.join(name))
+        })
+        .collect()
+}
+
+/// Check if a name matches a wildcard pattern like `*.txt`, as `find
+/// -name` and `.gitignore` files use them
+pub fn matches_name(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<PatternChar> = pattern
+        .chars()
+        .map(|ch| PatternChar { ch, wild: true })
+        .collect();
+    matches(&pattern, &name.chars().collect::<Vec<_>>())
+}
+
+/// Check if a name matches a pattern
+fn matches(pattern: &[PatternChar], name: &[char]) -> bool {
+    let Some((first, rest)) = pattern.split_first() else {
+        return name.is_empty();
+    };
+
+    if first.is('*') {
+        return (0..=name.len()).any(|skip| matches(rest, &name[skip..]));
+    }
+
+    let Some((ch, name_rest)) = name.split_first() else {
+        return false;
+    };
+
+    if first.is('?') {
+        return matches(rest, name_rest);
+    }
+    if first.is('[') {
+        if let Some((matched, after)) = match_bracket(rest, *ch) {
+            return matched && matches(after, name_rest);
+        }
+    }
+
+    first.ch == *ch && matches(rest, name_rest)
+}
+
+/// Match a character against the class following a `[`, like `[abc]`,
+/// `[a-z]` or `[!0-9]`. Returns whether it matched and the rest of the
+/// pattern, or None if the class is never closed.
+fn match_bracket(pattern: &[PatternChar], ch: char) -> Option<(bool, &[PatternChar])> {
+    let negated = pattern.first().is_some_and(|p| p.is('!') || p.is('^'));
+    let start = usize::from(negated);
+    let mut i = start;
+    let mut matched = false;
+
+    loop {
+        let p = pattern.get(i)?;
+        // A ']' right after the '[' is an ordinary member
+        if p.ch == ']' && i > start {
+            break;
+        }
+
+        match (pattern.get(i + 1), pattern.get(i + 2)) {
+            (Some(dash), Some(high)) if dash.ch == '-' && high.ch != ']' => {
+                matched |= p.ch <= ch && ch <= high.ch;
+                i += 3;
+            }
+            _ => {
+                matched |= p.ch == ch;
+                i += 1;
+            }
+        }
+    }
+
+    Some((matched != negated, &pattern[i + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn pattern(text: &str) -> Vec<PatternChar> {
+        text.chars()
+            .map(|ch| PatternChar { ch, wild: true })
+            .collect()
+    }
+
+    fn is_match(pattern_text: &str, name: &str) -> bool {
+        matches(&pattern(pattern_text), &name.chars().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(is_match("*.txt", "notes.txt"));
+        assert!(!is_match("*.txt", "notes.txt.bak"));
+        assert!(is_match("file?.rs", "file1.rs"));
+        assert!(is_match("[a-c]*", "banana"));
+        assert!(!is_match("[!a-c]*", "banana"));
+        assert!(is_match("[]x]", "]"));
+        assert!(is_match("a[b", "a[b"));
+    }
+
+    #[test]
+    fn test_expand_in_directory() {
+        let dir = tempdir().unwrap();
+        for name in ["b.txt", "a.txt", "c.log", ".hidden.txt"] {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+        fs::create_dir(dir.path().join("sub")).unwrap();
+
+        let base = dir.path().to_string_lossy();
+        let found = expand(&Token::word(&format!("{}/*.txt", base))).unwrap();
+        assert_eq!(
+            found,
+            vec![format!("{}/a.txt", base), format!("{}/b.txt", base)]
+        );
+
+        let dirs = expand(&Token::word(&format!("{}/*/", base))).unwrap();
+        assert_eq!(dirs, vec![format!("{}/sub/", base)]);
+
+        fs::create_dir(dir.path().join("sub/deeper")).unwrap();
+        fs::write(dir.path().join("sub/deeper/d.txt"), "").unwrap();
+        let deep = expand(&Token::word(&format!("{}/**/*.txt", base))).unwrap();
+        assert_eq!(
+            deep,
+            vec![
+                format!("{}/a.txt", base),
+                format!("{}/b.txt", base),
+                format!("{}/sub/deeper/d.txt", base)
+            ]
+        );
+        assert_eq!(expand(&Token::word(&format!("{}/[", base))), None);
+
+        let quoted = Token::from_parts(vec![
+            (format!("{}/", base), Quote::Bare),
+            ("*.txt".to_string(), Quote::Double),
+        ]);
+        assert_eq!(expand(&quoted), None);
+    }
+}
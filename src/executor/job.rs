@@ -0,0 +1,672 @@
+use crate::error::{Result, VshError};
+use crate::executor::process::{ForegroundGuard, Group};
+use crate::executor::{report_error, Shell};
+use colored::*;
+use nix::errno::Errno;
+use nix::sys::signal::{self, kill, killpg, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::{getpgrp, getpid, setpgid, tcgetpgrp, tcsetpgrp, Pid};
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by the SIGCHLD handler: a child has ended or stopped since we looked
+static CHILD_CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// Set when Ctrl-C breaks off a `wait`
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn note_child(_: libc::c_int) {
+    CHILD_CHANGED.store(true, Ordering::SeqCst);
+}
+
+extern "C" fn note_interrupt(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// What a job, or one of its processes, is doing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobState {
+    Running,
+    Stopped,
+    /// Exited with this status
+    Done(i32),
+    /// Ended by a signal
+    Killed(Signal),
+}
+
+impl JobState {
+    fn from_wait(status: WaitStatus) -> Option<Self> {
+        match status {
+            WaitStatus::Exited(_, code) => Some(JobState::Done(code)),
+            WaitStatus::Signaled(_, sig, _) => Some(JobState::Killed(sig)),
+            WaitStatus::Stopped(..) => Some(JobState::Stopped),
+            WaitStatus::Continued(_) => Some(JobState::Running),
+            _ => None,
+        }
+    }
+
+    fn is_finished(self) -> bool {
+        matches!(self, JobState::Done(_) | JobState::Killed(_))
+    }
+
+    /// The exit status this state stands for, as `$?` would see it
+    pub fn status(self) -> i32 {
+        match self {
+            JobState::Running => 0,
+            JobState::Stopped => 128 + Signal::SIGTSTP as i32,
+            JobState::Done(code) => code,
+            JobState::Killed(sig) => 128 + sig as i32,
+        }
+    }
+}
+
+impl fmt::Display for JobState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JobState::Running => write!(f, "Running"),
+            JobState::Stopped => write!(f, "Stopped"),
+            JobState::Done(0) => write!(f, "Done"),
+            JobState::Done(code) => write!(f, "Exit {}", code),
+            JobState::Killed(sig) => write!(f, "Killed by {}", sig),
+        }
+    }
+}
+
+/// A pipeline the shell started, while it runs in the background or sits
+/// stopped after Ctrl-Z
+#[derive(Debug, Clone)]
+pub struct Job {
+    /// The `1` in `%1`; 0 until the job goes in the table
+    pub id: usize,
+    /// The job's process group with job control on, its first process
+    /// otherwise
+    pub pgid: Pid,
+    /// Each process with what it was last seen doing
+    processes: Vec<(Pid, JobState)>,
+    /// The command line, for `jobs` and notifications
+    pub command: String,
+    /// Program named when the job is killed by a signal
+    name: String,
+    /// The state last shown to the user
+    reported: JobState,
+}
+
+impl Job {
+    pub fn new(pids: Vec<Pid>, command: String, name: String) -> Self {
+        Self {
+            id: 0,
+            pgid: pids[0],
+            processes: pids
+                .into_iter()
+                .map(|pid| (pid, JobState::Running))
+                .collect(),
+            command,
+            name,
+            reported: JobState::Running,
+        }
+    }
+
+    /// Stopped while any process is stopped, running while any runs, and
+    /// otherwise however its last process ended
+    pub fn state(&self) -> JobState {
+        let states = || self.processes.iter().map(|(_, state)| *state);
+        if states().any(|state| state == JobState::Stopped) {
+            JobState::Stopped
+        } else if states().any(|state| state == JobState::Running) {
+            JobState::Running
+        } else {
+            self.processes[self.processes.len() - 1].1
+        }
+    }
+
+    fn pids(&self) -> impl Iterator<Item = Pid> + '_ {
+        self.processes.iter().map(|(pid, _)| *pid)
+    }
+
+    /// Collect what the job's processes have done. Blocking, this returns
+    /// once they have all finished, the job stops, or Ctrl-C breaks off a
+    /// `wait`.
+    fn wait(&mut self, block: bool) -> Result<()> {
+        let flags = if block {
+            WaitPidFlag::WUNTRACED
+        } else {
+            WaitPidFlag::WUNTRACED | WaitPidFlag::WNOHANG | WaitPidFlag::WCONTINUED
+        };
+
+        for i in 0..self.processes.len() {
+            let (pid, state) = self.processes[i];
+            if state.is_finished() || (block && self.state() == JobState::Stopped) {
+                continue;
+            }
+            loop {
+                match waitpid(pid, Some(flags)) {
+                    Err(Errno::EINTR) if INTERRUPTED.load(Ordering::SeqCst) => return Ok(()),
+                    Err(Errno::EINTR) => continue,
+                    // Already collected by someone else; nothing more to learn
+                    Err(Errno::ECHILD) => self.processes[i].1 = JobState::Done(0),
+                    Err(e) => return Err(VshError::ExecutionError(format!("wait failed: {}", e))),
+                    Ok(status) => {
+                        if let Some(state) = JobState::from_wait(status) {
+                            self.processes[i].1 = state;
+                        }
+                    }
+                }
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The jobs in the background or stopped, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct JobTable {
+    jobs: Vec<Job>,
+    /// The shell's own process group, once job control is on
+    shell_group: Option<Pid>,
+}
+
+impl JobTable {
+    /// Whether jobs get process groups and the terminal is handed around
+    pub fn job_control(&self) -> bool {
+        self.shell_group.is_some()
+    }
+
+    /// The process group for the next process of a job whose first process
+    /// is `leader`
+    pub fn group(&self, leader: Option<Pid>, background: bool) -> Group {
+        match (self.job_control(), background) {
+            (true, _) => Group::Job {
+                leader,
+                foreground: !background,
+            },
+            (false, true) => Group::ShellBackground,
+            (false, false) => Group::Shell,
+        }
+    }
+
+    /// Give the terminal back to the shell after a foreground job
+    pub fn take_terminal(&self) {
+        if let Some(group) = self.shell_group {
+            let _ = tcsetpgrp(libc::STDIN_FILENO, group);
+        }
+    }
+
+    /// Put a job in the table, numbering it unless it had a number before
+    fn add(&mut self, mut job: Job) -> usize {
+        if job.id == 0 {
+            job.id = self.jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+        }
+        job.reported = job.state();
+        let id = job.id;
+        self.jobs.push(job);
+        id
+    }
+
+    /// Find a job by `%1`, `%+` or `%%` (the current one), `%-` (the one
+    /// before) or `%name` (the start of its command). A bare number is a job
+    /// number too.
+    fn find(&self, spec: Option<&str>) -> Result<usize> {
+        let spec = spec.unwrap_or("%+");
+        let found = match spec.strip_prefix('%').unwrap_or(spec) {
+            "" | "%" | "+" => self.jobs.len().checked_sub(1),
+            "-" => self.jobs.len().checked_sub(2),
+            text => match text.parse::<usize>() {
+                Ok(id) => self.jobs.iter().position(|job| job.id == id),
+                Err(_) => self
+                    .jobs
+                    .iter()
+                    .position(|job| job.command.starts_with(text)),
+            },
+        };
+
+        found.ok_or_else(|| match self.jobs.is_empty() {
+            true => VshError::ExecutionError("There are no jobs".to_string()),
+            false => VshError::ExecutionError(format!("No such job: {}", spec)),
+        })
+    }
+
+    /// A job as `jobs` shows it: `[1]+ Running    sleep 60 &`
+    fn line(&self, index: usize) -> String {
+        let job = &self.jobs[index];
+        let mark = match self.jobs.len() - index {
+            1 => '+',
+            2 => '-',
+            _ => ' ',
+        };
+        let state = job.state();
+        let text = format!("{:<12}", state.to_string());
+        let text = match state {
+            JobState::Running | JobState::Done(0) => text.green(),
+            JobState::Stopped => text.yellow(),
+            _ => text.red(),
+        };
+        let suffix = if state == JobState::Running { " &" } else { "" };
+        format!("[{}]{} {} {}{}", job.id, mark, text, job.command, suffix)
+    }
+
+    /// Send a signal to every process of a job, waking it up first if it's
+    /// stopped and the signal should end it
+    fn signal(&self, job: &Job, sig: Signal) -> Result<()> {
+        let send = |sig: Signal| -> Result<()> {
+            let result = if self.job_control() {
+                killpg(job.pgid, sig)
+            } else {
+                job.pids().try_for_each(|pid| kill(pid, sig))
+            };
+            result.map_err(|e| {
+                VshError::ExecutionError(format!("Could not signal job {}: {}", job.id, e))
+            })
+        };
+
+        send(sig)?;
+        if job.state() == JobState::Stopped
+            && !matches!(sig, Signal::SIGSTOP | Signal::SIGTSTP | Signal::SIGCONT)
+        {
+            send(Signal::SIGCONT)?;
+        }
+        Ok(())
+    }
+
+    /// Check on every job without waiting
+    fn update(&mut self) {
+        for job in &mut self.jobs {
+            if let Err(e) = job.wait(false) {
+                report_error(&e);
+            }
+        }
+    }
+}
+
+impl Shell {
+    /// Turn on job control for an interactive session: the shell gets its
+    /// own process group and the terminal, and hands the terminal to each
+    /// foreground job. Ctrl-Z stops the job rather than the shell.
+    pub fn enable_job_control(&mut self) {
+        let terminal = libc::STDIN_FILENO;
+        if !io::stdin().is_terminal() {
+            return;
+        }
+
+        // Started in the background, wait until we're brought to the front
+        loop {
+            match tcgetpgrp(terminal) {
+                Ok(group) if group == getpgrp() => break,
+                Ok(_) => {
+                    let _ = killpg(getpgrp(), Signal::SIGTTIN);
+                }
+                Err(_) => return,
+            }
+        }
+
+        let child = SigAction::new(
+            SigHandler::Handler(note_child),
+            SaFlags::SA_RESTART,
+            SigSet::empty(),
+        );
+        // SAFETY: the handlers only store to an atomic
+        unsafe {
+            for sig in [Signal::SIGTSTP, Signal::SIGTTIN, Signal::SIGTTOU] {
+                let _ = signal::signal(sig, SigHandler::SigIgn);
+            }
+            let _ = signal::sigaction(Signal::SIGCHLD, &child);
+        }
+
+        let _ = setpgid(getpid(), getpid());
+        let group = getpgrp();
+        if tcsetpgrp(terminal, group).is_ok() {
+            self.jobs.shell_group = Some(group);
+        }
+    }
+
+    /// Run a started job: in the background it goes in the job table,
+    /// otherwise the shell waits for it
+    pub(crate) fn start_job(&mut self, job: Job, background: bool) -> Result<i32> {
+        if !background {
+            return self.wait_foreground(job);
+        }
+
+        let last = job.pids().last();
+        let id = self.jobs.add(job);
+        if let (true, Some(pid)) = (self.jobs.job_control(), last) {
+            eprintln!("[{}] {}", id, pid);
+        }
+        Ok(0)
+    }
+
+    /// Wait for a job to finish, or to be stopped with Ctrl-Z, which puts it
+    /// in the job table. The status is that of its last process.
+    pub(crate) fn wait_foreground(&mut self, mut job: Job) -> Result<i32> {
+        let waited = {
+            let _guard = ForegroundGuard::new();
+            job.wait(true)
+        };
+        self.jobs.take_terminal();
+        waited?;
+
+        let state = job.state();
+        match state {
+            JobState::Stopped => {
+                self.jobs.add(job);
+                eprintln!("\n{}", self.jobs.line(self.jobs.jobs.len() - 1));
+            }
+            // Ctrl-C speaks for itself
+            JobState::Killed(sig) if sig != Signal::SIGINT => {
+                eprintln!("{} '{}' terminated by signal {}", "✗".red(), job.name, sig);
+            }
+            _ => {}
+        }
+        Ok(state.status())
+    }
+
+    /// Print the jobs that finished or stopped since the last look, as the
+    /// prompt comes back. Only an interactive session (with job control)
+    /// hears about them.
+    pub fn notify_jobs(&mut self) {
+        if !self.jobs.job_control() || !CHILD_CHANGED.swap(false, Ordering::SeqCst) {
+            return;
+        }
+        self.jobs.update();
+
+        let mut i = 0;
+        while i < self.jobs.jobs.len() {
+            let state = self.jobs.jobs[i].state();
+            if state != self.jobs.jobs[i].reported && state != JobState::Running {
+                eprintln!("{}", self.jobs.line(i));
+            }
+            self.jobs.jobs[i].reported = state;
+            if state.is_finished() {
+                self.jobs.jobs.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    /// `jobs`: list the jobs; `-l` adds process ids and `-p` prints only
+    /// those. Finished jobs are listed once and then forgotten.
+    pub(crate) fn list_jobs(&mut self, args: &[String], out: &mut dyn Write) -> Result<i32> {
+        let (long, pids) = match args {
+            [] => (false, false),
+            [flag] if flag == "-l" => (true, false),
+            [flag] if flag == "-p" => (false, true),
+            _ => {
+                return Err(VshError::InvalidSyntax(
+                    "Expected: jobs [-l | -p]".to_string(),
+                ))
+            }
+        };
+        self.jobs.update();
+
+        for (i, job) in self.jobs.jobs.iter().enumerate() {
+            if pids {
+                writeln!(out, "{}", job.pgid)?;
+            } else if long {
+                let pids: Vec<String> = job.pids().map(|pid| pid.to_string()).collect();
+                writeln!(
+                    out,
+                    "{} {}",
+                    self.jobs.line(i),
+                    pids.join(" ").bright_black()
+                )?;
+            } else {
+                writeln!(out, "{}", self.jobs.line(i))?;
+            }
+        }
+
+        for job in &mut self.jobs.jobs {
+            job.reported = job.state();
+        }
+        self.jobs.jobs.retain(|job| !job.state().is_finished());
+        Ok(0)
+    }
+
+    /// `fg [job]`: continue a job in the foreground and wait for it
+    pub(crate) fn foreground(&mut self, args: &[String], out: &mut dyn Write) -> Result<i32> {
+        self.jobs.update();
+        let index = self.jobs.find(args.first().map(String::as_str))?;
+        writeln!(out, "{}", self.jobs.jobs[index].command)?;
+        out.flush()?;
+
+        let mut job = self.jobs.jobs.remove(index);
+        if self.jobs.job_control() {
+            let _ = tcsetpgrp(libc::STDIN_FILENO, job.pgid);
+        }
+        if job.state() == JobState::Stopped {
+            if let Err(e) = self.jobs.signal(&job, Signal::SIGCONT) {
+                self.jobs.take_terminal();
+                self.jobs.add(job);
+                return Err(e);
+            }
+        }
+        for (_, state) in &mut job.processes {
+            if *state == JobState::Stopped {
+                *state = JobState::Running;
+            }
+        }
+
+        self.wait_foreground(job)
+    }
+
+    /// `bg [job]`: let a stopped job carry on in the background
+    pub(crate) fn background(&mut self, args: &[String], out: &mut dyn Write) -> Result<i32> {
+        self.jobs.update();
+        let index = self.jobs.find(args.first().map(String::as_str))?;
+        if self.jobs.jobs[index].state() != JobState::Stopped {
+            return Err(VshError::ExecutionError(format!(
+                "Job {} is already running",
+                self.jobs.jobs[index].id
+            )));
+        }
+
+        self.jobs.signal(&self.jobs.jobs[index], Signal::SIGCONT)?;
+        let job = &mut self.jobs.jobs[index];
+        for (_, state) in &mut job.processes {
+            if *state == JobState::Stopped {
+                *state = JobState::Running;
+            }
+        }
+        job.reported = JobState::Running;
+        writeln!(out, "[{}] {} &", job.id, job.command)?;
+        Ok(0)
+    }
+
+    /// `wait [job | pid]...`: wait for the given jobs, or all of them, to
+    /// finish. The status is that of the last one given, or 130 if Ctrl-C
+    /// broke off the wait.
+    pub(crate) fn wait_for_jobs(&mut self, args: &[String]) -> Result<i32> {
+        let indexes = if args.is_empty() {
+            (0..self.jobs.jobs.len()).collect()
+        } else {
+            args.iter()
+                .map(|arg| self.job_index(arg))
+                .collect::<Result<Vec<_>>>()?
+        };
+        let ids: Vec<usize> = indexes.iter().map(|&i| self.jobs.jobs[i].id).collect();
+
+        let _interrupt = InterruptGuard::new();
+        let mut status = 0;
+        for id in ids {
+            let Some(index) = self.jobs.jobs.iter().position(|job| job.id == id) else {
+                continue;
+            };
+            self.jobs.jobs[index].wait(true)?;
+            if INTERRUPTED.load(Ordering::SeqCst) {
+                return Ok(130);
+            }
+
+            let state = self.jobs.jobs[index].state();
+            if state.is_finished() {
+                self.jobs.jobs.remove(index);
+            }
+            // Waiting for everything always succeeds, as in bash
+            if !args.is_empty() {
+                status = state.status();
+            }
+        }
+        Ok(status)
+    }
+
+    /// `kill [-SIGNAL | -s SIGNAL] job-or-pid...`, or `kill -l` to list the
+    /// signals. The signal is TERM unless given.
+    pub(crate) fn kill(&mut self, args: &[String], out: &mut dyn Write) -> Result<i32> {
+        let mut sig = Signal::SIGTERM;
+        let mut targets = args;
+        match args {
+            [flag] if flag == "-l" => {
+                let names: Vec<&str> = Signal::iterator().map(|sig| &sig.as_str()[3..]).collect();
+                writeln!(out, "{}", names.join(" "))?;
+                return Ok(0);
+            }
+            [flag, name, rest @ ..] if flag == "-s" => {
+                sig = parse_signal(name)?;
+                targets = rest;
+            }
+            [flag, rest @ ..] if flag.starts_with('-') && flag != "--" => {
+                sig = parse_signal(&flag[1..])?;
+                targets = rest;
+            }
+            [flag, rest @ ..] if flag == "--" => targets = rest,
+            _ => {}
+        }
+        if targets.is_empty() {
+            return Err(VshError::InvalidSyntax(
+                "Expected: kill [-SIGNAL] <%job or process id>...".to_string(),
+            ));
+        }
+
+        let mut failed = false;
+        for target in targets {
+            let result = match target.starts_with('%') {
+                true => self
+                    .jobs
+                    .find(Some(target))
+                    .and_then(|index| self.jobs.signal(&self.jobs.jobs[index], sig)),
+                false => parse_pid(target).and_then(|pid| {
+                    kill(pid, sig).map_err(|e| {
+                        VshError::ExecutionError(format!("Could not signal {}: {}", target, e))
+                    })
+                }),
+            };
+            if let Err(e) = result {
+                report_error(&e);
+                failed = true;
+            }
+        }
+        Ok(if failed { 1 } else { 0 })
+    }
+
+    /// A job named by `%spec`, or the job a process id belongs to
+    fn job_index(&self, arg: &str) -> Result<usize> {
+        if arg.starts_with('%') {
+            return self.jobs.find(Some(arg));
+        }
+        let pid = parse_pid(arg)?;
+        self.jobs
+            .jobs
+            .iter()
+            .position(|job| job.pids().any(|p| p == pid))
+            .ok_or_else(|| {
+                VshError::ExecutionError(format!("Process {} is not a job of this shell", pid))
+            })
+    }
+}
+
+/// A signal by name (`TERM`, `SIGTERM`, any case) or number
+fn parse_signal(name: &str) -> Result<Signal> {
+    let invalid = || VshError::InvalidSyntax(format!("Unknown signal '{}'", name));
+    if let Ok(number) = name.parse::<i32>() {
+        return Signal::try_from(number).map_err(|_| invalid());
+    }
+    let name = name.to_uppercase();
+    let name = match name.starts_with("SIG") {
+        true => name,
+        false => format!("SIG{}", name),
+    };
+    Signal::from_str(&name).map_err(|_| invalid())
+}
+
+fn parse_pid(text: &str) -> Result<Pid> {
+    text.parse()
+        .map(Pid::from_raw)
+        .map_err(|_| VshError::InvalidSyntax(format!("'{}' is not a job or process id", text)))
+}
+
+/// Lets Ctrl-C break off a `wait`, rather than end the shell, restoring the
+/// previous handler when dropped
+struct InterruptGuard {
+    old: Option<SigAction>,
+}
+
+impl InterruptGuard {
+    fn new() -> Self {
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        // No SA_RESTART, so a waiting `waitpid` returns at once
+        let action = SigAction::new(
+            SigHandler::Handler(note_interrupt),
+            SaFlags::empty(),
+            SigSet::empty(),
+        );
+        // SAFETY: the handler only stores to an atomic
+        Self {
+            old: unsafe { signal::sigaction(Signal::SIGINT, &action).ok() },
+        }
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        if let Some(old) = &self.old {
+            // SAFETY: puts back the handler that was there before
+            unsafe {
+                let _ = signal::sigaction(Signal::SIGINT, old);
+            }
+        }
+        INTERRUPTED.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: usize, command: &str) -> Job {
+        let mut job = Job::new(vec![Pid::from_raw(1)], command.to_string(), "x".to_string());
+        job.id = id;
+        job
+    }
+
+    #[test]
+    fn test_find_job_by_spec() {
+        let mut table = JobTable::default();
+        table.add(job(1, "sleep 60"));
+        table.add(job(2, "make all"));
+
+        assert_eq!(table.find(None).unwrap(), 1);
+        assert_eq!(table.find(Some("%%")).unwrap(), 1);
+        assert_eq!(table.find(Some("%-")).unwrap(), 0);
+        assert_eq!(table.find(Some("%1")).unwrap(), 0);
+        assert_eq!(table.find(Some("2")).unwrap(), 1);
+        assert_eq!(table.find(Some("%sleep")).unwrap(), 0);
+        assert!(table.find(Some("%3")).is_err());
+        assert!(JobTable::default().find(None).is_err());
+    }
+
+    #[test]
+    fn test_job_state_and_signals() {
+        let mut job = job(1, "a | b");
+        job.processes.push((Pid::from_raw(2), JobState::Running));
+        job.processes[0].1 = JobState::Done(0);
+        assert_eq!(job.state(), JobState::Running);
+        job.processes[1].1 = JobState::Killed(Signal::SIGTERM);
+        assert_eq!(job.state().status(), 143);
+        assert_eq!(JobState::Done(2).to_string(), "Exit 2");
+
+        assert_eq!(parse_signal("9").unwrap(), Signal::SIGKILL);
+        assert_eq!(parse_signal("hup").unwrap(), Signal::SIGHUP);
+        assert_eq!(parse_signal("SIGTERM").unwrap(), Signal::SIGTERM);
+        assert!(parse_signal("NOPE").is_err());
+    }
+}
@@ -0,0 +1,417 @@
+use crate::error::{Result, VshError};
+use crate::executor::copy::path_error;
+use colored::*;
+use nix::unistd::{Gid, Group, Uid, User};
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::io::Write;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Roughly six months: older modification times show the year instead
+const RECENT_SECONDS: i64 = 182 * 24 * 60 * 60;
+
+/// What to sort a listing by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortBy {
+    Name,
+    /// Largest first
+    Size,
+    /// Newest first
+    Time,
+}
+
+impl SortBy {
+    fn parse(key: &str) -> Result<Self> {
+        match key {
+            "name" => Ok(SortBy::Name),
+            "size" => Ok(SortBy::Size),
+            "time" | "date" | "modified" => Ok(SortBy::Time),
+            _ => Err(VshError::InvalidSyntax(format!(
+                "Unknown sort order '{}' (use name, size or time)",
+                key
+            ))),
+        }
+    }
+}
+
+/// How `list` shows a directory, from `ls` flags or their long forms
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ListOptions {
+    /// Permissions, owner, size and modification time for every entry
+    pub long: bool,
+    /// Include hidden entries
+    pub all: bool,
+    pub sort: SortBy,
+    pub reverse: bool,
+    /// No emojis, for terminals that can't show them
+    pub plain: bool,
+}
+
+impl ListOptions {
+    pub fn from_flags(flags: &[String]) -> Result<Self> {
+        let term = std::env::var("TERM").unwrap_or_default();
+        let mut options = ListOptions {
+            long: false,
+            all: false,
+            sort: SortBy::Name,
+            reverse: false,
+            plain: term == "dumb" || term == "linux",
+        };
+
+        for flag in flags {
+            if let Some(long) = flag.strip_prefix("--") {
+                match long.split_once('=') {
+                    Some(("sort", key)) => options.sort = SortBy::parse(key)?,
+                    Some(_) => return Err(unknown_option(flag)),
+                    None => options.set(long, flag)?,
+                }
+            } else {
+                for short in flag.chars().skip(1) {
+                    options.set(&short.to_string(), flag)?;
+                }
+            }
+        }
+
+        Ok(options)
+    }
+
+    fn set(&mut self, option: &str, flag: &str) -> Result<()> {
+        match option {
+            "l" | "long" | "details" => self.long = true,
+            "a" | "A" | "all" | "hidden" | "almost-all" => self.all = true,
+            "S" => self.sort = SortBy::Size,
+            "t" => self.sort = SortBy::Time,
+            "r" | "reverse" => self.reverse = true,
+            "plain" => self.plain = true,
+            // Sizes are always human-readable, and there is one entry a line
+            "h" | "human-readable" | "1" => {}
+            _ => return Err(unknown_option(flag)),
+        }
+        Ok(())
+    }
+}
+
+fn unknown_option(flag: &str) -> VshError {
+    VshError::InvalidSyntax(format!("Unknown option '{}'", flag))
+}
+
+/// One thing in a listing. The metadata is the link's own for a symlink.
+struct Entry {
+    name: String,
+    meta: Metadata,
+    /// Where a symlink points
+    target: Option<String>,
+    /// A directory, or a link to one
+    is_dir: bool,
+}
+
+impl Entry {
+    fn read(path: &Path, name: String) -> Result<Self> {
+        let meta = path.symlink_metadata().map_err(|e| path_error(path, e))?;
+        let target = meta
+            .file_type()
+            .is_symlink()
+            .then(|| fs::read_link(path).map(|t| t.to_string_lossy().into_owned()))
+            .transpose()
+            .map_err(|e| path_error(path, e))?;
+        Ok(Entry {
+            name,
+            is_dir: path.is_dir(),
+            meta,
+            target,
+        })
+    }
+}
+
+/// List a directory, or show a single file, the way `options` ask
+pub fn list(path: &str, options: &ListOptions, out: &mut dyn Write) -> Result<()> {
+    let dir_path = Path::new(path);
+
+    if dir_path.symlink_metadata().is_err() {
+        return Err(VshError::FileNotFound(path.to_string()));
+    }
+
+    if !dir_path.is_dir() {
+        let entry = Entry::read(dir_path, path.to_string())?;
+        return write_entries(&[entry], options, out);
+    }
+
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir_path).map_err(|e| path_error(dir_path, e))? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !options.all && name.starts_with('.') {
+            continue;
+        }
+        entries.push(Entry::read(&entry.path(), name)?);
+    }
+    sort(&mut entries, options);
+
+    writeln!(out, "{}", format!("Contents of {}:", path).bright_cyan())?;
+    writeln!(out)?;
+    write_entries(&entries, options, out)?;
+    writeln!(out)?;
+
+    Ok(())
+}
+
+fn sort(entries: &mut [Entry], options: &ListOptions) {
+    entries.sort_by(|a, b| {
+        let by_name = a
+            .name
+            .to_lowercase()
+            .cmp(&b.name.to_lowercase())
+            .then_with(|| a.name.cmp(&b.name));
+        match options.sort {
+            SortBy::Name => by_name,
+            SortBy::Size => b.meta.len().cmp(&a.meta.len()).then(by_name),
+            SortBy::Time => b.meta.mtime().cmp(&a.meta.mtime()).then(by_name),
+        }
+    });
+    if options.reverse {
+        entries.reverse();
+    }
+}
+
+fn write_entries(entries: &[Entry], options: &ListOptions, out: &mut dyn Write) -> Result<()> {
+    if !options.long {
+        for entry in entries {
+            writeln!(out, "  {}", name_column(entry, options.plain, false))?;
+        }
+        return Ok(());
+    }
+
+    let mut names = Names::default();
+    let rows: Vec<[String; 4]> = entries
+        .iter()
+        .map(|entry| {
+            [
+                names.user(entry.meta.uid()),
+                names.group(entry.meta.gid()),
+                human_size(entry.meta.len()),
+                format_time(entry.meta.mtime(), now()),
+            ]
+        })
+        .collect();
+    let width = |column: usize| rows.iter().map(|r| r[column].len()).max().unwrap_or(0);
+    let (user_width, group_width, size_width) = (width(0), width(1), width(2));
+
+    for (entry, [user, group, size, time]) in entries.iter().zip(rows) {
+        writeln!(
+            out,
+            "  {}  {:<uw$}  {:<gw$}  {:>sw$}  {}  {}",
+            permissions(entry.meta.mode()),
+            user,
+            group,
+            size,
+            time,
+            name_column(entry, options.plain, true),
+            uw = user_width,
+            gw = group_width,
+            sw = size_width,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// The name, marked as a directory or link, with a link's target in the
+/// long format
+fn name_column(entry: &Entry, plain: bool, long: bool) -> String {
+    let (icon, name) = if let Some(target) = &entry.target {
+        let name = if long {
+            format!("{} -> {}", entry.name.bright_cyan(), target)
+        } else {
+            format!("{}@", entry.name.bright_cyan())
+        };
+        ("🔗", name)
+    } else if entry.is_dir {
+        ("📁", format!("{}/", entry.name.bright_blue()))
+    } else {
+        ("📄", entry.name.clone())
+    };
+
+    if plain {
+        name
+    } else {
+        format!("{} {}", icon, name)
+    }
+}
+
+/// User and group names, looked up once each
+#[derive(Default)]
+struct Names {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+impl Names {
+    fn user(&mut self, uid: u32) -> String {
+        self.users
+            .entry(uid)
+            .or_insert_with(|| match User::from_uid(Uid::from_raw(uid)) {
+                Ok(Some(user)) => user.name,
+                _ => uid.to_string(),
+            })
+            .clone()
+    }
+
+    fn group(&mut self, gid: u32) -> String {
+        self.groups
+            .entry(gid)
+            .or_insert_with(|| match Group::from_gid(Gid::from_raw(gid)) {
+                Ok(Some(group)) => group.name,
+                _ => gid.to_string(),
+            })
+            .clone()
+    }
+}
+
+/// A mode as `ls` shows it, like `drwxr-xr-x`
+fn permissions(mode: u32) -> String {
+    let kind = match mode & libc::S_IFMT {
+        libc::S_IFDIR => 'd',
+        libc::S_IFLNK => 'l',
+        libc::S_IFCHR => 'c',
+        libc::S_IFBLK => 'b',
+        libc::S_IFIFO => 'p',
+        libc::S_IFSOCK => 's',
+        _ => '-',
+    };
+
+    let mut text = String::from(kind);
+    for (shift, special, set_char) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = (mode >> shift) & 0o7;
+        text.push(if bits & 4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 2 != 0 { 'w' } else { '-' });
+        text.push(match (bits & 1 != 0, mode & special != 0) {
+            (true, true) => set_char,
+            (false, true) => set_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    text
+}
+
+/// A size like `512B`, `4.0K` or `23M`
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["K", "M", "G", "T", "P"];
+
+    if bytes < 1024 {
+        return format!("{}B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if size < 10.0 {
+        format!("{:.1}{}", size, UNITS[unit])
+    } else {
+        format!("{:.0}{}", size, UNITS[unit])
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// A modification time in local time: `Oct 18 14:05` for recent ones,
+/// `Mar  2  2023` for older (or future) ones
+fn format_time(seconds: i64, now: i64) -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let time = seconds as libc::time_t;
+    // SAFETY: localtime_r only writes to the tm we give it
+    let tm = unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&time, &mut tm).is_null() {
+            return "?".to_string();
+        }
+        tm
+    };
+
+    let month = MONTHS[tm.tm_mon.clamp(0, 11) as usize];
+    if seconds <= now && now - seconds < RECENT_SECONDS {
+        format!(
+            "{} {:>2} {:02}:{:02}",
+            month, tm.tm_mday, tm.tm_hour, tm.tm_min
+        )
+    } else {
+        format!("{} {:>2}  {}", month, tm.tm_mday, tm.tm_year + 1900)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn flags(flags: &[&str]) -> Result<ListOptions> {
+        ListOptions::from_flags(&flags.iter().map(|f| f.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_options_from_flags() {
+        let options = flags(&["-laS", "--reverse"]).unwrap();
+        assert!(options.long && options.all && options.reverse);
+        assert_eq!(options.sort, SortBy::Size);
+        assert_eq!(flags(&["--sort=time"]).unwrap().sort, SortBy::Time);
+        assert!(flags(&["--plain"]).unwrap().plain);
+        assert!(flags(&["--sort=colour"]).is_err());
+        assert!(flags(&["-z"]).is_err());
+    }
+
+    #[test]
+    fn test_human_sizes_and_permissions() {
+        assert_eq!(human_size(512), "512B");
+        assert_eq!(human_size(4096), "4.0K");
+        assert_eq!(human_size(23 * 1024 * 1024 + 1), "23M");
+        assert_eq!(permissions(libc::S_IFDIR | 0o755), "drwxr-xr-x");
+        assert_eq!(permissions(libc::S_IFREG | 0o4644), "-rwSr--r--");
+        assert_eq!(permissions(libc::S_IFDIR | 0o1777), "drwxrwxrwt");
+    }
+
+    #[test]
+    fn test_listing_order_and_hidden_files() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("small.txt"), "a").unwrap();
+        fs::write(dir.path().join("Big.txt"), "a".repeat(2000)).unwrap();
+        fs::write(dir.path().join(".hidden"), "").unwrap();
+        fs::create_dir(dir.path().join("docs")).unwrap();
+        std::os::unix::fs::symlink("small.txt", dir.path().join("link")).unwrap();
+
+        let listing = |flags_given: &[&str]| {
+            colored::control::set_override(false);
+            let mut out = Vec::new();
+            let path = dir.path().to_string_lossy();
+            list(&path, &flags(flags_given).unwrap(), &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let names = listing(&["--plain"]);
+        assert!(names.find("Big.txt").unwrap() < names.find("docs/").unwrap());
+        assert!(names.contains("  link@\n"));
+        assert!(!names.contains(".hidden"));
+        assert!(listing(&["-a", "--plain"]).contains(".hidden"));
+
+        let by_size = listing(&["-S", "--plain"]);
+        assert!(by_size.find("Big.txt").unwrap() < by_size.find("small.txt").unwrap());
+        let reversed = listing(&["-Sr", "--plain"]);
+        assert!(reversed.find("small.txt").unwrap() < reversed.find("Big.txt").unwrap());
+
+        let long = listing(&["-l", "--plain"]);
+        assert!(long.contains("link -> small.txt"));
+        assert!(long.contains("2.0K"));
+        assert!(long.lines().any(|line| line.starts_with("  drwx")));
+    }
+}
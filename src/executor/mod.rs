@@ -1,9 +1,40 @@
+mod brace;
 mod builtin;
+mod condition;
+mod content;
+mod control;
+mod copy;
+mod environment;
+mod expand;
+mod external;
+mod find;
+mod function;
+mod glob;
+mod job;
+mod list;
+mod navigate;
+mod pipeline;
+mod process;
+mod redirect;
+mod search;
+mod shell;
+mod value;
 
-use crate::error::Result;
-use crate::parser::Command;
+use crate::error::VshError;
+use colored::*;
+use std::io::{self, IsTerminal};
 
-/// Execute a parsed command
-pub fn execute_command(cmd: Command) -> Result<()> {
-    builtin::execute_builtin(cmd)
+pub use expand::NoMatch;
+pub use external::find_program;
+pub use shell::Shell;
+pub use value::{Value, ValueType};
+
+/// Print an execution error the way the shell always does; a `2>` file
+/// gets plain text
+pub fn report_error(e: &VshError) {
+    if io::stderr().is_terminal() {
+        eprintln!("{} {}", "✗ Error:".red(), e);
+    } else {
+        eprintln!("✗ Error: {}", e);
+    }
 }
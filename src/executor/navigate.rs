@@ -0,0 +1,185 @@
+use crate::error::{Result, VshError};
+use crate::executor::Shell;
+use crate::parser::Command;
+use colored::*;
+use std::env;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+impl Shell {
+    /// `pushd dir`: go to `dir`, remembering where we were. Without one,
+    /// swap the current directory with the top of the stack.
+    pub(crate) fn push_directory(&mut self, cmd: &Command, out: &mut dyn Write) -> Result<i32> {
+        let here = env::current_dir()?;
+        match cmd.args.path.as_deref() {
+            Some(path) => change_directory(path, out)?,
+            None => {
+                let top = self.dir_stack.last().ok_or_else(empty_stack)?;
+                change_to(top, out)?;
+                self.dir_stack.pop();
+            }
+        }
+
+        self.dir_stack.push(here);
+        self.print_stack(false, out)?;
+        Ok(0)
+    }
+
+    /// `popd`: go back to the directory on top of the stack
+    pub(crate) fn pop_directory(&mut self, out: &mut dyn Write) -> Result<i32> {
+        let top = self.dir_stack.last().ok_or_else(empty_stack)?;
+        change_to(top, out)?;
+        self.dir_stack.pop();
+
+        self.print_stack(false, out)?;
+        Ok(0)
+    }
+
+    /// `dirs`: the current directory and then the stack, most recent first.
+    /// `-v` numbers them, one a line, and `-c` empties the stack.
+    pub(crate) fn directories(&mut self, args: &[String], out: &mut dyn Write) -> Result<i32> {
+        match args {
+            [] => self.print_stack(false, out)?,
+            [flag] if flag == "-v" => self.print_stack(true, out)?,
+            [flag] if flag == "-c" => self.dir_stack.clear(),
+            _ => {
+                return Err(VshError::InvalidSyntax(
+                    "Expected: dirs [-v | -c]".to_string(),
+                ))
+            }
+        }
+        Ok(0)
+    }
+
+    fn print_stack(&self, numbered: bool, out: &mut dyn Write) -> Result<()> {
+        let here = env::current_dir()?;
+        let stack = std::iter::once(&here).chain(self.dir_stack.iter().rev());
+
+        if numbered {
+            for (i, dir) in stack.enumerate() {
+                writeln!(out, "{:>2}  {}", i, display(dir))?;
+            }
+        } else {
+            let line: Vec<String> = stack.map(|dir| display(dir)).collect();
+            writeln!(out, "{}", line.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Execute change directory command: `cd` alone goes home, `cd -` (or
+/// `go back`) to the previous directory, and a relative name not found here
+/// is looked for in the directories of `$CDPATH`
+pub fn execute_cd(cmd: &Command, out: &mut dyn Write) -> Result<()> {
+    let path = cmd.args.path.as_deref().or(cmd.args.destination.as_deref());
+    match path {
+        Some(path) => change_directory(path, out),
+        None => {
+            let home = dirs::home_dir().ok_or_else(|| {
+                VshError::ExecutionError("No home directory to go to".to_string())
+            })?;
+            change_to(&home, out)
+        }
+    }
+}
+
+fn change_directory(path: &str, out: &mut dyn Write) -> Result<()> {
+    if path == "-" {
+        let previous = env::var_os("OLDPWD").ok_or_else(|| {
+            VshError::ExecutionError("No previous directory to go back to".to_string())
+        })?;
+        return change_to(Path::new(&previous), out);
+    }
+
+    let target = Path::new(path);
+    if !target.exists() {
+        let cdpath = env::var("CDPATH").unwrap_or_default();
+        if let Some(found) = find_in_cdpath(path, &cdpath) {
+            return change_to(&found, out);
+        }
+        return Err(VshError::FileNotFound(path.to_string()));
+    }
+
+    change_to(target, out)
+}
+
+/// Look for a relative directory in each directory of a `CDPATH`-style
+/// list. Paths starting with `/`, `.` or `..` are never looked up.
+fn find_in_cdpath(path: &str, cdpath: &str) -> Option<PathBuf> {
+    if path.starts_with('/') || path.starts_with('.') {
+        return None;
+    }
+    cdpath
+        .split(':')
+        .map(|dir| Path::new(if dir.is_empty() { "." } else { dir }).join(path))
+        .find(|candidate| candidate.is_dir())
+}
+
+/// Go to `target`, keeping `$OLDPWD` and `$PWD` up to date
+fn change_to(target: &Path, out: &mut dyn Write) -> Result<()> {
+    if !target.is_dir() {
+        return Err(match target.exists() {
+            true => VshError::ExecutionError(format!("'{}' is not a directory", target.display())),
+            false => VshError::FileNotFound(target.display().to_string()),
+        });
+    }
+
+    let previous = env::current_dir().ok();
+    env::set_current_dir(target)?;
+    if let Some(previous) = previous {
+        env::set_var("OLDPWD", previous);
+    }
+    let now = env::current_dir()?;
+    env::set_var("PWD", &now);
+
+    writeln!(
+        out,
+        "{} Changed directory to {}",
+        "✓".green().bold(),
+        display(&now)
+    )?;
+    Ok(())
+}
+
+/// A path with the home directory shortened to `~`
+fn display(path: &Path) -> String {
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+fn empty_stack() -> VshError {
+    VshError::ExecutionError("The directory stack is empty".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_cdpath_lookup() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("projects/vsh")).unwrap();
+        let cdpath = format!("/nonexistent:{}", dir.path().join("projects").display());
+
+        assert_eq!(
+            find_in_cdpath("vsh", &cdpath),
+            Some(dir.path().join("projects/vsh"))
+        );
+        assert_eq!(find_in_cdpath("missing", &cdpath), None);
+        assert_eq!(find_in_cdpath("./vsh", &cdpath), None);
+    }
+
+    #[test]
+    fn test_home_is_shortened() {
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(display(&home), "~");
+            assert_eq!(display(&home.join("src")), "~/src");
+        }
+        assert_eq!(display(Path::new("/nonexistent/x")), "/nonexistent/x");
+    }
+}
@@ -0,0 +1,151 @@
+use crate::error::{Result, VshError};
+use crate::executor::job::Job;
+use crate::executor::process::{self, ChildIo};
+use crate::executor::redirect::OpenRedirects;
+use crate::executor::{external, find_program, report_error, Shell};
+use crate::parser::{parse_assignment, Command, Intent, Pipeline};
+use nix::unistd::Pid;
+use std::fs::File;
+use std::io::{self, IsTerminal};
+
+impl Shell {
+    /// Execute a pipeline, connecting each stage's stdout to the next stage's
+    /// stdin. The pipeline's exit status is that of its last stage, inverted
+    /// if it was written with `not`.
+    pub fn execute_pipeline(&mut self, pipeline: &Pipeline) -> Result<i32> {
+        let status = self.run_stages(pipeline)?;
+
+        Ok(match (pipeline.negated, status) {
+            (false, status) => status,
+            (true, 0) => 1,
+            (true, _) => 0,
+        })
+    }
+
+    fn run_stages(&mut self, pipeline: &Pipeline) -> Result<i32> {
+        // A lone assignment changes this shell rather than running anything
+        if let [stage] = pipeline.stages.as_slice() {
+            if let Some(assignment) = parse_assignment(&stage.tokens) {
+                return self.assign(assignment);
+            }
+        }
+
+        let mut stages = pipeline
+            .stages
+            .iter()
+            .map(|stage| self.prepare(stage))
+            .collect::<Result<Vec<_>>>()?;
+
+        if stages.len() == 1 && !pipeline.background {
+            return self.execute_command(stages.remove(0));
+        }
+
+        // Make sure every program exists before starting any of them
+        for stage in &stages {
+            if let Intent::External(program) = &stage.intent {
+                if find_program(program).is_none() {
+                    return Err(VshError::UnknownCommand(program.clone()));
+                }
+            }
+        }
+
+        let last_name = stage_name(&stages[stages.len() - 1]);
+        let count = stages.len();
+        let mut pids: Vec<Pid> = Vec::with_capacity(count);
+        // Without job control a background job can't have the terminal, so
+        // it reads nothing rather than fight the shell for input
+        let mut previous_read = match pipeline.background && !self.jobs.job_control() {
+            true => Some(File::open("/dev/null")?),
+            false => None,
+        };
+
+        for (i, stage) in stages.into_iter().enumerate() {
+            let (next_read, write) = if i + 1 < count {
+                let (reader, writer) = process::pipe()?;
+                (Some(reader), Some(writer))
+            } else {
+                (None, None)
+            };
+
+            // Redirections win over the pipe, as in bash
+            let spawned = OpenRedirects::open(&stage.redirects).and_then(|redirects| {
+                let mut child_io = redirects.child_io(previous_read.as_ref(), write.as_ref());
+                child_io.group = self.jobs.group(pids.first().copied(), pipeline.background);
+                self.spawn_stage(stage, child_io)
+            });
+
+            let pid = match spawned {
+                Ok(pid) => pid,
+                Err(e) => {
+                    // Don't leave already started stages behind
+                    drop(write);
+                    drop(previous_read);
+                    let _ = process::wait_all(&pids);
+                    self.jobs.take_terminal();
+                    return Err(e);
+                }
+            };
+            pids.push(pid);
+
+            // Our copies of the pipe ends must close, or readers never see EOF
+            previous_read = next_read;
+        }
+
+        let job = Job::new(pids, describe(pipeline), last_name);
+        self.start_job(job, pipeline.background)
+    }
+
+    /// Start one stage of a pipeline in its own process
+    fn spawn_stage(&mut self, stage: Command, io: ChildIo) -> Result<Pid> {
+        if let Intent::External(_) = stage.intent {
+            return external::spawn_external(&stage, io);
+        }
+
+        // Built-ins run in a forked copy of the shell, just like in bash
+        let to_terminal = io.stdout.is_none() && io::stdout().is_terminal();
+        process::spawn(io, move || {
+            if !to_terminal {
+                colored::control::set_override(false);
+            }
+            self.run_internal(stage, &mut io::stdout())
+                .unwrap_or_else(|e| {
+                    report_error(&e);
+                    e.exit_code()
+                })
+        })
+    }
+}
+
+/// A pipeline as written, for `jobs`
+fn describe(pipeline: &Pipeline) -> String {
+    pipeline
+        .stages
+        .iter()
+        .map(|stage| {
+            let words: Vec<&str> = stage.tokens.iter().map(|t| t.value.as_str()).collect();
+            words.join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// Name used when reporting a stage's exit status
+fn stage_name(stage: &Command) -> String {
+    match &stage.intent {
+        Intent::External(program) | Intent::Function(program) => program.clone(),
+        intent => format!("{:?}", intent).to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_pipeline;
+
+    #[test]
+    fn test_missing_program_starts_nothing() {
+        let pipeline = parse_pipeline("list | definitely-not-a-real-program-vsh").unwrap();
+        let result = Shell::new().execute_pipeline(&pipeline);
+        assert!(matches!(result, Err(VshError::UnknownCommand(_))));
+    }
+}
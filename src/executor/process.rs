@@ -0,0 +1,169 @@
+use crate::error::{Result, VshError};
+use nix::errno::Errno;
+use nix::sys::signal::{self, SigHandler, Signal};
+use nix::sys::wait::{waitpid, WaitStatus};
+use nix::unistd::{dup2, fork, setpgid, tcsetpgrp, ForkResult, Pid};
+use std::fs::File;
+use std::io::{self, Write};
+use std::os::fd::{AsRawFd, OwnedFd};
+
+/// Standard streams handed to a child process (None inherits the shell's),
+/// and the process group it joins
+#[derive(Default)]
+pub struct ChildIo<'a> {
+    pub stdin: Option<&'a File>,
+    pub stdout: Option<&'a File>,
+    pub stderr: Option<&'a File>,
+    /// Send stderr wherever stdout ended up (`2>&1`)
+    pub stderr_to_stdout: bool,
+    pub group: Group,
+}
+
+/// Which process group a child runs in
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Group {
+    /// The shell's own, as when job control is off
+    #[default]
+    Shell,
+    /// The shell's own, but deaf to Ctrl-C: a background job in a script
+    ShellBackground,
+    /// A job's own group, led by its first process (`None` for the first
+    /// process itself). A foreground job also gets the terminal.
+    Job {
+        leader: Option<Pid>,
+        foreground: bool,
+    },
+}
+
+/// Create a pipe as a (read end, write end) pair of files.
+/// Both ends are close-on-exec, so only the stages they are handed to keep them.
+pub fn pipe() -> Result<(File, File)> {
+    let (reader, writer) = io::pipe()?;
+    Ok((
+        File::from(OwnedFd::from(reader)),
+        File::from(OwnedFd::from(writer)),
+    ))
+}
+
+/// Fork a child with its streams wired up and run `body` in it.
+/// `body` either replaces the process (exec) or returns its exit code.
+pub fn spawn<F>(io: ChildIo, body: F) -> Result<Pid>
+where
+    F: FnOnce() -> i32,
+{
+    // Anything still buffered would otherwise be written twice
+    io::stdout().flush()?;
+    io::stderr().flush()?;
+
+    // SAFETY: vsh is single-threaded when it forks, so the child cannot
+    // inherit a lock held by another thread.
+    match unsafe { fork() } {
+        Ok(ForkResult::Child) => {
+            join_group(io.group, Pid::from_raw(0));
+            let keyboard = match io.group {
+                Group::ShellBackground => SigHandler::SigIgn,
+                _ => SigHandler::SigDfl,
+            };
+            unsafe {
+                let _ = signal::signal(Signal::SIGINT, keyboard);
+                let _ = signal::signal(Signal::SIGQUIT, keyboard);
+                // An interactive shell ignores these and catches SIGCHLD;
+                // what it runs mustn't
+                for sig in [
+                    Signal::SIGTSTP,
+                    Signal::SIGTTIN,
+                    Signal::SIGTTOU,
+                    Signal::SIGCHLD,
+                ] {
+                    let _ = signal::signal(sig, SigHandler::SigDfl);
+                }
+            }
+            if let Some(stdin) = io.stdin {
+                let _ = dup2(stdin.as_raw_fd(), 0);
+            }
+            if let Some(stdout) = io.stdout {
+                let _ = dup2(stdout.as_raw_fd(), 1);
+            }
+            if let Some(stderr) = io.stderr {
+                let _ = dup2(stderr.as_raw_fd(), 2);
+            } else if io.stderr_to_stdout {
+                let _ = dup2(1, 2);
+            }
+            let code = body();
+            let _ = io::stdout().flush();
+            std::process::exit(code);
+        }
+        Ok(ForkResult::Parent { child }) => {
+            // Both sides join the group, so neither can run ahead of it
+            join_group(io.group, child);
+            Ok(child)
+        }
+        Err(e) => Err(VshError::ExecutionError(format!(
+            "Could not start process: {}",
+            e
+        ))),
+    }
+}
+
+/// Put a new child (`0` for the calling process) in its job's group, and a
+/// foreground job in charge of the terminal
+fn join_group(group: Group, child: Pid) {
+    if let Group::Job { leader, foreground } = group {
+        let leader = leader.unwrap_or(child);
+        let _ = setpgid(child, leader);
+        if foreground {
+            let leader = if leader.as_raw() == 0 {
+                nix::unistd::getpid()
+            } else {
+                leader
+            };
+            let _ = tcsetpgrp(libc::STDIN_FILENO, leader);
+        }
+    }
+}
+
+/// Wait for every child in order, keeping Ctrl-C away from the shell meanwhile
+pub fn wait_all(pids: &[Pid]) -> Result<Vec<WaitStatus>> {
+    let _guard = ForegroundGuard::new();
+
+    pids.iter()
+        .map(|&pid| loop {
+            match waitpid(pid, None) {
+                Err(Errno::EINTR) => continue,
+                Ok(status) => break Ok(status),
+                Err(e) => break Err(VshError::ExecutionError(format!("wait failed: {}", e))),
+            }
+        })
+        .collect()
+}
+
+/// Ignores SIGINT/SIGQUIT in the shell while foreground children run,
+/// restoring the previous handlers when dropped
+pub struct ForegroundGuard {
+    old_int: Option<SigHandler>,
+    old_quit: Option<SigHandler>,
+}
+
+impl ForegroundGuard {
+    pub fn new() -> Self {
+        unsafe {
+            Self {
+                old_int: signal::signal(Signal::SIGINT, SigHandler::SigIgn).ok(),
+                old_quit: signal::signal(Signal::SIGQUIT, SigHandler::SigIgn).ok(),
+            }
+        }
+    }
+}
+
+impl Drop for ForegroundGuard {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(handler) = self.old_int {
+                let _ = signal::signal(Signal::SIGINT, handler);
+            }
+            if let Some(handler) = self.old_quit {
+                let _ = signal::signal(Signal::SIGQUIT, handler);
+            }
+        }
+    }
+}
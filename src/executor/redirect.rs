@@ -0,0 +1,247 @@
+use crate::error::{Result, VshError};
+use crate::executor::process::ChildIo;
+use crate::parser::Redirect;
+use nix::unistd::{close, dup, dup2};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::os::fd::{AsRawFd, RawFd};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Files opened for a command's redirections. `None` keeps the default stream
+/// (the terminal, or a pipe when the command is part of a pipeline).
+#[derive(Debug, Default)]
+pub struct OpenRedirects {
+    pub stdin: Option<File>,
+    pub stdout: Option<File>,
+    pub stderr: Option<File>,
+    /// `2>&1` was given while stdout was still the default stream
+    pub stderr_to_stdout: bool,
+}
+
+impl OpenRedirects {
+    /// Open every redirection target in the order they were written
+    pub fn open(redirects: &[Redirect]) -> Result<Self> {
+        let mut open = Self::default();
+
+        for redirect in redirects {
+            match redirect {
+                Redirect::Output(path) => open.stdout = Some(create(path, false)?),
+                Redirect::Append(path) => open.stdout = Some(create(path, true)?),
+                Redirect::Input(path) => {
+                    open.stdin = Some(File::open(path).map_err(|e| file_error(path, e))?)
+                }
+                Redirect::Text(text) => open.stdin = Some(text_file(text)?),
+                Redirect::Errors(path) => {
+                    open.stderr = Some(create(path, false)?);
+                    open.stderr_to_stdout = false;
+                }
+                Redirect::ErrorsAppend(path) => {
+                    open.stderr = Some(create(path, true)?);
+                    open.stderr_to_stdout = false;
+                }
+                Redirect::ErrorsToOutput => match &open.stdout {
+                    Some(stdout) => open.stderr = Some(stdout.try_clone()?),
+                    None => {
+                        open.stderr = None;
+                        open.stderr_to_stdout = true;
+                    }
+                },
+            }
+        }
+
+        Ok(open)
+    }
+
+    /// Streams for a child process, falling back to the given defaults
+    pub fn child_io<'a>(
+        &'a self,
+        stdin: Option<&'a File>,
+        stdout: Option<&'a File>,
+    ) -> ChildIo<'a> {
+        ChildIo {
+            stdin: self.stdin.as_ref().or(stdin),
+            stdout: self.stdout.as_ref().or(stdout),
+            stderr: self.stderr.as_ref(),
+            stderr_to_stdout: self.stderr_to_stdout,
+            ..ChildIo::default()
+        }
+    }
+
+    /// Point the shell's own stdin at the `<` redirection, if there is one,
+    /// until the returned guard is dropped
+    pub fn redirect_stdin(&self) -> Result<RestoreStreams> {
+        let mut restore = RestoreStreams {
+            saved: Vec::new(),
+            colors_off: false,
+        };
+        if let Some(file) = &self.stdin {
+            restore.save(0)?;
+            dup2(file.as_raw_fd(), 0).map_err(io::Error::from)?;
+        }
+        Ok(restore)
+    }
+
+    /// Point the shell's own stderr at the `2>` redirection, if there is one,
+    /// until the returned guard is dropped
+    pub fn redirect_stderr(&self) -> Result<RestoreStreams> {
+        io::stderr().flush()?;
+
+        let mut restore = RestoreStreams {
+            saved: Vec::new(),
+            colors_off: false,
+        };
+        if let Some(file) = &self.stderr {
+            restore.save(2)?;
+            dup2(file.as_raw_fd(), 2).map_err(io::Error::from)?;
+        } else if self.stderr_to_stdout {
+            restore.save(2)?;
+            dup2(1, 2).map_err(io::Error::from)?;
+        }
+        Ok(restore)
+    }
+
+    /// Point the shell's own stdin, stdout and stderr at the redirections
+    /// until the returned guard is dropped. For commands that run inside the
+    /// shell but may start programs, like functions.
+    pub fn redirect_shell(&self) -> Result<RestoreStreams> {
+        io::stdout().flush()?;
+        io::stderr().flush()?;
+
+        let mut restore = RestoreStreams {
+            saved: Vec::new(),
+            colors_off: self.stdout.is_some(),
+        };
+        let targets = [
+            (0, self.stdin.as_ref()),
+            (1, self.stdout.as_ref()),
+            (2, self.stderr.as_ref()),
+        ];
+        for (fd, file) in targets {
+            if let Some(file) = file {
+                restore.save(fd)?;
+                dup2(file.as_raw_fd(), fd).map_err(io::Error::from)?;
+            }
+        }
+        if self.stderr_to_stdout {
+            restore.save(2)?;
+            dup2(1, 2).map_err(io::Error::from)?;
+        }
+
+        if restore.colors_off {
+            // Files get plain text, not terminal color codes
+            colored::control::set_override(false);
+        }
+        Ok(restore)
+    }
+}
+
+/// The shell's streams from before `redirect_shell`, put back when dropped
+pub struct RestoreStreams {
+    /// Each redirected descriptor with a copy of what it was
+    saved: Vec<(RawFd, RawFd)>,
+    colors_off: bool,
+}
+
+impl RestoreStreams {
+    fn save(&mut self, fd: RawFd) -> Result<()> {
+        let copy = dup(fd).map_err(io::Error::from)?;
+        self.saved.push((fd, copy));
+        Ok(())
+    }
+}
+
+impl Drop for RestoreStreams {
+    fn drop(&mut self) {
+        let _ = io::stdout().flush();
+        let _ = io::stderr().flush();
+        for (fd, copy) in self.saved.drain(..).rev() {
+            let _ = dup2(copy, fd);
+            let _ = close(copy);
+        }
+        if self.colors_off {
+            colored::control::unset_override();
+        }
+    }
+}
+
+/// Open a file for writing, truncating or appending
+fn create(path: &str, append: bool) -> Result<File> {
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .map_err(|e| file_error(path, e))
+}
+
+/// A file holding `text`, ready to be read from the start. It is removed
+/// at once, so it disappears when the last command reading it is done.
+fn text_file(text: &str) -> Result<File> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let path = env::temp_dir().join(format!(
+        "vsh-input-{}-{}",
+        process::id(),
+        COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    fs::remove_file(&path)?;
+    file.write_all(text.as_bytes())?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+/// Map an open failure onto the friendliest error we have
+fn file_error(path: &str, e: io::Error) -> VshError {
+    match e.kind() {
+        io::ErrorKind::NotFound => VshError::FileNotFound(path.to_string()),
+        io::ErrorKind::PermissionDenied => VshError::PermissionDenied(path.to_string()),
+        _ => VshError::IoError(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_output_then_errors_to_output() {
+        let dir = tempdir().unwrap();
+        let out = dir.path().join("out.txt");
+        let redirects = vec![
+            Redirect::Output(out.to_string_lossy().to_string()),
+            Redirect::ErrorsToOutput,
+        ];
+
+        let open = OpenRedirects::open(&redirects).unwrap();
+        assert!(open.stdout.is_some());
+        assert!(open.stderr.is_some());
+        assert!(!open.stderr_to_stdout);
+    }
+
+    #[test]
+    fn test_missing_input_file() {
+        let redirects = vec![Redirect::Input("no-such-input.txt".to_string())];
+        let result = OpenRedirects::open(&redirects);
+        assert!(matches!(result, Err(VshError::FileNotFound(_))));
+    }
+
+    #[test]
+    fn test_text_input() {
+        use std::io::Read;
+
+        let redirects = vec![Redirect::Text("line one\nline two\n".to_string())];
+        let mut stdin = OpenRedirects::open(&redirects).unwrap().stdin.unwrap();
+        let mut text = String::new();
+        stdin.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "line one\nline two\n");
+    }
+}
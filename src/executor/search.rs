@@ -0,0 +1,328 @@
+use crate::error::{Result, VshError};
+use crate::executor::copy::path_error;
+use crate::executor::report_error;
+use crate::parser::Command;
+use colored::*;
+use regex::{Regex, RegexBuilder};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// How `grep` matches and what it prints, from its flags
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SearchOptions {
+    pub ignore_case: bool,
+    /// Only whole words match
+    pub word: bool,
+    /// The pattern is plain text rather than a regular expression
+    pub literal: bool,
+    pub line_numbers: bool,
+    /// Print how many lines match instead of the lines
+    pub count: bool,
+    pub recursive: bool,
+    /// Lines that don't match
+    pub invert: bool,
+    /// Print only the names of files with a match
+    pub files_only: bool,
+}
+
+impl SearchOptions {
+    pub fn from_flags(flags: &[String]) -> Result<Self> {
+        let mut options = SearchOptions::default();
+
+        for flag in flags {
+            if let Some(long) = flag.strip_prefix("--") {
+                options.set(long, flag)?;
+            } else {
+                for short in flag.chars().skip(1) {
+                    options.set(&short.to_string(), flag)?;
+                }
+            }
+        }
+
+        Ok(options)
+    }
+
+    fn set(&mut self, option: &str, flag: &str) -> Result<()> {
+        match option {
+            "i" | "ignore-case" => self.ignore_case = true,
+            "w" | "word" | "word-regexp" | "whole-word" | "whole-words" => self.word = true,
+            "F" | "literal" | "fixed-strings" => self.literal = true,
+            "E" | "regex" | "extended-regexp" => self.literal = false,
+            "n" | "line-number" | "line-numbers" => self.line_numbers = true,
+            "c" | "count" => self.count = true,
+            "r" | "R" | "recursive" => self.recursive = true,
+            "v" | "invert" | "invert-match" => self.invert = true,
+            "l" | "files-with-matches" => self.files_only = true,
+            _ => {
+                return Err(VshError::InvalidSyntax(format!(
+                    "Unknown option '{}'",
+                    flag
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    /// The pattern as a regular expression, with the options applied
+    fn regex(&self, pattern: &str) -> Result<Regex> {
+        let mut expression = if self.literal {
+            regex::escape(pattern)
+        } else {
+            pattern.to_string()
+        };
+        if self.word {
+            expression = format!(r"\b(?:{})\b", expression);
+        }
+
+        RegexBuilder::new(&expression)
+            .case_insensitive(self.ignore_case)
+            .build()
+            .map_err(|e| {
+                VshError::InvalidSyntax(format!(
+                    "Invalid pattern '{}' (use -F or 'literally' to search for the text itself): {}",
+                    pattern, e
+                ))
+            })
+    }
+}
+
+/// Search files, directories or stdin for lines matching a pattern. The
+/// status is 0 if anything matched and 1 if nothing did, as with `grep`, or
+/// 2 if some of several files couldn't be read.
+pub fn execute_search(cmd: &Command, out: &mut dyn Write) -> Result<i32> {
+    let pattern = cmd
+        .args
+        .pattern
+        .as_deref()
+        .ok_or_else(|| VshError::InvalidSyntax("Missing pattern to search for".to_string()))?;
+    let options = SearchOptions::from_flags(&cmd.args.flags)?;
+    let mut searcher = Searcher {
+        regex: options.regex(pattern)?,
+        options,
+        show_names: false,
+        out,
+    };
+
+    let mut inputs: Vec<&String> = cmd.args.sources.iter().chain(&cmd.args.path).collect();
+    let default_root = ".".to_string();
+    if inputs.is_empty() {
+        if !options.recursive {
+            let matched = searcher.search(None, &mut io::stdin().lock())?;
+            return Ok(if matched { 0 } else { 1 });
+        }
+        inputs.push(&default_root);
+    }
+
+    // With one file its error is the command's error, as for `copy`
+    if let [input] = inputs.as_slice() {
+        if !options.recursive || !Path::new(input).is_dir() {
+            let matched = searcher.search_file(Path::new(input))?;
+            return Ok(if matched { 0 } else { 1 });
+        }
+    }
+
+    searcher.show_names = true;
+    let mut matched = false;
+    let mut failed = false;
+    for input in inputs {
+        let files = match files_below(Path::new(input), options.recursive) {
+            Ok(files) => files,
+            Err(e) => {
+                report_error(&e);
+                failed = true;
+                continue;
+            }
+        };
+        for file in files {
+            match searcher.search_file(&file) {
+                Ok(found) => matched |= found,
+                Err(e) => {
+                    report_error(&e);
+                    failed = true;
+                }
+            }
+        }
+    }
+
+    Ok(match (failed, matched) {
+        (true, _) => 2,
+        (false, true) => 0,
+        (false, false) => 1,
+    })
+}
+
+/// A file as is, or every file below a directory when searching
+/// recursively. Hidden entries are skipped and links to directories aren't
+/// followed.
+fn files_below(path: &Path, recursive: bool) -> Result<Vec<PathBuf>> {
+    let meta = path
+        .metadata()
+        .map_err(|_| VshError::FileNotFound(path.display().to_string()))?;
+    if !meta.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    if !recursive {
+        return Err(directory_error(path));
+    }
+
+    let mut entries: Vec<_> = fs::read_dir(path)
+        .map_err(|e| path_error(path, e))?
+        .flatten()
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .collect();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let child = if path == Path::new(".") {
+            PathBuf::from(entry.file_name())
+        } else {
+            entry.path()
+        };
+        match entry.file_type() {
+            Ok(kind) if kind.is_dir() => files.extend(files_below(&child, true)?),
+            Ok(kind) if kind.is_symlink() && child.is_dir() => {}
+            _ => files.push(child),
+        }
+    }
+    Ok(files)
+}
+
+fn directory_error(path: &Path) -> VshError {
+    VshError::ExecutionError(format!(
+        "'{}' is a directory; use 'grep -r' or 'search ... in {} recursively'",
+        path.display(),
+        path.display()
+    ))
+}
+
+struct Searcher<'a> {
+    regex: Regex,
+    options: SearchOptions,
+    /// Put the file name in front of each line, when there are several
+    show_names: bool,
+    out: &'a mut dyn Write,
+}
+
+impl Searcher<'_> {
+    fn search_file(&mut self, path: &Path) -> Result<bool> {
+        if path.is_dir() {
+            return Err(directory_error(path));
+        }
+        let file = File::open(path).map_err(|e| path_error(path, e))?;
+        let name = path.display().to_string();
+        self.search(Some(&name), &mut BufReader::new(file))
+    }
+
+    /// Print the matching lines of one input and return whether any matched
+    fn search(&mut self, name: Option<&str>, reader: &mut dyn BufRead) -> Result<bool> {
+        let prefix = match name {
+            Some(name) if self.show_names => format!("{}{}", name.magenta(), ":".cyan()),
+            _ => String::new(),
+        };
+        let mut count = 0;
+        let mut line = Vec::new();
+        let mut number = 0;
+
+        while reader.read_until(b'\n', &mut line)? > 0 {
+            number += 1;
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+            let text = String::from_utf8_lossy(&line);
+
+            if self.regex.is_match(&text) != self.options.invert {
+                count += 1;
+                if line.contains(&0) && !self.options.count {
+                    // Binary files would only garble the terminal
+                    writeln!(
+                        self.out,
+                        "Binary file {} matches",
+                        name.unwrap_or("(input)")
+                    )?;
+                    return Ok(true);
+                }
+                if self.options.files_only {
+                    break;
+                }
+                if !self.options.count {
+                    self.print_line(&prefix, number, &text)?;
+                }
+            }
+            line.clear();
+        }
+
+        if self.options.files_only && count > 0 {
+            writeln!(self.out, "{}", name.unwrap_or("(standard input)").magenta())?;
+        } else if self.options.count && !self.options.files_only {
+            writeln!(self.out, "{}{}", prefix, count)?;
+        }
+        Ok(count > 0)
+    }
+
+    fn print_line(&mut self, prefix: &str, number: usize, text: &str) -> Result<()> {
+        let number = if self.options.line_numbers {
+            format!("{}{}", number.to_string().green(), ":".cyan())
+        } else {
+            String::new()
+        };
+
+        let mut highlighted = String::new();
+        let mut last = 0;
+        if !self.options.invert {
+            for found in self.regex.find_iter(text) {
+                highlighted.push_str(&text[last..found.start()]);
+                highlighted.push_str(&found.as_str().red().bold().to_string());
+                last = found.end();
+            }
+        }
+        highlighted.push_str(&text[last..]);
+
+        writeln!(self.out, "{}{}{}", prefix, number, highlighted)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(flags: &[&str], pattern: &str, text: &str) -> (bool, String) {
+        colored::control::set_override(false);
+        let flags: Vec<String> = flags.iter().map(|f| f.to_string()).collect();
+        let options = SearchOptions::from_flags(&flags).unwrap();
+        let mut out = Vec::new();
+        let mut searcher = Searcher {
+            regex: options.regex(pattern).unwrap(),
+            options,
+            show_names: false,
+            out: &mut out,
+        };
+        let matched = searcher.search(None, &mut text.as_bytes()).unwrap();
+        (matched, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_patterns_and_options() {
+        let text = "Error: disk full\nwarning\nerrors: 2\nthe a.b field\n";
+        assert_eq!(search(&[], "error", text).1, "errors: 2\n");
+        assert_eq!(search(&["-i", "-w"], "error", text).1, "Error: disk full\n");
+        assert_eq!(
+            search(&["-in"], "^e", text).1,
+            "1:Error: disk full\n3:errors: 2\n"
+        );
+        assert_eq!(search(&["-c", "-i"], "error", text).1, "2\n");
+        assert_eq!(search(&["-F"], "a.b", text).1, "the a.b field\n");
+        assert!(!search(&["-F"], "a.", "abc\n").0);
+        assert_eq!(search(&["-v", "-c"], "r", text).1, "1\n");
+    }
+
+    #[test]
+    fn test_invalid_pattern_suggests_literal() {
+        let options = SearchOptions::default();
+        let error = options.regex("a(b").unwrap_err().to_string();
+        assert!(error.contains("literally"));
+        assert!(SearchOptions::from_flags(&["-z".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,427 @@
+use crate::error::{Result, VshError};
+use crate::executor::control::Flow;
+use crate::executor::environment::{print_environment, ScopedEnv};
+use crate::executor::expand::{expand_words, NoMatch};
+use crate::executor::job::{Job, JobTable};
+use crate::executor::redirect::OpenRedirects;
+use crate::executor::{builtin, condition, external, report_error, search, Value, ValueType};
+use crate::parser::{
+    self, Assignment, Command, CommandList, Connector, FunctionDef, Intent, SimpleCommand,
+};
+use colored::*;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// A shell session: everything that outlives a single command
+#[derive(Debug, Clone, Default)]
+pub struct Shell {
+    /// Name of the running script, or "vsh" ($0)
+    pub script_name: String,
+    /// Positional parameters ($1, $2, ... and $@)
+    pub positional: Vec<String>,
+    /// Shell variables set with `name = value`, `set`, `let` or `var`
+    pub variables: HashMap<String, String>,
+    /// Types given in declarations like `set count: number = 42`; later
+    /// assignments to the variable must keep to them
+    pub(crate) declared: HashMap<String, ValueType>,
+    /// Functions defined with `function name … end`
+    pub functions: HashMap<String, Rc<FunctionDef>>,
+    /// One frame of parameters and `local` variables per running function
+    pub(crate) locals: Vec<HashMap<String, String>>,
+    /// What the last `return` handed back, for `result = some-function`
+    pub(crate) return_value: Option<String>,
+    /// Set once `exit` has run; the session should end with this status
+    pub exit_status: Option<i32>,
+    /// Status of the last command that finished ($?)
+    pub last_status: i32,
+    /// A `break`, `continue` or `return` that hasn't reached its loop or
+    /// function yet
+    pub(crate) flow: Option<Flow>,
+    /// How many loops are running, so `break` outside one is an error
+    pub(crate) loop_depth: usize,
+    /// What a glob that matches nothing does
+    pub no_match: NoMatch,
+    /// Print every parsed line before running it
+    pub debug: bool,
+    /// Directories saved by `pushd`, the most recent last
+    pub(crate) dir_stack: Vec<PathBuf>,
+    /// Jobs running in the background or stopped with Ctrl-Z
+    pub(crate) jobs: JobTable,
+}
+
+impl Shell {
+    pub fn new() -> Self {
+        Self {
+            script_name: "vsh".to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Parse and run one entry of input, which may span several lines.
+    /// Parse errors are returned (`VshError::Incomplete` if a block or quote
+    /// is still open); execution errors are reported as they happen and show
+    /// up in the exit status.
+    pub fn run_line(&mut self, line: &str) -> Result<i32> {
+        let statements = parser::parse_script(line)?;
+
+        if self.debug {
+            println!("{} {:?}", "Parsed:".cyan(), statements);
+        }
+
+        Ok(self.execute_block(&statements))
+    }
+
+    /// Run a script file with the given positional arguments
+    pub fn run_script(&mut self, path: &str, args: Vec<String>) -> Result<i32> {
+        let source = fs::read_to_string(path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => VshError::FileNotFound(path.to_string()),
+            io::ErrorKind::PermissionDenied => VshError::PermissionDenied(path.to_string()),
+            _ => VshError::IoError(e),
+        })?;
+
+        self.script_name = path.to_string();
+        self.positional = args;
+
+        self.run_source(&source, path)
+    }
+
+    /// Run script source one statement at a time. A statement runs as soon
+    /// as its last line is read, so a block runs once its `end` is reached.
+    /// Blank lines and `#` comments (including a shebang) are skipped. A
+    /// parse error stops the script; a failing command does not. Returns the
+    /// status of the last command run.
+    pub fn run_source(&mut self, source: &str, name: &str) -> Result<i32> {
+        let mut status = 0;
+        let mut pending = String::new();
+        let mut first_line = 0;
+
+        for (number, line) in source.lines().enumerate() {
+            if pending.is_empty() {
+                let trimmed = line.trim();
+                if trimmed.is_empty() || trimmed.starts_with('#') {
+                    continue;
+                }
+                first_line = number + 1;
+            }
+            pending.push_str(line);
+            pending.push('\n');
+
+            status = match self.run_line(&pending) {
+                Ok(status) => status,
+                Err(VshError::Incomplete(_)) => continue,
+                Err(e) => return Err(script_error(name, first_line, e)),
+            };
+            pending.clear();
+
+            if let Some(exit_status) = self.exit_status {
+                return Ok(exit_status);
+            }
+        }
+
+        match parser::parse_script(&pending) {
+            Err(e) => Err(script_error(name, first_line, e)),
+            Ok(_) => Ok(status),
+        }
+    }
+
+    /// Execute a list of pipelines, honoring each connector, and return the
+    /// exit status of the last pipeline that ran. Errors are reported as they
+    /// happen and count as a failed pipeline.
+    pub fn execute_list(&mut self, list: &CommandList) -> i32 {
+        let mut status = 0;
+
+        for (connector, pipeline) in &list.items {
+            let should_run = match connector {
+                Connector::Always => true,
+                Connector::OnSuccess => status == 0,
+                Connector::OnFailure => status != 0,
+            };
+
+            if should_run {
+                status = self.execute_pipeline(pipeline).unwrap_or_else(|e| {
+                    report_error(&e);
+                    e.exit_code()
+                });
+                self.last_status = status;
+            }
+
+            if let Some(exit_status) = self.exit_status {
+                return exit_status;
+            }
+            if self.flow.is_some() {
+                break;
+            }
+        }
+
+        status
+    }
+
+    /// Set a variable to its expanded value, or to what the function it
+    /// names returns. The status is that of the function, if one ran.
+    pub fn assign(&mut self, assignment: Assignment) -> Result<i32> {
+        if let Some(result) = self.assign_call(&assignment) {
+            return result;
+        }
+
+        let value = self.assigned_value(&assignment)?;
+        self.store(&assignment, value)?;
+        Ok(0)
+    }
+
+    /// Store an assigned value; `local` puts it in the running function's
+    /// frame. A value that doesn't fit the variable's declared type is an
+    /// error, and the variable keeps its old value.
+    pub(crate) fn store(&mut self, assignment: &Assignment, value: String) -> Result<()> {
+        let declared = match &assignment.declared {
+            Some(kind) => Some(ValueType::parse(kind)?),
+            None if assignment.local => None,
+            None => self.declared.get(&assignment.name).copied(),
+        };
+        if let Some(kind) = declared {
+            kind.check(&assignment.name, &value)?;
+        }
+
+        match self.locals.last_mut() {
+            Some(frame) if assignment.local => {
+                frame.insert(assignment.name.clone(), value);
+            }
+            _ => {
+                if let Some(kind) = declared {
+                    self.declared.insert(assignment.name.clone(), kind);
+                }
+                self.set_variable(assignment.name.clone(), value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Set a variable. A parameter or local of the running function is
+    /// changed in place, and one that was exported stays in the environment
+    /// so programs started later see the new value.
+    pub fn set_variable(&mut self, name: String, value: String) {
+        if let Some(local) = self
+            .locals
+            .last_mut()
+            .and_then(|frame| frame.get_mut(&name))
+        {
+            *local = value;
+        } else if !self.variables.contains_key(&name) && env::var_os(&name).is_some() {
+            env::set_var(name, value);
+        } else {
+            self.variables.insert(name, value);
+        }
+    }
+
+    /// The expanded value of an assignment; several words are joined with
+    /// spaces, and a list literal keeps its items' quotes so their types
+    /// survive
+    fn assigned_value(&self, assignment: &Assignment) -> Result<String> {
+        if let Ok(Some(items)) = parser::parse_list(&assignment.value) {
+            let items = items
+                .iter()
+                .map(|item| {
+                    let text = self.expand_string(item)?;
+                    Ok(match item.is_quoted {
+                        true => Value::Text(text),
+                        false => Value::infer(&text),
+                    })
+                })
+                .collect::<Result<_>>()?;
+            return Ok(Value::List(items).to_string());
+        }
+
+        Ok(assignment
+            .value
+            .iter()
+            .map(|token| self.expand_string(token))
+            .collect::<Result<Vec<_>>>()?
+            .join(" "))
+    }
+
+    /// Expand a command's words and translate them into a canonical
+    /// `Command`. A function of the same name wins over any built-in.
+    pub fn prepare(&self, stage: &SimpleCommand) -> Result<Command> {
+        let (prefix, tokens) = parser::split_env_prefix(&stage.tokens);
+        let tokens = expand_words(self.expand_tokens(tokens)?, self.no_match)?;
+        let mut cmd = match tokens.first() {
+            Some(first) if self.functions.contains_key(&first.value) => {
+                parser::build_function_call(&tokens)?
+            }
+            _ => parser::build_command(&tokens)?,
+        };
+
+        cmd.env = prefix
+            .iter()
+            .map(|assignment| Ok((assignment.name.clone(), self.assigned_value(assignment)?)))
+            .collect::<Result<_>>()?;
+        Ok(cmd)
+    }
+
+    /// Execute a parsed command and return its exit status
+    pub fn execute_command(&mut self, cmd: Command) -> Result<i32> {
+        let redirects = OpenRedirects::open(&cmd.redirects)?;
+
+        match &cmd.intent {
+            Intent::External(program) => {
+                let mut io = redirects.child_io(None, None);
+                io.group = self.jobs.group(None, false);
+                let pid = external::spawn_external(&cmd, io)?;
+                let command = std::iter::once(program)
+                    .chain(&cmd.args.extra)
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                self.wait_foreground(Job::new(vec![pid], command, program.clone()))
+            }
+            Intent::Function(name) => {
+                // The body may start programs, so redirect the shell's own streams
+                let _restore = redirects.redirect_shell()?;
+                let _env = ScopedEnv::set(&cmd.env);
+                self.call_function(name, &cmd.args.extra)
+            }
+            _ => self.run_builtin(cmd, redirects),
+        }
+    }
+
+    /// Run a built-in inside the shell itself, honoring its redirections.
+    /// Its errors are reported here, while `2>` still applies.
+    fn run_builtin(&mut self, cmd: Command, redirects: OpenRedirects) -> Result<i32> {
+        // Built-ins that read, like `grep`, read from `< file`
+        let _stdin = redirects.redirect_stdin()?;
+        // Errors about single items, like one missing file of several, are
+        // printed while the built-in runs
+        let _stderr = redirects.redirect_stderr()?;
+        let result = match redirects.stdout {
+            Some(mut file) => {
+                // Files get plain text, not terminal color codes
+                colored::control::set_override(false);
+                let result = self.run_internal(cmd, &mut file);
+                colored::control::unset_override();
+                result
+            }
+            None => {
+                let result = self.run_internal(cmd, &mut io::stdout());
+                // `echo -n` output mustn't wait behind a program's
+                io::stdout().flush()?;
+                result
+            }
+        };
+
+        Ok(result.unwrap_or_else(|e| {
+            report_error(&e);
+            e.exit_code()
+        }))
+    }
+
+    /// Run a non-external command in this process, writing output to `out`.
+    /// Its `NAME=value` prefixes hold only while it runs.
+    pub(crate) fn run_internal(&mut self, cmd: Command, out: &mut dyn Write) -> Result<i32> {
+        let _env = ScopedEnv::set(&cmd.env);
+        match cmd.intent {
+            Intent::Exit => {
+                let status = match cmd.args.extra.iter().find(|arg| *arg != "with") {
+                    Some(code) => code.parse().map_err(|_| {
+                        VshError::InvalidSyntax(format!("'{}' is not an exit status", code))
+                    })?,
+                    None => 0,
+                };
+                self.exit_status = Some(status);
+                Ok(status)
+            }
+            Intent::Export => self.export(&cmd.args.extra, out),
+            Intent::Unset => self.unset(&cmd.args.extra),
+            Intent::Environment => print_environment(out),
+            Intent::LastStatus => {
+                writeln!(out, "{}", self.last_status)?;
+                Ok(0)
+            }
+            Intent::FileExists | Intent::DirectoryExists | Intent::IsEmpty | Intent::Compare => {
+                condition::check(&cmd)
+            }
+            Intent::Break | Intent::Continue => {
+                let (name, flow) = match cmd.intent {
+                    Intent::Break => ("break", Flow::Break),
+                    _ => ("continue", Flow::Continue),
+                };
+                if self.loop_depth == 0 {
+                    return Err(VshError::InvalidSyntax(format!(
+                        "'{}' only works inside a loop",
+                        name
+                    )));
+                }
+                self.flow = Some(flow);
+                Ok(0)
+            }
+            Intent::Return => self.return_from_function(&cmd.args.extra),
+            Intent::Function(name) => self.call_function(&name, &cmd.args.extra),
+            Intent::Search => search::execute_search(&cmd, out),
+            Intent::PushDirectory => self.push_directory(&cmd, out),
+            Intent::PopDirectory => self.pop_directory(out),
+            Intent::Directories => self.directories(&cmd.args.extra, out),
+            Intent::Jobs => self.list_jobs(&cmd.args.extra, out),
+            Intent::Foreground => self.foreground(&cmd.args.extra, out),
+            Intent::Background => self.background(&cmd.args.extra, out),
+            Intent::Wait => self.wait_for_jobs(&cmd.args.extra),
+            Intent::Kill => self.kill(&cmd.args.extra, out),
+            _ => builtin::execute_builtin(cmd, out),
+        }
+    }
+}
+
+/// Point a parse error at the script line where its statement starts
+fn script_error(name: &str, line: usize, e: VshError) -> VshError {
+    VshError::ParseError(format!("{}:{}: {}", name, line, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_source_skips_comments_and_blank_lines() {
+        let mut shell = Shell::new();
+        let source = "#!/usr/bin/env vsh\n\n# nothing to see\n   \n";
+        assert_eq!(shell.run_source(source, "test.vsh").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_exit_stops_script() {
+        let mut shell = Shell::new();
+        let source = "exit 4\nremove this-line-never-runs.txt";
+        assert_eq!(shell.run_source(source, "test.vsh").unwrap(), 4);
+        assert_eq!(shell.exit_status, Some(4));
+    }
+
+    #[test]
+    fn test_last_status() {
+        let mut shell = Shell::new();
+        shell
+            .run_source(
+                "list /no/such/dir-vsh\nafter = $?\nunknown-command-vsh",
+                "test.vsh",
+            )
+            .unwrap();
+        assert_eq!(shell.variables["after"], "4");
+        assert_eq!(shell.last_status, 127);
+    }
+
+    #[test]
+    fn test_variables_persist_between_lines() {
+        let mut shell = Shell::new();
+        shell
+            .run_source("name = \"World\"\nlet greeting=\"Hello $name\"", "test.vsh")
+            .unwrap();
+        assert_eq!(shell.variables["greeting"], "Hello World");
+    }
+
+    #[test]
+    fn test_parse_error_names_the_line() {
+        let mut shell = Shell::new();
+        let err = shell.run_source("\nlist &&", "broken.vsh").unwrap_err();
+        assert!(err.to_string().contains("broken.vsh:2"));
+    }
+}
@@ -0,0 +1,498 @@
+use crate::error::{Result, VshError};
+use crate::executor::Shell;
+use crate::parser::{parse_list, tokenize, BinaryOp, Expr, UnaryOp};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A value in an expression. Variables hold text; the type is read back
+/// from it: `42` is a number, `true` a boolean and `[1, "a"]` a list.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    Boolean(bool),
+    List(Vec<Value>),
+}
+
+impl Value {
+    /// The value a variable's text stands for
+    pub fn infer(text: &str) -> Value {
+        if let Ok(n) = text.parse() {
+            return Value::Integer(n);
+        }
+        if looks_numeric(text) {
+            if let Ok(n) = text.parse() {
+                return Value::Float(n);
+            }
+        }
+        match text {
+            "true" => return Value::Boolean(true),
+            "false" => return Value::Boolean(false),
+            _ => {}
+        }
+        if text.starts_with('[') && text.ends_with(']') {
+            if let Some(items) = tokenize(text)
+                .ok()
+                .and_then(|tokens| parse_list(&tokens).ok().flatten())
+            {
+                return Value::List(
+                    items
+                        .iter()
+                        .map(|item| match item.is_quoted {
+                            true => Value::Text(item.value.clone()),
+                            false => Value::infer(&item.value),
+                        })
+                        .collect(),
+                );
+            }
+        }
+        Value::Text(text.to_string())
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Integer(_) | Value::Float(_) => "number",
+            Value::Text(_) => "string",
+            Value::Boolean(_) => "boolean",
+            Value::List(_) => "list",
+        }
+    }
+
+    /// The value with its type, for error messages: `string 'abc'`
+    fn describe(&self) -> String {
+        match self {
+            Value::Text(text) => format!("string '{}'", text),
+            _ => format!("{} {}", self.type_name(), self),
+        }
+    }
+
+    fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Integer(n) => Some(*n as f64),
+            Value::Float(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn as_boolean(&self) -> Result<bool> {
+        match self {
+            Value::Boolean(b) => Ok(*b),
+            _ => Err(type_error(format!(
+                "expected true or false, not {}",
+                self.describe()
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Integer(n) => write!(f, "{}", n),
+            // A whole float keeps its `.0`, so it reads back as a float
+            Value::Float(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{:.1}", n),
+            Value::Float(n) => write!(f, "{}", n),
+            Value::Text(text) => write!(f, "{}", text),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::List(items) => {
+                let items: Vec<String> = items
+                    .iter()
+                    .map(|item| match item {
+                        Value::Text(text) if text.contains('"') => format!("'{}'", text),
+                        Value::Text(text) => format!("\"{}\"", text),
+                        _ => item.to_string(),
+                    })
+                    .collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+        }
+    }
+}
+
+/// A type named in a declaration like `set count: number = 42`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueType {
+    Number,
+    Integer,
+    String,
+    Boolean,
+    List,
+}
+
+impl ValueType {
+    pub fn parse(name: &str) -> Result<ValueType> {
+        match name {
+            "number" | "float" => Ok(ValueType::Number),
+            "integer" | "int" => Ok(ValueType::Integer),
+            "string" | "text" => Ok(ValueType::String),
+            "boolean" | "bool" => Ok(ValueType::Boolean),
+            "list" => Ok(ValueType::List),
+            _ => Err(VshError::InvalidSyntax(format!(
+                "Unknown type '{}' (expected number, integer, string, boolean or list)",
+                name
+            ))),
+        }
+    }
+
+    /// Check that a variable declared with this type may hold `text`
+    pub fn check(self, name: &str, text: &str) -> Result<()> {
+        let value = Value::infer(text);
+        let fits = match self {
+            ValueType::Number => matches!(value, Value::Integer(_) | Value::Float(_)),
+            ValueType::Integer => matches!(value, Value::Integer(_)),
+            ValueType::String => true,
+            ValueType::Boolean => matches!(value, Value::Boolean(_)),
+            ValueType::List => matches!(value, Value::List(_)),
+        };
+        match fits {
+            true => Ok(()),
+            false => Err(type_error(format!(
+                "{} is declared as {}, so it cannot hold {}",
+                name,
+                self,
+                value.describe()
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ValueType::Number => "a number",
+            ValueType::Integer => "an integer",
+            ValueType::String => "a string",
+            ValueType::Boolean => "a boolean",
+            ValueType::List => "a list",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Shell {
+    /// Evaluate an expression, as `$(( … ))` does. Values are never
+    /// converted behind your back: adding a string to a number is an error.
+    pub fn evaluate(&self, expr: &Expr) -> Result<Value> {
+        match expr {
+            Expr::Integer(n) => Ok(Value::Integer(*n)),
+            Expr::Float(n) => Ok(Value::Float(*n)),
+            Expr::Text(text) => Ok(Value::Text(text.clone())),
+            Expr::Boolean(b) => Ok(Value::Boolean(*b)),
+            Expr::List(items) => items
+                .iter()
+                .map(|item| self.evaluate(item))
+                .collect::<Result<_>>()
+                .map(Value::List),
+            Expr::Variable(name) => self
+                .parameter(name)
+                .map(|text| Value::infer(&text))
+                .ok_or_else(|| VshError::ExecutionError(format!("'{}' is not set", name))),
+            Expr::Index(value, index) => item(self.evaluate(value)?, self.evaluate(index)?),
+            Expr::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.evaluate(arg))
+                    .collect::<Result<Vec<_>>>()?;
+                call(name, args)
+            }
+            Expr::Unary(UnaryOp::Negate, value) => match self.evaluate(value)? {
+                Value::Integer(n) => n.checked_neg().map(Value::Integer).ok_or_else(too_large),
+                Value::Float(n) => Ok(Value::Float(-n)),
+                value => Err(type_error(format!("cannot negate {}", value.describe()))),
+            },
+            Expr::Unary(UnaryOp::Not, value) => {
+                Ok(Value::Boolean(!self.evaluate(value)?.as_boolean()?))
+            }
+            // `and` and `or` only look at the right side when they need to
+            Expr::Binary(op @ (BinaryOp::And | BinaryOp::Or), lhs, rhs) => {
+                let lhs = self.evaluate(lhs)?.as_boolean()?;
+                if lhs == (*op == BinaryOp::Or) {
+                    return Ok(Value::Boolean(lhs));
+                }
+                Ok(Value::Boolean(self.evaluate(rhs)?.as_boolean()?))
+            }
+            Expr::Binary(op, lhs, rhs) => binary(*op, self.evaluate(lhs)?, self.evaluate(rhs)?),
+        }
+    }
+}
+
+pub(crate) fn binary(op: BinaryOp, lhs: Value, rhs: Value) -> Result<Value> {
+    use Value::*;
+
+    match op {
+        BinaryOp::Equal => return Ok(Boolean(equal(&lhs, &rhs))),
+        BinaryOp::NotEqual => return Ok(Boolean(!equal(&lhs, &rhs))),
+        BinaryOp::Less | BinaryOp::Greater | BinaryOp::LessEqual | BinaryOp::GreaterEqual => {
+            let ordering = order(&lhs, &rhs)?;
+            return Ok(Boolean(match op {
+                BinaryOp::Less => ordering == Ordering::Less,
+                BinaryOp::Greater => ordering == Ordering::Greater,
+                BinaryOp::LessEqual => ordering != Ordering::Greater,
+                _ => ordering != Ordering::Less,
+            }));
+        }
+        _ => {}
+    }
+
+    let result = match (op, &lhs, &rhs) {
+        (BinaryOp::Add, Text(a), Text(b)) => Some(Text(format!("{}{}", a, b))),
+        (BinaryOp::Add, List(a), List(b)) => Some(List([a.clone(), b.clone()].concat())),
+        (BinaryOp::Divide | BinaryOp::Remainder, _, Integer(0)) => {
+            return Err(VshError::ExecutionError("Division by zero".to_string()))
+        }
+        (BinaryOp::Divide | BinaryOp::Remainder, _, Float(b)) if *b == 0.0 => {
+            return Err(VshError::ExecutionError("Division by zero".to_string()))
+        }
+        (_, Integer(a), Integer(b)) => Some(integer(op, *a, *b)?),
+        _ => match (lhs.as_float(), rhs.as_float()) {
+            (Some(a), Some(b)) => Some(Float(match op {
+                BinaryOp::Add => a + b,
+                BinaryOp::Subtract => a - b,
+                BinaryOp::Multiply => a * b,
+                BinaryOp::Divide => a / b,
+                _ => a % b,
+            })),
+            _ => None,
+        },
+    };
+
+    result.ok_or_else(|| {
+        let (a, b) = (lhs.describe(), rhs.describe());
+        type_error(match op {
+            BinaryOp::Add => format!("cannot add {} to {}", b, a),
+            BinaryOp::Subtract => format!("cannot subtract {} from {}", b, a),
+            BinaryOp::Multiply => format!("cannot multiply {} by {}", a, b),
+            BinaryOp::Divide => format!("cannot divide {} by {}", a, b),
+            _ => format!("cannot take the remainder of {} divided by {}", a, b),
+        })
+    })
+}
+
+/// Whole-number arithmetic; a division that doesn't come out even gives a
+/// float
+fn integer(op: BinaryOp, a: i64, b: i64) -> Result<Value> {
+    let result = match op {
+        BinaryOp::Add => a.checked_add(b),
+        BinaryOp::Subtract => a.checked_sub(b),
+        BinaryOp::Multiply => a.checked_mul(b),
+        BinaryOp::Divide if a.checked_rem(b).is_some_and(|r| r != 0) => {
+            return Ok(Value::Float(a as f64 / b as f64))
+        }
+        BinaryOp::Divide => a.checked_div(b),
+        _ => a.checked_rem(b),
+    };
+    result.map(Value::Integer).ok_or_else(too_large)
+}
+
+/// Values of different types are never equal, except `1 == 1.0`
+fn equal(lhs: &Value, rhs: &Value) -> bool {
+    match (lhs, rhs) {
+        (Value::List(a), Value::List(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equal(a, b))
+        }
+        _ => match (lhs.as_float(), rhs.as_float()) {
+            (Some(a), Some(b)) => a == b,
+            _ => lhs == rhs,
+        },
+    }
+}
+
+/// Numbers compare with numbers and strings with strings
+fn order(lhs: &Value, rhs: &Value) -> Result<Ordering> {
+    let ordering = match (lhs, rhs) {
+        (Value::Text(a), Value::Text(b)) => Some(a.cmp(b)),
+        _ => match (lhs.as_float(), rhs.as_float()) {
+            (Some(a), Some(b)) => a.partial_cmp(&b),
+            _ => None,
+        },
+    };
+    ordering.ok_or_else(|| {
+        type_error(format!(
+            "cannot compare {} with {}",
+            lhs.describe(),
+            rhs.describe()
+        ))
+    })
+}
+
+/// `list[i]` or `string[i]`; a negative index counts from the end
+fn item(value: Value, index: Value) -> Result<Value> {
+    let Value::Integer(i) = index else {
+        return Err(type_error(format!(
+            "an index must be a whole number, not {}",
+            index.describe()
+        )));
+    };
+
+    let (length, found) = match &value {
+        Value::List(items) => (
+            items.len(),
+            position(i, items.len()).map(|p| items[p].clone()),
+        ),
+        Value::Text(text) => {
+            let chars: Vec<char> = text.chars().collect();
+            let found = position(i, chars.len()).map(|p| Value::Text(chars[p].to_string()));
+            (chars.len(), found)
+        }
+        _ => return Err(type_error(format!("cannot index {}", value.describe()))),
+    };
+
+    found.ok_or_else(|| {
+        VshError::ExecutionError(format!(
+            "Index {} is out of range for {} of length {}",
+            i,
+            value.type_name(),
+            length
+        ))
+    })
+}
+
+fn position(index: i64, length: usize) -> Option<usize> {
+    let position = match index {
+        i if i < 0 => length.checked_sub(i.unsigned_abs() as usize)?,
+        i => i as usize,
+    };
+    (position < length).then_some(position)
+}
+
+/// The functions an expression can call
+fn call(name: &str, args: Vec<Value>) -> Result<Value> {
+    match (name, args.as_slice()) {
+        ("len" | "length", [Value::List(items)]) => Ok(Value::Integer(items.len() as i64)),
+        ("len" | "length", [Value::Text(text)]) => Ok(Value::Integer(text.chars().count() as i64)),
+        ("len" | "length", [value]) => Err(type_error(format!(
+            "{}() needs a list or a string, not {}",
+            name,
+            value.describe()
+        ))),
+        ("string", [value]) => Ok(Value::Text(value.to_string())),
+        ("len" | "length" | "string", _) => Err(VshError::InvalidSyntax(format!(
+            "{}() takes exactly one value",
+            name
+        ))),
+        _ => Err(VshError::InvalidSyntax(format!(
+            "Unknown function '{}' (expected len or string)",
+            name
+        ))),
+    }
+}
+
+/// Text that may be a float: digits with a point or exponent, so words like
+/// `inf` stay strings
+fn looks_numeric(text: &str) -> bool {
+    text.chars().any(|c| c.is_ascii_digit())
+        && text
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E'))
+}
+
+fn type_error(message: String) -> VshError {
+    VshError::TypeError(message)
+}
+
+fn too_large() -> VshError {
+    VshError::ExecutionError("Number too large".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_expression;
+
+    fn eval(shell: &Shell, text: &str) -> Result<String> {
+        shell
+            .evaluate(&parse_expression(text)?)
+            .map(|value| value.to_string())
+    }
+
+    #[test]
+    fn test_inferred_types() {
+        assert_eq!(Value::infer("42"), Value::Integer(42));
+        assert_eq!(Value::infer("-2.5"), Value::Float(-2.5));
+        assert_eq!(Value::infer("inf"), Value::Text("inf".to_string()));
+        assert_eq!(Value::infer("true"), Value::Boolean(true));
+        assert_eq!(
+            Value::infer("[1, \"2\", b.txt]"),
+            Value::List(vec![
+                Value::Integer(1),
+                Value::Text("2".to_string()),
+                Value::Text("b.txt".to_string())
+            ])
+        );
+        assert_eq!(Value::infer("[1, \"2\"]").to_string(), "[1, \"2\"]");
+    }
+
+    #[test]
+    fn test_arithmetic_and_logic() {
+        let mut shell = Shell::new();
+        shell.variables.insert("n".to_string(), "7".to_string());
+        shell
+            .variables
+            .insert("files".to_string(), "[\"a.txt\", \"b.txt\"]".to_string());
+
+        assert_eq!(eval(&shell, "$n * 2 - 4 % 3").unwrap(), "13");
+        assert_eq!(eval(&shell, "n / 2").unwrap(), "3.5");
+        assert_eq!(eval(&shell, "8 / 2").unwrap(), "4");
+        assert_eq!(eval(&shell, "1.5 + 1.5").unwrap(), "3.0");
+        assert_eq!(eval(&shell, "\"ab\" + 'c'").unwrap(), "abc");
+        assert_eq!(eval(&shell, "n > 5 and not (n == 7.0)").unwrap(), "false");
+        assert_eq!(eval(&shell, "files[-1] + \"!\"").unwrap(), "b.txt!");
+        assert_eq!(eval(&shell, "len(files + [3])").unwrap(), "3");
+        assert_eq!(eval(&shell, "false && missing").unwrap(), "false");
+    }
+
+    #[test]
+    fn test_type_errors() {
+        let shell = Shell::new();
+        let error = |text| eval(&shell, text).unwrap_err().to_string();
+
+        assert_eq!(
+            error("3 + \"abc\""),
+            "Type error: cannot add string 'abc' to number 3"
+        );
+        assert!(error("\"10\" < 9").contains("cannot compare"));
+        assert!(error("1 && true").contains("expected true or false"));
+        assert!(error("[1, 2][2]").contains("out of range"));
+        assert!(error("1 / 0").contains("Division by zero"));
+        assert!(error("9223372036854775807 + 1").contains("too large"));
+        assert!(error("undefined_variable + 1").contains("not set"));
+    }
+
+    #[test]
+    fn test_smallest_number_divided_by_minus_one() {
+        let mut shell = Shell::new();
+        shell
+            .variables
+            .insert("x".to_string(), i64::MIN.to_string());
+        for text in ["x / -1", "x % -1"] {
+            let error = eval(&shell, text).unwrap_err().to_string();
+            assert!(error.contains("Number too large"), "{}", error);
+        }
+        assert_eq!(eval(&shell, "x / 1").unwrap(), i64::MIN.to_string());
+    }
+
+    #[test]
+    fn test_declared_types() {
+        assert!(ValueType::parse("number")
+            .unwrap()
+            .check("n", "2.5")
+            .is_ok());
+        assert!(ValueType::parse("integer")
+            .unwrap()
+            .check("n", "2.5")
+            .is_err());
+        assert!(ValueType::parse("list")
+            .unwrap()
+            .check("l", "[a, b]")
+            .is_ok());
+        assert!(ValueType::parse("strin").is_err());
+        assert_eq!(
+            ValueType::Number
+                .check("count", "abc")
+                .unwrap_err()
+                .to_string(),
+            "Type error: count is declared as a number, so it cannot hold string 'abc'"
+        );
+    }
+}
@@ -3,8 +3,9 @@ use clap::Parser;
 use colored::*;
 use rustyline::error::ReadlineError;
 use rustyline::DefaultEditor;
-use vsh::executor::execute_command;
-use vsh::parser::parse_command;
+use vsh::config::VshConfig;
+use vsh::error::VshError;
+use vsh::executor::{NoMatch, Shell};
 
 #[derive(Parser)]
 #[command(name = "vsh")]
@@ -21,53 +22,78 @@ struct Cli {
     /// Enable debug output
     #[arg(short, long)]
     debug: bool,
+
+    /// Script to run (this is how a `#!/usr/bin/env vsh` line calls vsh),
+    /// followed by its arguments
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    args: Vec<String>,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let config = VshConfig::load();
+    let mut shell = Shell::new();
+    shell.debug = cli.debug;
+    if let Some(no_match) = config.glob_no_match.as_deref().and_then(NoMatch::parse) {
+        shell.no_match = no_match;
+    }
+    let mut args = cli.args;
 
     // Execute single command if provided
     if let Some(cmd) = cli.command {
-        return execute_single_command(&cmd, cli.debug);
+        shell.positional = args;
+        return execute_single_command(&mut shell, &cmd);
     }
 
-    // Execute script if provided
-    if let Some(script_path) = cli.script {
-        return execute_script(&script_path, cli.debug);
+    // Execute script if provided, either with --script or as the first argument
+    let script = cli
+        .script
+        .or_else(|| (!args.is_empty()).then(|| args.remove(0)));
+    if let Some(script_path) = script {
+        return execute_script(&mut shell, &script_path, args);
     }
 
     // Otherwise, start REPL
-    start_repl(cli.debug)
+    start_repl(&mut shell)
 }
 
-fn execute_single_command(command: &str, debug: bool) -> Result<()> {
-    if debug {
+fn execute_single_command(shell: &mut Shell, command: &str) -> Result<()> {
+    if shell.debug {
         println!("{} {}", "DEBUG:".yellow(), command);
     }
 
-    match parse_command(command) {
-        Ok(cmd) => {
-            if debug {
-                println!("{} {:?}", "Parsed:".cyan(), cmd);
+    match shell.run_line(command) {
+        Ok(status) => {
+            if status != 0 {
+                std::process::exit(status);
             }
-            execute_command(cmd)?;
         }
         Err(e) => {
             eprintln!("{} {}", "✗ Parse error:".red(), e);
-            std::process::exit(1);
+            std::process::exit(e.exit_code());
         }
     }
 
     Ok(())
 }
 
-fn execute_script(_script_path: &str, _debug: bool) -> Result<()> {
-    // TODO: Implement script execution in Phase 3
-    eprintln!("Script execution not yet implemented");
-    std::process::exit(1);
+fn execute_script(shell: &mut Shell, script_path: &str, args: Vec<String>) -> Result<()> {
+    match shell.run_script(script_path, args) {
+        Ok(status) => {
+            if status != 0 {
+                std::process::exit(status);
+            }
+        }
+        Err(e) => {
+            eprintln!("{} {}", "✗".red(), e);
+            std::process::exit(e.exit_code());
+        }
+    }
+
+    Ok(())
 }
 
-fn start_repl(debug: bool) -> Result<()> {
+fn start_repl(shell: &mut Shell) -> Result<()> {
     println!("{}", "VSH - Vic's Shell v0.1.0".bright_cyan().bold());
     println!(
         "{}",
@@ -79,6 +105,7 @@ fn start_repl(debug: bool) -> Result<()> {
     );
 
     let mut rl = DefaultEditor::new()?;
+    shell.enable_job_control();
 
     // Load history if it exists
     let history_path = dirs::home_dir()
@@ -90,7 +117,13 @@ fn start_repl(debug: bool) -> Result<()> {
 
     let _ = rl.load_history(&history_path);
 
+    // Lines of a block (or quote) that is still open
+    let mut pending = String::new();
+
     loop {
+        // Say which background jobs finished while the last command ran
+        shell.notify_jobs();
+
         // Get username and hostname
         let username = whoami::username();
         let hostname = whoami::fallible::hostname().unwrap_or_else(|_| "localhost".to_string());
@@ -111,35 +144,41 @@ fn start_repl(debug: bool) -> Result<()> {
             })
             .unwrap_or_else(|| "?".to_string());
 
-        let prompt = format!(
-            "{} ",
-            format!("{}@{}:{}$", username, hostname, current_dir)
-                .bright_green()
-                .bold()
-        );
+        let prompt = if pending.is_empty() {
+            format!(
+                "{} ",
+                format!("{}@{}:{}$", username, hostname, current_dir)
+                    .bright_green()
+                    .bold()
+            )
+        } else {
+            format!("{} ", "...".bright_black())
+        };
 
         match rl.readline(&prompt) {
             Ok(line) => {
-                let line = line.trim();
+                // Lines continuing a here-document keep their indentation
+                let line = match pending.is_empty() {
+                    true => line.trim(),
+                    false => line.trim_end_matches(['\r', '\n']),
+                };
 
-                if line.is_empty() {
+                if line.is_empty() && pending.is_empty() {
                     continue;
                 }
 
                 // Add to history
-                let _ = rl.add_history_entry(line);
+                if !line.is_empty() {
+                    let _ = rl.add_history_entry(line);
+                }
 
-                // Handle built-in commands
+                // Handle built-in commands (not inside an open block)
                 match line {
-                    "exit" | "quit" => {
-                        println!("{}", "Goodbye! 👋".bright_cyan());
-                        break;
-                    }
-                    "help" => {
+                    "help" if pending.is_empty() => {
                         print_help();
                         continue;
                     }
-                    "pwd" => {
+                    "pwd" if pending.is_empty() => {
                         match std::env::current_dir() {
                             Ok(path) => println!("{}", path.display()),
                             Err(e) => eprintln!("{} {}", "✗ Error:".red(), e),
@@ -150,28 +189,33 @@ fn start_repl(debug: bool) -> Result<()> {
                 }
 
                 // Parse and execute command
-                if debug {
+                if shell.debug {
                     println!("{} {}", "DEBUG:".yellow(), line);
                 }
 
-                match parse_command(line) {
-                    Ok(cmd) => {
-                        if debug {
-                            println!("{} {:?}", "Parsed:".cyan(), cmd);
-                        }
+                pending.push_str(line);
+                pending.push('\n');
 
-                        if let Err(e) = execute_command(cmd) {
-                            eprintln!("{} {}", "✗ Error:".red(), e);
-                        }
-                    }
+                match shell.run_line(&pending) {
+                    // Keep reading until the block is closed
+                    Err(VshError::Incomplete(_)) => continue,
                     Err(e) => {
                         eprintln!("{} {}", "✗ Parse error:".red(), e);
                         print_suggestion(line);
+                        shell.last_status = e.exit_code();
                     }
+                    Ok(_) => {}
+                }
+                pending.clear();
+
+                if shell.exit_status.is_some() {
+                    println!("{}", "Goodbye! 👋".bright_cyan());
+                    break;
                 }
             }
             Err(ReadlineError::Interrupted) => {
                 println!("^C");
+                pending.clear();
                 continue;
             }
             Err(ReadlineError::Eof) => {
@@ -188,6 +232,10 @@ fn start_repl(debug: bool) -> Result<()> {
     // Save history
     let _ = rl.save_history(&history_path);
 
+    if let Some(status) = shell.exit_status.filter(|&status| status != 0) {
+        std::process::exit(status);
+    }
+
     Ok(())
 }
 
@@ -203,6 +251,14 @@ fn print_help() {
         "  {}",
         "       (or: copy source=<file> destination=<dest>)".bright_black()
     );
+    println!(
+        "  {}",
+        "       (folders: cp -r, copy <dir> recursively to <dest>, recursive=true)".bright_black()
+    );
+    println!(
+        "  {}",
+        "       (existing files: -i ask, -n skip, -f overwrite, -u newer-only)".bright_black()
+    );
     println!();
     println!(
         "  {}  Move/rename files",
@@ -213,9 +269,102 @@ fn print_help() {
     println!("  {}  Remove files", "remove <file>".bright_yellow());
     println!("  {}", "        (or: rm <file>)".bright_black());
     println!("  {}", "        (or: delete <file>)".bright_black());
+    println!(
+        "  {}",
+        "        (several at once: rm a b c, copy a and b to dir/, files=[a, b])".bright_black()
+    );
+    println!(
+        "  {}",
+        "        (without asking: rm -f a, remove files=[a, b] force=true)".bright_black()
+    );
     println!();
     println!("  {}  List files", "list [path]".bright_yellow());
     println!("  {}", "              (or: ls [path])".bright_black());
+    println!(
+        "  {}",
+        "              (details: ls -l, list all-details; hidden: ls -a, list including hidden)"
+            .bright_black()
+    );
+    println!(
+        "  {}",
+        "              (order: ls -S/-t/-r, list sorted by size in reverse; no emojis: --plain)"
+            .bright_black()
+    );
+    println!();
+    println!(
+        "  {}  Make a directory",
+        "create directory <path>".bright_yellow()
+    );
+    println!(
+        "  {}",
+        "                        (or: mkdir [-p] [-m 700] <path>, make-directory path=<path>)"
+            .bright_black()
+    );
+    println!(
+        "  {}",
+        "                        (missing parents: with parents, parents=true)".bright_black()
+    );
+    println!();
+    println!(
+        "  {}  Find files below a directory",
+        "find files named \"*.txt\" in <path>".bright_yellow()
+    );
+    println!(
+        "  {}",
+        "                  (or: find . -name \"*.txt\" -type f, find type=file name=\"*.txt\")"
+            .bright_black()
+    );
+    println!(
+        "  {}",
+        "                  (filters: larger than 1M, modified in last 2 days, within 2 levels)"
+            .bright_black()
+    );
+    println!(
+        "  {}",
+        "                  (.gitignore is respected; add: including ignored, including hidden)"
+            .bright_black()
+    );
+    println!();
+    println!(
+        "  {}  Search files for a pattern",
+        "search \"text\" in <file>".bright_yellow()
+    );
+    println!(
+        "  {}",
+        "                  (or: grep [-i -w -n -c -r -F] <pattern> [files], grep pattern=x file=y)"
+            .bright_black()
+    );
+    println!(
+        "  {}",
+        "                  (options: ignoring case, as whole words, with line numbers, recursively,"
+            .bright_black()
+    );
+    println!(
+        "  {}",
+        "                   literally, count only; no files searches what's piped in)"
+            .bright_black()
+    );
+    println!();
+    println!("  {}  Print a file", "show <file>".bright_yellow());
+    println!(
+        "  {}",
+        "              (or: cat [-n] <file>; no file prints what's piped in)".bright_black()
+    );
+    println!(
+        "  {}",
+        "              (show first 10 lines of <file>, head -n 10, tail -n 10)".bright_black()
+    );
+    println!();
+    println!(
+        "  {}  Create a file, or update its time",
+        "create file <file>".bright_yellow()
+    );
+    println!(
+        "  {}",
+        "                   (or: touch <file>)".bright_black()
+    );
+    println!();
+    println!("  {}  Print text", "echo [-n] [-e] <text>".bright_yellow());
     println!();
     println!("  {}  Change directory", "cd <path>".bright_yellow());
     println!(
@@ -223,16 +372,158 @@ fn print_help() {
         "                  (or: change-directory <path>)".bright_black()
     );
     println!("  {}", "                  (or: goto <path>)".bright_black());
+    println!(
+        "  {}",
+        "                  (cd alone goes home, cd - or go back to the last one)".bright_black()
+    );
+    println!(
+        "  {}",
+        "                  (also searches the directories in $CDPATH)".bright_black()
+    );
+    println!(
+        "  {}  Change directory, saving this one",
+        "pushd <path>".bright_yellow()
+    );
+    println!(
+        "  {}",
+        "                  (popd returns, dirs [-v | -c] shows the stack)".bright_black()
+    );
     println!();
     println!("  {}  Print working directory", "pwd".bright_yellow());
     println!();
+    println!(
+        "  {}  Run a command without waiting for it",
+        "<command> &".bright_yellow()
+    );
+    println!(
+        "  {}",
+        "                  (or: <command> in background)".bright_black()
+    );
+    println!(
+        "  {}",
+        "                  (Ctrl-Z stops the running command)".bright_black()
+    );
+    println!(
+        "  {}  List background and stopped jobs",
+        "jobs [-l]".bright_yellow()
+    );
+    println!(
+        "  {}",
+        "                  (fg/bg [%n] resume one, wait [%n] waits for it)".bright_black()
+    );
+    println!(
+        "  {}",
+        "                  (kill [-SIGNAL] %n|pid sends it a signal)".bright_black()
+    );
+    println!();
+    println!(
+        "  {}  Run any program on your $PATH",
+        "<program> [args]".bright_yellow()
+    );
+    println!(
+        "  {}",
+        "                  (e.g.: git status)".bright_black()
+    );
+    println!(
+        "  {}  Feed text to a command",
+        "<command> <<EOF ... EOF".bright_yellow()
+    );
+    println!(
+        "  {}",
+        "      (<<- strips tabs, <<'EOF' leaves $ alone; or: <<< \"text\", with input \"\"\" ... \"\"\")"
+            .bright_black()
+    );
+    println!();
+    println!(
+        "  {}  Set a variable, used as $name",
+        "name = value".bright_yellow()
+    );
+    println!(
+        "  {}",
+        "              (or: set/let/var name = value, name=value)".bright_black()
+    );
+    println!(
+        "  {}",
+        "              (name = $(command) keeps what the command prints)".bright_black()
+    );
+    println!(
+        "  {}",
+        "              (set count: number = 42 keeps count a number)".bright_black()
+    );
+    println!(
+        "  {}  Calculate: numbers, \"text\", [lists], list[0], len(list)",
+        "$(( expression ))".bright_yellow()
+    );
+    println!(
+        "  {}",
+        "                   (+ - * / %, == != < > <= >=, and or not)".bright_black()
+    );
+    println!(
+        "  {}  Pass a variable to programs",
+        "export NAME=value".bright_yellow()
+    );
+    println!(
+        "  {}",
+        "                   (or: export NAME as value, unset NAME, env)".bright_black()
+    );
+    println!(
+        "  {}",
+        "                   (or: NAME=value program, for one command)".bright_black()
+    );
+    println!(
+        "  {}  Status of the last command (0 is success)",
+        "$?".bright_yellow()
+    );
+    println!(
+        "  {}",
+        "                   (or: last-status)".bright_black()
+    );
+    println!();
+    println!(
+        "  {}  Run commands when a condition holds",
+        "if <cond> then ... else ... end".bright_yellow()
+    );
+    println!(
+        "  {}",
+        "      (conditions: file-exists, directory-exists, is-empty, $a > $b, not ...)"
+            .bright_black()
+    );
+    println!(
+        "  {}  Repeat commands",
+        "for x in <items> do ... end".bright_yellow()
+    );
+    println!(
+        "  {}",
+        "      (items: 1..10, *.txt, [\"a\", \"b\"]; or: while <cond> do ... end)".bright_black()
+    );
+    println!(
+        "  {}",
+        "      (break and continue work inside loops)".bright_black()
+    );
+    println!(
+        "  {}  Define a command",
+        "function name params ... end".bright_yellow()
+    );
+    println!(
+        "  {}",
+        "      (return <value>; call with: name args, or result = name args)".bright_black()
+    );
+    println!();
     println!("  {}                Exit VSH", "exit".bright_yellow());
+    println!(
+        "  {}",
+        "                    (or: exit <status>, quit)".bright_black()
+    );
     println!("  {}                Show this help", "help".bright_yellow());
     println!();
     println!(
         "{}",
         "Tip: All commands support terse, verbose, and named syntax!".bright_black()
     );
+    println!(
+        "{}",
+        "Tip: *.txt, **/*.rs, file{1..3}, {a,b} and ~ work in every command.".bright_black()
+    );
 }
 
 fn print_suggestion(input: &str) {
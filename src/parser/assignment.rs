@@ -0,0 +1,178 @@
+use crate::parser::tokenizer::{Quote, Token};
+
+/// A variable assignment, before its value is expanded
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+    pub name: String,
+    /// The words of the value; they are joined with spaces when assigned
+    pub value: Vec<Token>,
+    /// Declared with `local`: visible only inside the running function
+    pub local: bool,
+    /// The type named in `set count: number = 42`, checked when assigned
+    pub declared: Option<String>,
+}
+
+/// Recognize a command that only assigns a variable:
+///
+/// - `name = value`
+/// - `set name = value`, `let name = value`, `var name = value`
+/// - `local name = value`, inside a function
+/// - `name=value` (also after `set`, `let`, `var` or `local`)
+/// - `name: type = value` or `name:type = value`, with any of the above
+///
+/// A `key=value` word after a command word is a named argument, not an
+/// assignment, so `copy source=a.txt dest=b/` is left alone.
+pub fn parse_assignment(tokens: &[Token]) -> Option<Assignment> {
+    let local = tokens.first().is_some_and(|t| t.is_operator("local"));
+    let tokens = match tokens.first() {
+        Some(first) if is_declaration_word(first) && tokens.len() > 1 => &tokens[1..],
+        _ => tokens,
+    };
+
+    match tokens {
+        [name, equals, value @ ..] if is_bare_identifier(name) && equals.is_operator("=") => {
+            Some(Assignment {
+                name: name.value.clone(),
+                value: value.to_vec(),
+                local,
+                declared: None,
+            })
+        }
+        [name, kind, equals, value @ ..] if equals.is_operator("=") => {
+            let name = name.value.strip_suffix(':')?;
+            typed(name, kind.value.as_str(), value, local, &[&tokens[0], kind])
+        }
+        [name, equals, value @ ..] if equals.is_operator("=") => {
+            let (name, kind) = name.value.split_once(':')?;
+            typed(name, kind, value, local, &[&tokens[0]])
+        }
+        [word] => {
+            let (name, value) = word.key_value()?;
+            is_identifier(name).then(|| Assignment {
+                name: name.to_string(),
+                value: vec![value],
+                local,
+                declared: None,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// An assignment with a declared type, if the name and type are bare words
+fn typed(
+    name: &str,
+    kind: &str,
+    value: &[Token],
+    local: bool,
+    words: &[&Token],
+) -> Option<Assignment> {
+    let bare = words
+        .iter()
+        .all(|word| matches!(word.parts.as_slice(), [(_, Quote::Bare)]));
+    (bare && is_identifier(name) && is_identifier(kind)).then(|| Assignment {
+        name: name.to_string(),
+        value: value.to_vec(),
+        local,
+        declared: Some(kind.to_string()),
+    })
+}
+
+/// Split `NAME=value` words off the front of a command, as in
+/// `RUST_LOG=debug cargo test`. They apply only to that one command.
+pub fn split_env_prefix(tokens: &[Token]) -> (Vec<Assignment>, &[Token]) {
+    let mut prefix = Vec::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        match token.key_value() {
+            Some((name, value)) if is_identifier(name) => prefix.push(Assignment {
+                name: name.to_string(),
+                value: vec![value],
+                local: false,
+                declared: None,
+            }),
+            _ => return (prefix, &tokens[i..]),
+        }
+    }
+
+    (prefix, &[])
+}
+
+/// Check if a word is a valid variable name
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Check if a token is an unquoted variable name
+fn is_bare_identifier(token: &Token) -> bool {
+    matches!(token.parts.as_slice(), [(_, Quote::Bare)]) && is_identifier(&token.value)
+}
+
+/// Check if a token is one of the words that declare a variable
+fn is_declaration_word(token: &Token) -> bool {
+    !token.is_quoted && matches!(token.value.as_str(), "set" | "let" | "var" | "local")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tokenizer::tokenize;
+
+    fn assignment(input: &str) -> Option<(String, Vec<String>)> {
+        let tokens = tokenize(input).unwrap();
+        parse_assignment(&tokens).map(|a| (a.name, a.value.into_iter().map(|t| t.value).collect()))
+    }
+
+    #[test]
+    fn test_assignment_forms() {
+        let expected = Some(("name".to_string(), vec!["World".to_string()]));
+        assert_eq!(assignment("name = \"World\""), expected);
+        assert_eq!(assignment("set name = World"), expected);
+        assert_eq!(assignment("let name = World"), expected);
+        assert_eq!(assignment("var name=World"), expected);
+        assert_eq!(assignment("name=\"World\""), expected);
+        assert_eq!(assignment("local name = World"), expected);
+    }
+
+    #[test]
+    fn test_named_arguments_are_not_assignments() {
+        assert_eq!(assignment("copy source=a.txt dest=b/"), None);
+        assert_eq!(assignment("\"name\" = x"), None);
+        assert_eq!(assignment("1st = x"), None);
+        assert_eq!(assignment("set"), None);
+    }
+
+    #[test]
+    fn test_env_prefix() {
+        let tokens = tokenize("RUST_LOG=debug A=\"1 2\" cargo test name=x").unwrap();
+        let (prefix, rest) = split_env_prefix(&tokens);
+        let names: Vec<_> = prefix.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["RUST_LOG", "A"]);
+        assert_eq!(prefix[1].value[0].value, "1 2");
+        assert_eq!(rest.len(), 3);
+        assert_eq!(rest[0].value, "cargo");
+    }
+
+    #[test]
+    fn test_declared_types() {
+        let declared = |input| parse_assignment(&tokenize(input).unwrap()).unwrap();
+        let count = declared("set count: number = 42");
+        assert_eq!(count.name, "count");
+        assert_eq!(count.declared.as_deref(), Some("number"));
+        assert_eq!(count.value[0].value, "42");
+        assert_eq!(
+            declared("local n:integer = 1").declared.as_deref(),
+            Some("integer")
+        );
+        assert!(declared("local n:integer = 1").local);
+        assert_eq!(assignment("copy a: b = c"), None);
+        assert_eq!(assignment("\"n:\" list = x"), None);
+    }
+
+    #[test]
+    fn test_empty_value() {
+        assert_eq!(assignment("name ="), Some(("name".to_string(), vec![])));
+    }
+}
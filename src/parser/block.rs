@@ -0,0 +1,493 @@
+use super::{
+    build_pipeline, is_identifier, list, literal, CommandList, ForLoop, FunctionDef, IfBlock,
+    Pipeline, Statement, WhileLoop,
+};
+use crate::error::{Result, VshError};
+use crate::parser::tokenizer::Token;
+use std::rc::Rc;
+
+/// Words that close or continue a block and can't start a command
+const BLOCK_WORDS: &[&str] = &["then", "do", "else", "end"];
+
+/// Words that start a block
+const BLOCK_STARTS: &[&str] = &["if", "for", "while", "function"];
+
+/// Operators that make a condition a comparison instead of a command
+const COMPARISONS: &[&str] = &[
+    "==",
+    "=",
+    "!=",
+    ">",
+    "<",
+    ">=",
+    "<=",
+    "equals",
+    "not-equals",
+    "greater-than",
+    "less-than",
+    "at-least",
+    "at-most",
+];
+
+/// Parse the tokens of a whole script (or REPL entry) into statements
+pub fn parse_statements(tokens: &[Token]) -> Result<Vec<Statement>> {
+    Parser { tokens, pos: 0 }.block(&[], "")
+}
+
+/// Check if a token is a newline, which ends a statement
+pub fn is_newline(token: &Token) -> bool {
+    token.is_operator("\n")
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// The current token if it is the bare word `word`
+    fn at(&self, word: &str) -> bool {
+        self.tokens
+            .get(self.pos)
+            .is_some_and(|t| t.is_operator(word))
+    }
+
+    /// Consume the bare word `word` if it comes next
+    fn eat(&mut self, word: &str) -> bool {
+        let found = self.at(word);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    /// Check if nothing but a newline (or the end) follows
+    fn at_line_end(&self) -> bool {
+        self.tokens.get(self.pos).is_none_or(is_newline)
+    }
+
+    fn skip_separators(&mut self) {
+        while self.at("\n") || self.at(";") {
+            self.pos += 1;
+        }
+    }
+
+    /// Statements up to one of `terminators`, which is left for the caller.
+    /// `opener` names the block in the error when input runs out first.
+    fn block(&mut self, terminators: &[&str], opener: &str) -> Result<Vec<Statement>> {
+        let mut statements = Vec::new();
+
+        loop {
+            self.skip_separators();
+
+            let Some(token) = self.tokens.get(self.pos) else {
+                if terminators.is_empty() {
+                    return Ok(statements);
+                }
+                return Err(VshError::Incomplete(format!(
+                    "Missing 'end' to close '{}'",
+                    opener
+                )));
+            };
+
+            if token.is_quoted {
+                statements.push(Statement::Commands(self.command_list(false)?));
+            } else if terminators.contains(&token.value.as_str()) {
+                return Ok(statements);
+            } else if BLOCK_WORDS.contains(&token.value.as_str()) {
+                return Err(VshError::ParseError(format!(
+                    "Unexpected '{}'",
+                    token.value
+                )));
+            } else if token.value == "if" {
+                statements.push(self.if_block()?);
+            } else if token.value == "for" {
+                statements.push(self.for_loop()?);
+            } else if token.value == "while" {
+                statements.push(self.while_loop()?);
+            } else if token.value == "function" {
+                statements.push(self.function()?);
+            } else {
+                statements.push(Statement::Commands(self.command_list(false)?));
+            }
+        }
+    }
+
+    /// `if cond then` followed by a block ending in `end`, or the one-line
+    /// `if cond then command [else command]`
+    fn if_block(&mut self) -> Result<Statement> {
+        self.pos += 1;
+        let mut branches = Vec::new();
+        let mut otherwise = Vec::new();
+
+        loop {
+            let condition = self.condition("if", "then")?;
+
+            if self.at_line_end() {
+                branches.push((condition, self.block(&["else", "end"], "if")?));
+                if self.eat("else") {
+                    if self.eat("if") {
+                        continue;
+                    }
+                    otherwise = self.block(&["end"], "if")?;
+                }
+                self.eat("end");
+            } else {
+                branches.push((condition, vec![self.one_line_statement()?]));
+                if self.eat("else") {
+                    if self.eat("if") {
+                        continue;
+                    }
+                    otherwise = vec![self.one_line_statement()?];
+                }
+                // A one-line `if` may still be closed with `end`
+                self.eat("end");
+            }
+            break;
+        }
+
+        Ok(Statement::If(IfBlock {
+            branches,
+            otherwise,
+        }))
+    }
+
+    /// `for name in items do` followed by a block ending in `end`, or the
+    /// one-line `for name in items do command`. Items may be a list literal.
+    fn for_loop(&mut self) -> Result<Statement> {
+        self.pos += 1;
+
+        let variable = match self.tokens.get(self.pos) {
+            Some(token) if !token.is_quoted && is_identifier(&token.value) => token.value.clone(),
+            _ => {
+                return Err(VshError::ParseError(
+                    "Expected a variable name after 'for'".to_string(),
+                ))
+            }
+        };
+        self.pos += 1;
+
+        if !self.eat("in") {
+            return Err(VshError::ParseError(format!(
+                "Expected 'in' after 'for {}'",
+                variable
+            )));
+        }
+
+        let words = self.words_until("for", "do")?;
+        let items = literal::parse_list(words)?.unwrap_or_else(|| words.to_vec());
+        let body = self.body("for")?;
+
+        Ok(Statement::For(ForLoop {
+            variable,
+            items,
+            body,
+        }))
+    }
+
+    /// `while condition do` followed by a block ending in `end`, or the
+    /// one-line `while condition do command`
+    fn while_loop(&mut self) -> Result<Statement> {
+        self.pos += 1;
+        let condition = self.condition("while", "do")?;
+        let body = self.body("while")?;
+
+        Ok(Statement::While(WhileLoop { condition, body }))
+    }
+
+    /// `function name params…` on one line, then a body ending in `end`.
+    /// Names may contain dashes, like `quick-backup`.
+    fn function(&mut self) -> Result<Statement> {
+        self.pos += 1;
+
+        let name = match self.tokens.get(self.pos) {
+            Some(token)
+                if !token.is_quoted
+                    && !is_newline(token)
+                    && !BLOCK_WORDS.contains(&token.value.as_str()) =>
+            {
+                token.value.clone()
+            }
+            _ => {
+                return Err(VshError::ParseError(
+                    "Expected a name after 'function'".to_string(),
+                ))
+            }
+        };
+        self.pos += 1;
+
+        let mut params = Vec::new();
+        while let Some(token) = self.tokens.get(self.pos).filter(|t| !is_newline(t)) {
+            if token.is_quoted || !is_identifier(&token.value) {
+                return Err(VshError::ParseError(format!(
+                    "'{}' is not a valid parameter name for '{}'",
+                    token.value, name
+                )));
+            }
+            params.push(token.value.clone());
+            self.pos += 1;
+        }
+
+        let body = self.block(&["end"], "function")?;
+        self.eat("end");
+
+        Ok(Statement::Function(Rc::new(FunctionDef {
+            name,
+            params,
+            body,
+        })))
+    }
+
+    /// The body after `do`: a block closed by `end` when `do` ends the line,
+    /// otherwise the rest of the line (optionally closed by `end`)
+    fn body(&mut self, opener: &str) -> Result<Vec<Statement>> {
+        let body = if self.at_line_end() {
+            self.block(&["end"], opener)?
+        } else {
+            vec![self.one_line_statement()?]
+        };
+        self.eat("end");
+        Ok(body)
+    }
+
+    /// The body of a one-line block: a command list, or another block
+    /// as in `for f in *.txt do if is-empty $f then remove $f`
+    fn one_line_statement(&mut self) -> Result<Statement> {
+        if self.at("if") {
+            return self.if_block();
+        }
+        if self.at("for") {
+            return self.for_loop();
+        }
+        if self.at("while") {
+            return self.while_loop();
+        }
+        Ok(Statement::Commands(self.command_list(true)?))
+    }
+
+    /// The words after `keyword` up to `until` on the same line, consuming
+    /// `until` as well
+    fn words_until(&mut self, keyword: &str, until: &str) -> Result<&'a [Token]> {
+        let start = self.pos;
+
+        while !self.at(until) {
+            match self.tokens.get(self.pos) {
+                Some(token) if !is_newline(token) => {
+                    // Skip whole connectors, so `and then` is not our `then`
+                    self.pos += list::connector_at(self.tokens, self.pos).map_or(1, |(_, n)| n);
+                }
+                _ => {
+                    return Err(VshError::ParseError(format!(
+                        "Expected '{}' after '{}'",
+                        until, keyword
+                    )))
+                }
+            }
+        }
+
+        let words = &self.tokens[start..self.pos];
+        self.pos += 1;
+        Ok(words)
+    }
+
+    /// The condition after `keyword`, up to `until` on the same line. A
+    /// comparison like `$count > 10` runs the `compare` built-in.
+    fn condition(&mut self, keyword: &str, until: &str) -> Result<CommandList> {
+        let tokens = self.words_until(keyword, until)?;
+
+        if tokens.is_empty() {
+            return Err(VshError::ParseError(format!(
+                "Missing condition after '{}'",
+                keyword
+            )));
+        }
+
+        let items = list::split_list(tokens)?
+            .into_iter()
+            .map(|(connector, tokens)| {
+                let (negated, rest) = split_negation(tokens);
+                let pipeline = match comparison(rest) {
+                    Some(compare) => Pipeline {
+                        negated,
+                        ..build_pipeline(&compare)?
+                    },
+                    None => build_pipeline(tokens)?,
+                };
+                Ok((connector, pipeline))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(CommandList { items })
+    }
+
+    /// A command list running to the end of the line. In a one-line block
+    /// it also stops at `else` or `end`. A `;` followed by a block word,
+    /// like `; end` or `; if`, ends it too.
+    fn command_list(&mut self, one_line: bool) -> Result<CommandList> {
+        let start = self.pos;
+
+        while let Some(token) = self.tokens.get(self.pos) {
+            let closes_block = |t: &Token| t.is_operator("else") || t.is_operator("end");
+            let is_block_word =
+                |t: &Token| closes_block(t) || BLOCK_STARTS.iter().any(|w| t.is_operator(w));
+
+            if is_newline(token) || (one_line && closes_block(token)) {
+                break;
+            }
+            if token.is_operator(";") && self.tokens.get(self.pos + 1).is_some_and(is_block_word) {
+                self.pos += 1;
+                break;
+            }
+
+            // Skip whole connectors, so `or else` is not our `else`
+            self.pos += list::connector_at(self.tokens, self.pos).map_or(1, |(_, n)| n);
+        }
+
+        let tokens = &self.tokens[start..self.pos];
+        if tokens.is_empty() {
+            return Err(VshError::ParseError("Expected a command".to_string()));
+        }
+
+        let items = list::split_list(tokens)?
+            .into_iter()
+            .map(|(connector, tokens)| Ok((connector, build_pipeline(tokens)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(CommandList { items })
+    }
+}
+
+/// Take a leading `not` or `!` off a pipeline
+pub fn split_negation(tokens: &[Token]) -> (bool, &[Token]) {
+    match tokens.split_first() {
+        Some((first, rest))
+            if (first.is_operator("not") || first.is_operator("!")) && !rest.is_empty() =>
+        {
+            (true, rest)
+        }
+        _ => (false, tokens),
+    }
+}
+
+/// Rewrite `a > b` (or `a >= b`, `a equals b`, ...) as `compare a ">" b`
+fn comparison(tokens: &[Token]) -> Option<Vec<Token>> {
+    let (lhs, operator, rhs) = match tokens {
+        // The tokenizer reads `>=` as the operator `>` and the word `=`
+        [lhs, op, equals, rhs]
+            if (op.is_operator(">") || op.is_operator("<")) && equals.is_operator("=") =>
+        {
+            (lhs, format!("{}=", op.value), rhs)
+        }
+        [lhs, op, rhs] if !op.is_quoted && COMPARISONS.contains(&op.value.as_str()) => {
+            (lhs, op.value.clone(), rhs)
+        }
+        _ => return None,
+    };
+
+    Some(vec![
+        Token::word("compare"),
+        lhs.clone(),
+        Token::quoted(&operator),
+        rhs.clone(),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tokenize;
+
+    fn parse(input: &str) -> Result<Vec<Statement>> {
+        parse_statements(&tokenize(input)?)
+    }
+
+    fn if_block(statement: &Statement) -> &IfBlock {
+        match statement {
+            Statement::If(block) => block,
+            other => panic!("expected an if block, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_block_if() {
+        let statements =
+            parse("if file-exists a.txt then\n  echo yes\n  list\nelse\n  echo no\nend\necho done")
+                .unwrap();
+        assert_eq!(statements.len(), 2);
+        let block = if_block(&statements[0]);
+        assert_eq!(block.branches.len(), 1);
+        assert_eq!(block.branches[0].1.len(), 2);
+        assert_eq!(block.otherwise.len(), 1);
+    }
+
+    #[test]
+    fn test_one_line_if_and_else_if() {
+        let statements = parse("if a then echo x | wc -l else echo y; echo z").unwrap();
+        let block = if_block(&statements[0]);
+        assert_eq!(block.branches[0].1.len(), 1);
+        assert_eq!(block.otherwise.len(), 1);
+
+        let statements = parse("if a then\nx\nelse if b then\ny\nelse\nz\nend").unwrap();
+        assert_eq!(if_block(&statements[0]).branches.len(), 2);
+    }
+
+    #[test]
+    fn test_comparison_condition() {
+        let statements = parse("if not $count >= 10 then echo small").unwrap();
+        let (condition, _) = &if_block(&statements[0]).branches[0];
+        let pipeline = &condition.items[0].1;
+        assert!(pipeline.negated);
+        let values: Vec<_> = pipeline.stages[0]
+            .tokens
+            .iter()
+            .map(|t| t.value.as_str())
+            .collect();
+        assert_eq!(values, vec!["compare", "$count", ">=", "10"]);
+    }
+
+    #[test]
+    fn test_incomplete_and_invalid_blocks() {
+        assert!(matches!(
+            parse("if a then\necho x"),
+            Err(VshError::Incomplete(_))
+        ));
+        assert!(matches!(
+            parse("if a\necho x"),
+            Err(VshError::ParseError(_))
+        ));
+        assert!(matches!(parse("end"), Err(VshError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_function_definition() {
+        let statements = parse(
+            "function add x y
+  return $x
+end
+add 1 2",
+        )
+        .unwrap();
+        assert_eq!(statements.len(), 2);
+        let Statement::Function(function) = &statements[0] else {
+            panic!("expected a function, got {:?}", statements[0]);
+        };
+        assert_eq!(function.name, "add");
+        assert_eq!(function.params, vec!["x", "y"]);
+        assert_eq!(function.body.len(), 1);
+
+        assert!(matches!(
+            parse(
+                "function quick-backup
+copy $1 to backups/"
+            ),
+            Err(VshError::Incomplete(_))
+        ));
+        assert!(matches!(
+            parse(
+                "function f 1st
+end"
+            ),
+            Err(VshError::ParseError(_))
+        ));
+    }
+}
@@ -0,0 +1,444 @@
+use crate::error::{Result, VshError};
+use crate::parser::is_identifier;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// An expression, as written inside `$(( … ))`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    Boolean(bool),
+    /// `[1, 2, "three"]`
+    List(Vec<Expr>),
+    /// `$name`, `${name}`, `$1` or just `name`
+    Variable(String),
+    /// `list[0]`, `name[-1]`
+    Index(Box<Expr>, Box<Expr>),
+    /// `len(list)`
+    Call(String, Vec<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Negate,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+    And,
+    Or,
+}
+
+/// Parse an expression: numbers, `"strings"`, `true`/`false`, lists,
+/// variables, `+ - * / %`, comparisons, `&&`/`and`, `||`/`or`, `!`/`not`,
+/// indexing and `len(…)`
+pub fn parse_expression(text: &str) -> Result<Expr> {
+    parse(text).map_err(|e| match e {
+        VshError::InvalidSyntax(message) => {
+            VshError::InvalidSyntax(format!("{} in '{}'", message, text.trim()))
+        }
+        e => e,
+    })
+}
+
+fn parse(text: &str) -> Result<Expr> {
+    let tokens = lex(text)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+    };
+
+    let expr = parser.or()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(VshError::InvalidSyntax(format!("Unexpected '{}'", token))),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    Variable(String),
+    Word(String),
+    Symbol(&'static str),
+}
+
+impl std::fmt::Display for Tok {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Tok::Integer(n) => write!(f, "{}", n),
+            Tok::Float(n) => write!(f, "{}", n),
+            Tok::Text(text) => write!(f, "\"{}\"", text),
+            Tok::Variable(name) => write!(f, "${}", name),
+            Tok::Word(word) => write!(f, "{}", word),
+            Tok::Symbol(symbol) => write!(f, "{}", symbol),
+        }
+    }
+}
+
+/// Longest first, so `<=` isn't read as `<` then `=`
+const SYMBOLS: [&str; 18] = [
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "+", "-", "*", "/", "%", "(", ")", "[", "]",
+];
+
+fn lex(text: &str) -> Result<Vec<Tok>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+            }
+            '0'..='9' | '.' => tokens.push(number(&mut chars)?),
+            '"' | '\'' => {
+                chars.next();
+                tokens.push(Tok::Text(string(&mut chars, c)?));
+            }
+            '$' => {
+                chars.next();
+                tokens.push(Tok::Variable(variable(&mut chars)?));
+            }
+            ',' => {
+                chars.next();
+                tokens.push(Tok::Symbol(","));
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                let mut word = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    word.push(c);
+                }
+                tokens.push(Tok::Word(word));
+            }
+            _ => {
+                let rest: String = chars.clone().take(2).collect();
+                let symbol = SYMBOLS
+                    .iter()
+                    .find(|symbol| rest.starts_with(*symbol))
+                    .ok_or_else(|| match c {
+                        '=' => VshError::InvalidSyntax("Use '==' to compare".to_string()),
+                        _ => VshError::InvalidSyntax(format!("Unexpected '{}'", c)),
+                    })?;
+                for _ in 0..symbol.len() {
+                    chars.next();
+                }
+                tokens.push(Tok::Symbol(symbol));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// `42`, `3.14` or `.5`
+fn number(chars: &mut Peekable<Chars>) -> Result<Tok> {
+    let mut text = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || *c == '.' || *c == '_') {
+        if c != '_' {
+            text.push(c);
+        }
+    }
+
+    if text.contains('.') {
+        text.parse()
+            .map(Tok::Float)
+            .map_err(|_| VshError::InvalidSyntax(format!("'{}' is not a number", text)))
+    } else {
+        text.parse().map(Tok::Integer).map_err(|_| {
+            VshError::InvalidSyntax(format!("{} is too large for a whole number", text))
+        })
+    }
+}
+
+/// The rest of a quoted string; a backslash escapes the next character
+fn string(chars: &mut Peekable<Chars>, quote: char) -> Result<String> {
+    let mut text = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            _ if c == quote => return Ok(text),
+            '\\' => text.extend(chars.next()),
+            _ => text.push(c),
+        }
+    }
+    Err(VshError::InvalidSyntax("Unclosed quote".to_string()))
+}
+
+/// The name after a `$`: `{name}`, a digit, `#`, `?`, or an identifier
+fn variable(chars: &mut Peekable<Chars>) -> Result<String> {
+    let name: String = if chars.next_if_eq(&'{').is_some() {
+        let name = chars.by_ref().take_while(|&c| c != '}').collect();
+        name
+    } else if let Some(c) = chars.next_if(|c| c.is_ascii_digit() || matches!(c, '#' | '?')) {
+        c.to_string()
+    } else {
+        let mut name = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+            name.push(c);
+        }
+        name
+    };
+
+    let valid = is_identifier(&name) || name == "#" || name == "?" || name.parse::<usize>().is_ok();
+    match valid {
+        true => Ok(name),
+        false => Err(VshError::InvalidSyntax(format!(
+            "'${}' is not a variable",
+            name
+        ))),
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Tok],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&Tok> {
+        self.position += 1;
+        self.tokens.get(self.position - 1)
+    }
+
+    /// Take the next token if it's one of `symbols` or keyword `words`
+    fn eat(&mut self, symbols: &[&str], words: &[&str]) -> Option<String> {
+        let found = match self.peek()? {
+            Tok::Symbol(symbol) if symbols.contains(symbol) => symbol.to_string(),
+            Tok::Word(word) if words.contains(&word.as_str()) => word.clone(),
+            _ => return None,
+        };
+        self.position += 1;
+        Some(found)
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<()> {
+        match self.eat(&[symbol], &[]) {
+            Some(_) => Ok(()),
+            None => Err(VshError::InvalidSyntax(match self.peek() {
+                Some(token) => format!("Expected '{}' but found '{}'", symbol, token),
+                None => format!("Missing '{}'", symbol),
+            })),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.eat(&["||"], &["or"]).is_some() {
+            expr = Expr::Binary(BinaryOp::Or, Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.not()?;
+        while self.eat(&["&&"], &["and"]).is_some() {
+            expr = Expr::Binary(BinaryOp::And, Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        if self.eat(&["!"], &["not"]).is_some() {
+            return Ok(Expr::Unary(UnaryOp::Not, Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr> {
+        let expr = self.sum()?;
+        let op = match self.eat(&["==", "!=", "<=", ">=", "<", ">"], &[]) {
+            Some(symbol) => match symbol.as_str() {
+                "==" => BinaryOp::Equal,
+                "!=" => BinaryOp::NotEqual,
+                "<=" => BinaryOp::LessEqual,
+                ">=" => BinaryOp::GreaterEqual,
+                "<" => BinaryOp::Less,
+                _ => BinaryOp::Greater,
+            },
+            None => return Ok(expr),
+        };
+        Ok(Expr::Binary(op, Box::new(expr), Box::new(self.sum()?)))
+    }
+
+    fn sum(&mut self) -> Result<Expr> {
+        let mut expr = self.product()?;
+        while let Some(symbol) = self.eat(&["+", "-"], &[]) {
+            let op = match symbol.as_str() {
+                "+" => BinaryOp::Add,
+                _ => BinaryOp::Subtract,
+            };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.product()?));
+        }
+        Ok(expr)
+    }
+
+    fn product(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        while let Some(symbol) = self.eat(&["*", "/", "%"], &[]) {
+            let op = match symbol.as_str() {
+                "*" => BinaryOp::Multiply,
+                "/" => BinaryOp::Divide,
+                _ => BinaryOp::Remainder,
+            };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat(&["-"], &[]).is_some() {
+            return Ok(Expr::Unary(UnaryOp::Negate, Box::new(self.unary()?)));
+        }
+        self.eat(&["+"], &[]);
+
+        let mut expr = self.primary()?;
+        while self.eat(&["["], &[]).is_some() {
+            let index = self.or()?;
+            self.expect("]")?;
+            expr = Expr::Index(Box::new(expr), Box::new(index));
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let token = self
+            .next()
+            .cloned()
+            .ok_or_else(|| VshError::InvalidSyntax("Missing a value".to_string()))?;
+
+        match token {
+            Tok::Integer(n) => Ok(Expr::Integer(n)),
+            Tok::Float(n) => Ok(Expr::Float(n)),
+            Tok::Text(text) => Ok(Expr::Text(text)),
+            Tok::Variable(name) => Ok(Expr::Variable(name)),
+            Tok::Word(word) if word == "true" || word == "false" => {
+                Ok(Expr::Boolean(word == "true"))
+            }
+            Tok::Word(word) if self.eat(&["("], &[]).is_some() => {
+                let args = self.items(")")?;
+                Ok(Expr::Call(word, args))
+            }
+            Tok::Word(word) => Ok(Expr::Variable(word)),
+            Tok::Symbol("(") => {
+                let expr = self.or()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Tok::Symbol("[") => Ok(Expr::List(self.items("]")?)),
+            token => Err(VshError::InvalidSyntax(format!("Unexpected '{}'", token))),
+        }
+    }
+
+    /// Comma-separated expressions up to the closing symbol
+    fn items(&mut self, close: &str) -> Result<Vec<Expr>> {
+        let mut items = Vec::new();
+        while self.eat(&[close], &[]).is_none() {
+            if !items.is_empty() {
+                self.expect(",")?;
+            }
+            items.push(self.or()?);
+        }
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary(op: BinaryOp, lhs: Expr, rhs: Expr) -> Expr {
+        Expr::Binary(op, Box::new(lhs), Box::new(rhs))
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(
+            parse_expression("1 + 2 * $x").unwrap(),
+            binary(
+                BinaryOp::Add,
+                Expr::Integer(1),
+                binary(
+                    BinaryOp::Multiply,
+                    Expr::Integer(2),
+                    Expr::Variable("x".to_string())
+                )
+            )
+        );
+        assert_eq!(
+            parse_expression("not a > 1 and b").unwrap(),
+            binary(
+                BinaryOp::And,
+                Expr::Unary(
+                    UnaryOp::Not,
+                    Box::new(binary(
+                        BinaryOp::Greater,
+                        Expr::Variable("a".to_string()),
+                        Expr::Integer(1)
+                    ))
+                ),
+                Expr::Variable("b".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn test_lists_strings_and_calls() {
+        assert_eq!(
+            parse_expression("len([1, 2.5, \"a b\"])[0]").unwrap(),
+            Expr::Index(
+                Box::new(Expr::Call(
+                    "len".to_string(),
+                    vec![Expr::List(vec![
+                        Expr::Integer(1),
+                        Expr::Float(2.5),
+                        Expr::Text("a b".to_string())
+                    ])]
+                )),
+                Box::new(Expr::Integer(0))
+            )
+        );
+        assert_eq!(
+            parse_expression("${1} - -2").unwrap(),
+            binary(
+                BinaryOp::Subtract,
+                Expr::Variable("1".to_string()),
+                Expr::Unary(UnaryOp::Negate, Box::new(Expr::Integer(2)))
+            )
+        );
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        assert!(parse_expression("1 +").is_err());
+        assert!(parse_expression("(1 + 2").is_err());
+        assert!(parse_expression("1 2").is_err());
+        assert!(parse_expression("\"open").is_err());
+        let error = parse_expression("$x = 1").unwrap_err().to_string();
+        assert!(
+            error.contains("Use '==' to compare in '$x = 1'"),
+            "{}",
+            error
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use super::Connector;
+use crate::error::{Result, VshError};
+use crate::parser::tokenizer::Token;
+
+/// Check if the tokens at `i` form a list connector, returning it and how
+/// many tokens it spans
+pub fn connector_at(tokens: &[Token], i: usize) -> Option<(Connector, usize)> {
+    let token = &tokens[i];
+    let next_is = |word: &str| tokens.get(i + 1).is_some_and(|t| t.is_operator(word));
+
+    if token.is_operator(";") || token.is_operator("&") {
+        Some((Connector::Always, 1))
+    } else if token.is_operator("&&") {
+        Some((Connector::OnSuccess, 1))
+    } else if token.is_operator("||") {
+        Some((Connector::OnFailure, 1))
+    } else if token.is_operator("and") && next_is("then") {
+        Some((Connector::OnSuccess, 2))
+    } else if token.is_operator("and") && next_is("also") {
+        Some((Connector::Always, 2))
+    } else if token.is_operator("or") && next_is("else") {
+        Some((Connector::OnFailure, 2))
+    } else {
+        None
+    }
+}
+
+/// Split tokens into pipelines joined by connectors. The first pipeline is
+/// always run, so its connector is `Connector::Always`.
+pub fn split_list(tokens: &[Token]) -> Result<Vec<(Connector, &[Token])>> {
+    let mut items = Vec::new();
+    let mut connector = Connector::Always;
+    let mut start = 0;
+    let mut i = 0;
+
+    while i < tokens.len() {
+        match connector_at(tokens, i) {
+            Some((next, width)) => {
+                if i == start {
+                    return Err(VshError::InvalidSyntax(format!(
+                        "Missing command before '{}'",
+                        connector_words(tokens, i, width)
+                    )));
+                }
+                // `&` stays with the pipeline it sends to the background
+                let end = if tokens[i].is_operator("&") { i + 1 } else { i };
+                items.push((connector, &tokens[start..end]));
+                connector = next;
+                i += width;
+                start = i;
+            }
+            None => i += 1,
+        }
+    }
+
+    if start < tokens.len() {
+        items.push((connector, &tokens[start..]));
+    } else if connector != Connector::Always {
+        // A trailing ';' is fine, a trailing '&&' or '||' is not
+        return Err(VshError::InvalidSyntax(
+            "Missing command after the last connector".to_string(),
+        ));
+    }
+
+    Ok(items)
+}
+
+/// The connector as the user wrote it, for error messages
+fn connector_words(tokens: &[Token], i: usize, width: usize) -> String {
+    tokens[i..i + width]
+        .iter()
+        .map(|t| t.value.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tokenizer::tokenize;
+
+    #[test]
+    fn test_split_symbolic_connectors() {
+        let tokens = tokenize("mkdir x && cd x || echo failed; list").unwrap();
+        let items = split_list(&tokens).unwrap();
+        let connectors: Vec<_> = items.iter().map(|(c, _)| c.clone()).collect();
+        assert_eq!(
+            connectors,
+            vec![
+                Connector::Always,
+                Connector::OnSuccess,
+                Connector::OnFailure,
+                Connector::Always
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_verbose_connectors() {
+        let tokens = tokenize("copy a to b and then list b or else echo oops").unwrap();
+        let items = split_list(&tokens).unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[1].0, Connector::OnSuccess);
+        assert_eq!(items[1].1[0].value, "list");
+        assert_eq!(items[2].0, Connector::OnFailure);
+    }
+
+    #[test]
+    fn test_trailing_connectors() {
+        let tokens = tokenize("list;").unwrap();
+        assert_eq!(split_list(&tokens).unwrap().len(), 1);
+
+        let tokens = tokenize("list &&").unwrap();
+        assert!(split_list(&tokens).is_err());
+
+        let tokens = tokenize("; list").unwrap();
+        assert!(split_list(&tokens).is_err());
+    }
+
+    #[test]
+    fn test_background_ends_a_pipeline() {
+        let tokens = tokenize("make & list; sleep 5 &").unwrap();
+        let items = split_list(&tokens).unwrap();
+        let lengths: Vec<_> = items.iter().map(|(_, tokens)| tokens.len()).collect();
+        assert_eq!(lengths, vec![2, 1, 3]);
+        assert!(items[0].1[1].is_operator("&"));
+    }
+}
@@ -0,0 +1,97 @@
+use crate::error::{Result, VshError};
+use crate::parser::tokenizer::{Quote, Token};
+
+/// Parse a list literal like `["a.txt", "b.txt"]` or `[1, 2, 3]` into its
+/// items, keeping how each was quoted. Returns None if the words are not a
+/// list.
+pub fn parse_list(tokens: &[Token]) -> Result<Option<Vec<Token>>> {
+    let bare_text = |token: Option<&Token>, first: bool| -> Option<String> {
+        let parts = &token?.parts;
+        let (text, quote) = if first { parts.first() } else { parts.last() }?;
+        (*quote == Quote::Bare).then(|| text.clone())
+    };
+
+    if !bare_text(tokens.first(), true).is_some_and(|text| text.starts_with('[')) {
+        return Ok(None);
+    }
+    let closed = bare_text(tokens.last(), false).is_some_and(|text| {
+        // A lone "[" both opens and would close the list
+        text.ends_with(']') && !(tokens.len() == 1 && tokens[0].value == "[")
+    });
+    if !closed {
+        return Err(VshError::ParseError(
+            "Missing ']' to close the list".to_string(),
+        ));
+    }
+
+    let mut items = Vec::new();
+    let mut item = Vec::new();
+    let last = tokens.len() - 1;
+
+    for (i, token) in tokens.iter().enumerate() {
+        let last_part = token.parts.len() - 1;
+
+        for (j, (text, quote)) in token.parts.iter().enumerate() {
+            if *quote != Quote::Bare {
+                item.push((text.clone(), *quote));
+                continue;
+            }
+
+            let mut text = text.as_str();
+            if i == 0 && j == 0 {
+                text = &text[1..];
+            }
+            if i == last && j == last_part {
+                text = &text[..text.len() - 1];
+            }
+
+            for (k, piece) in text.split(',').enumerate() {
+                if k > 0 {
+                    finish_item(&mut items, &mut item);
+                }
+                if !piece.is_empty() {
+                    item.push((piece.to_string(), Quote::Bare));
+                }
+            }
+        }
+
+        // Spaces separate items just like commas
+        finish_item(&mut items, &mut item);
+    }
+
+    Ok(Some(items))
+}
+
+fn finish_item(items: &mut Vec<Token>, item: &mut Vec<(String, Quote)>) {
+    if !item.is_empty() {
+        items.push(Token::from_parts(std::mem::take(item)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tokenize;
+
+    fn items(input: &str) -> Result<Option<Vec<String>>> {
+        let tokens = tokenize(input)?;
+        Ok(parse_list(&tokens)?.map(|items| items.into_iter().map(|t| t.value).collect()))
+    }
+
+    #[test]
+    fn test_list_literals() {
+        let expected = Some(vec!["a.txt".to_string(), "b c.txt".to_string()]);
+        assert_eq!(items("[\"a.txt\", \"b c.txt\"]").unwrap(), expected);
+        assert_eq!(items("[a.txt,'b c.txt']").unwrap(), expected);
+        assert_eq!(items("[ a.txt \"b c.txt\" ]").unwrap(), expected);
+        assert_eq!(items("[]").unwrap(), Some(vec![]));
+    }
+
+    #[test]
+    fn test_not_a_list() {
+        assert_eq!(items("a.txt b.txt").unwrap(), None);
+        assert_eq!(items("\"[a]\"").unwrap(), None);
+        assert!(items("[a, b").is_err());
+        assert!(items("[").is_err());
+    }
+}
@@ -1,8 +1,21 @@
+mod assignment;
+mod block;
+mod expression;
+mod list;
+mod literal;
+mod pipeline;
+mod redirect;
 mod syntax;
 mod tokenizer;
 mod translator;
 
 use crate::error::{Result, VshError};
+use std::rc::Rc;
+
+pub use assignment::{is_identifier, parse_assignment, split_env_prefix, Assignment};
+pub use expression::{parse_expression, BinaryOp, Expr, UnaryOp};
+pub use literal::parse_list;
+pub use tokenizer::{read_substitution, tokenize, Quote, Token};
 
 /// Represents the intent/action the user wants to perform
 #[derive(Debug, Clone, PartialEq)]
@@ -12,6 +25,54 @@ pub enum Intent {
     Remove,
     List,
     ChangeDirectory,
+    /// `pushd`/`popd`/`dirs`: a stack of directories to come back to
+    PushDirectory,
+    PopDirectory,
+    Directories,
+    /// `mkdir`, `create directory`, `make-directory`
+    MakeDirectory,
+    /// `find`: walk directories for entries matching filters
+    Find,
+    /// `grep`/`search`: lines of files (or stdin) matching a pattern
+    Search,
+    /// `cat`/`show`: print files, or some of their lines
+    Show,
+    Head,
+    Tail,
+    /// `touch`/`create file`: create files or update their modification time
+    Touch,
+    /// Print the arguments
+    Echo,
+    /// Job control: list jobs, resume one in the foreground or background,
+    /// wait for jobs to finish, or send them a signal
+    Jobs,
+    Foreground,
+    Background,
+    Wait,
+    Kill,
+    Exit,
+    /// `export NAME=value` / `export NAME as value`
+    Export,
+    Unset,
+    /// Print the environment
+    Environment,
+    /// `last-status`: print the exit status of the previous command (`$?`)
+    LastStatus,
+    /// Predicates for conditions; they set the exit status and print nothing
+    FileExists,
+    DirectoryExists,
+    IsEmpty,
+    /// `compare a > b`, also what `if $a > $b` runs
+    Compare,
+    /// Leave the innermost loop, or skip to its next iteration
+    Break,
+    Continue,
+    /// Leave the running function, with an optional status or value
+    Return,
+    /// A function defined with `function name … end`
+    Function(String),
+    /// Any program found on $PATH, run with its arguments untouched
+    External(String),
     // Add more as we implement them
 }
 
@@ -29,14 +90,118 @@ pub struct Command {
     pub intent: Intent,
     pub args: CommandArgs,
     pub syntax_used: SyntaxStyle,
+    pub redirects: Vec<Redirect>,
+    /// `NAME=value` prefixes, set only in this command's environment
+    pub env: Vec<(String, String)>,
+}
+
+/// Pipelines joined by `;`, `&&` / `and then`, and `||` / `or else`
+#[derive(Debug, Clone)]
+pub struct CommandList {
+    pub items: Vec<(Connector, Pipeline)>,
+}
+
+/// When a pipeline in a list runs, based on the previous exit status
+#[derive(Debug, Clone, PartialEq)]
+pub enum Connector {
+    Always,    // ; or and also
+    OnSuccess, // && or and then
+    OnFailure, // || or or else
+}
+
+/// Where a command's standard streams are redirected to, in the order written
+#[derive(Debug, Clone, PartialEq)]
+pub enum Redirect {
+    /// `> file` / `output-to file`
+    Output(String),
+    /// `>> file` / `append-to file`
+    Append(String),
+    /// `< file` / `input-from file`
+    Input(String),
+    /// `2> file` / `errors-to file`
+    Errors(String),
+    /// `2>> file` / `append-errors-to file`
+    ErrorsAppend(String),
+    /// `2>&1` / `errors-to-output`
+    ErrorsToOutput,
+    /// `<<EOF … EOF`, `<<< text` / `with input """ … """`: text to read as
+    /// standard input
+    Text(String),
+}
+
+/// One step of a script: a command list or a control-flow block
+#[derive(Debug, Clone)]
+pub enum Statement {
+    Commands(CommandList),
+    If(IfBlock),
+    For(ForLoop),
+    While(WhileLoop),
+    Function(Rc<FunctionDef>),
+}
+
+/// `if … then … else if … then … else … end`
+#[derive(Debug, Clone)]
+pub struct IfBlock {
+    /// Each condition with the statements it guards, tried in order
+    pub branches: Vec<(CommandList, Vec<Statement>)>,
+    /// Statements run when no condition holds
+    pub otherwise: Vec<Statement>,
+}
+
+/// `for name in items do … end`
+#[derive(Debug, Clone)]
+pub struct ForLoop {
+    pub variable: String,
+    /// The words to loop over, expanded when the loop starts; ranges like
+    /// `1..10` and globs like `*.txt` become one item per number or file
+    pub items: Vec<Token>,
+    pub body: Vec<Statement>,
+}
+
+/// `while condition do … end`
+#[derive(Debug, Clone)]
+pub struct WhileLoop {
+    pub condition: CommandList,
+    pub body: Vec<Statement>,
+}
+
+/// `function name params… end`. Shared so a call doesn't copy the body.
+#[derive(Debug, Clone)]
+pub struct FunctionDef {
+    pub name: String,
+    /// Names bound to the arguments, in order; `$1`, `$2`, … work too
+    pub params: Vec<String>,
+    pub body: Vec<Statement>,
+}
+
+/// Commands connected by pipes, each feeding its output to the next
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    pub stages: Vec<SimpleCommand>,
+    /// Written with a leading `not` or `!`: success and failure swap
+    pub negated: bool,
+    /// Written with a trailing `&` or `in background`: the shell doesn't
+    /// wait for it
+    pub background: bool,
+}
+
+/// A single command exactly as written. Its words are expanded and then
+/// translated into a `Command` only when it runs, so variables and
+/// arguments always reflect the moment of execution.
+#[derive(Debug, Clone)]
+pub struct SimpleCommand {
+    pub tokens: Vec<Token>,
 }
 
 /// Command arguments in canonical form
 #[derive(Debug, Clone)]
 pub struct CommandArgs {
-    pub source: Option<String>,
+    /// Every file the command works on, in the order given
+    pub sources: Vec<String>,
     pub destination: Option<String>,
     pub path: Option<String>,
+    /// What `grep` looks for
+    pub pattern: Option<String>,
     pub flags: Vec<String>,
     pub extra: Vec<String>,
 }
@@ -44,9 +209,10 @@ pub struct CommandArgs {
 impl CommandArgs {
     pub fn new() -> Self {
         Self {
-            source: None,
+            sources: Vec::new(),
             destination: None,
             path: None,
+            pattern: None,
             flags: Vec::new(),
             extra: Vec::new(),
         }
@@ -64,23 +230,126 @@ pub fn parse_command(input: &str) -> Result<Command> {
     // Step 1: Tokenize
     let tokens = tokenizer::tokenize(input)?;
 
+    build_command(&tokens)
+}
+
+/// Parse a whole script or REPL entry, which may span several lines. Input
+/// that stops inside a block or quote fails with `VshError::Incomplete`.
+pub fn parse_script(input: &str) -> Result<Vec<Statement>> {
+    let tokens = tokenizer::tokenize(input)?;
+
+    block::parse_statements(&tokens)
+}
+
+/// Parse a full line: pipelines chained with list connectors
+pub fn parse_line(input: &str) -> Result<CommandList> {
+    let tokens = tokenizer::tokenize(input)?;
+
+    if tokens.is_empty() {
+        return Err(VshError::InvalidSyntax("Empty command".to_string()));
+    }
+
+    let items = list::split_list(&tokens)?
+        .into_iter()
+        .map(|(connector, tokens)| Ok((connector, build_pipeline(tokens)?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(CommandList { items })
+}
+
+/// Parse a line that may chain several commands with `|`, `->` or `then`
+pub fn parse_pipeline(input: &str) -> Result<Pipeline> {
+    let tokens = tokenizer::tokenize(input)?;
+
+    build_pipeline(&tokens)
+}
+
+/// Turn the tokens of a pipeline into its stages
+fn build_pipeline(tokens: &[Token]) -> Result<Pipeline> {
+    let (background, tokens) = pipeline::split_background(tokens);
+    let (negated, tokens) = block::split_negation(tokens);
+    let stages = pipeline::split_stages(tokens)?
+        .into_iter()
+        .map(|tokens| {
+            // Catch dangling redirections now rather than halfway through a run
+            redirect::extract_redirects(tokens)?;
+            Ok(SimpleCommand {
+                tokens: tokens.to_vec(),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Pipeline {
+        stages,
+        negated,
+        background,
+    })
+}
+
+/// Turn the (expanded) tokens of a single command into a canonical `Command`
+pub fn build_command(tokens: &[Token]) -> Result<Command> {
+    // Two-word commands like `create directory` act as one word
+    let joined: Vec<Token>;
+    let tokens = match syntax::command_phrase(tokens) {
+        Some(words) => {
+            joined = words
+                .iter()
+                .map(|word| Token::word(word))
+                .chain(tokens[2..].iter().cloned())
+                .collect();
+            &joined
+        }
+        None => tokens,
+    };
+
+    build(tokens, |tokens| {
+        // Identify intent, falling back to an external program
+        match syntax::identify_intent(&tokens[0].value) {
+            // `env` with arguments is the real program, as in `env -i cmd`
+            Ok(Intent::Environment) if tokens.len() > 1 => {
+                Intent::External(tokens[0].value.clone())
+            }
+            Ok(intent) => intent,
+            Err(_) => Intent::External(tokens[0].value.clone()),
+        }
+    })
+}
+
+/// Build a call to the user-defined function named by the first word. The
+/// shell checks its functions before any built-in, so they can replace one.
+pub fn build_function_call(tokens: &[Token]) -> Result<Command> {
+    build(tokens, |tokens| Intent::Function(tokens[0].value.clone()))
+}
+
+fn build(tokens: &[Token], identify: impl FnOnce(&[Token]) -> Intent) -> Result<Command> {
+    // Redirections apply to any command, so take them out first
+    let (tokens, redirects) = redirect::extract_redirects(tokens)?;
+    let tokens = tokens.as_slice();
+
     if tokens.is_empty() {
         return Err(VshError::InvalidSyntax("Empty command".to_string()));
     }
 
-    // Step 2: Detect syntax style
-    let syntax = syntax::detect_syntax(&tokens);
+    // Step 2: Identify intent
+    let intent = identify(tokens);
 
-    // Step 3: Identify intent
-    let intent = syntax::identify_intent(&tokens[0].value)?;
+    // Step 3: Detect syntax style (some commands always take their args as
+    // written, so `export path=x` is not a named argument)
+    let syntax = if syntax::takes_plain_args(&intent) {
+        SyntaxStyle::Terse
+    } else {
+        syntax::detect_syntax(tokens)
+    };
 
     // Step 4: Extract arguments based on syntax and intent
-    let args = translator::extract_args(&tokens, &syntax, &intent)?;
+    let args = translator::extract_args(tokens, &syntax, &intent)?;
 
     Ok(Command {
         intent,
         args,
         syntax_used: syntax,
+        redirects,
+        env: Vec::new(),
     })
 }
 
@@ -93,7 +362,7 @@ mod tests {
         let cmd = parse_command("cp file.txt backup/").unwrap();
         assert_eq!(cmd.intent, Intent::Copy);
         assert_eq!(cmd.syntax_used, SyntaxStyle::Terse);
-        assert_eq!(cmd.args.source, Some("file.txt".to_string()));
+        assert_eq!(cmd.args.sources, vec!["file.txt"]);
         assert_eq!(cmd.args.destination, Some("backup/".to_string()));
     }
 
@@ -102,7 +371,7 @@ mod tests {
         let cmd = parse_command("copy file.txt to backup/").unwrap();
         assert_eq!(cmd.intent, Intent::Copy);
         assert_eq!(cmd.syntax_used, SyntaxStyle::Verbose);
-        assert_eq!(cmd.args.source, Some("file.txt".to_string()));
+        assert_eq!(cmd.args.sources, vec!["file.txt"]);
         assert_eq!(cmd.args.destination, Some("backup/".to_string()));
     }
 
@@ -111,7 +380,131 @@ mod tests {
         let cmd = parse_command("copy source=file.txt destination=backup/").unwrap();
         assert_eq!(cmd.intent, Intent::Copy);
         assert_eq!(cmd.syntax_used, SyntaxStyle::Named);
-        assert_eq!(cmd.args.source, Some("file.txt".to_string()));
+        assert_eq!(cmd.args.sources, vec!["file.txt"]);
         assert_eq!(cmd.args.destination, Some("backup/".to_string()));
     }
+
+    #[test]
+    fn test_parse_external_command() {
+        let cmd = parse_command("git commit -m message=fix").unwrap();
+        assert_eq!(cmd.intent, Intent::External("git".to_string()));
+        assert_eq!(cmd.syntax_used, SyntaxStyle::Terse);
+        assert_eq!(cmd.args.extra, vec!["commit", "-m", "message=fix"]);
+    }
+
+    #[test]
+    fn test_parse_pipeline() {
+        let pipeline = parse_pipeline("list backup/ | grep \".txt\"").unwrap();
+        assert_eq!(pipeline.stages.len(), 2);
+
+        let list = build_command(&pipeline.stages[0].tokens).unwrap();
+        assert_eq!(list.intent, Intent::List);
+
+        let grep = build_command(&pipeline.stages[1].tokens).unwrap();
+        assert_eq!(grep.intent, Intent::Search);
+        assert_eq!(grep.args.pattern, Some(".txt".to_string()));
+    }
+
+    #[test]
+    fn test_parse_redirects() {
+        let cmd = parse_command("list backup/ > listing.txt").unwrap();
+        assert_eq!(cmd.intent, Intent::List);
+        assert_eq!(cmd.args.path, Some("backup/".to_string()));
+        assert_eq!(
+            cmd.redirects,
+            vec![Redirect::Output("listing.txt".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parse_line_with_connectors() {
+        let list = parse_line("cp a.txt b.txt && list then grep b; remove a.txt").unwrap();
+        assert_eq!(list.items.len(), 3);
+        assert_eq!(list.items[1].0, Connector::OnSuccess);
+        assert_eq!(list.items[1].1.stages.len(), 2);
+        assert_eq!(list.items[2].0, Connector::Always);
+        let remove = build_command(&list.items[2].1.stages[0].tokens).unwrap();
+        assert_eq!(remove.intent, Intent::Remove);
+    }
+
+    #[test]
+    fn test_file_content_commands() {
+        let show = parse_command("show first 10 lines of log.txt").unwrap();
+        assert_eq!(show.intent, Intent::Show);
+        assert_eq!(show.args.sources, vec!["log.txt"]);
+        assert_eq!(show.args.flags, vec!["--first=10"]);
+
+        let tail = parse_command("tail -n 5 a.txt b.txt").unwrap();
+        assert_eq!(tail.intent, Intent::Tail);
+        assert_eq!(tail.args.flags, vec!["--lines=5"]);
+        assert_eq!(tail.args.sources, vec!["a.txt", "b.txt"]);
+
+        let touch = parse_command("create file notes.md").unwrap();
+        assert_eq!(touch.intent, Intent::Touch);
+        assert_eq!(touch.args.sources, vec!["notes.md"]);
+
+        // Words after `echo` are printed as written, never read as options
+        let echo = parse_command("echo copy to dest=here").unwrap();
+        assert_eq!(echo.intent, Intent::Echo);
+        assert_eq!(echo.args.extra, vec!["copy", "to", "dest=here"]);
+    }
+
+    #[test]
+    fn test_make_directory_in_every_syntax() {
+        let terse = parse_command("mkdir -p -m 700 a/b c").unwrap();
+        assert_eq!(terse.intent, Intent::MakeDirectory);
+        assert_eq!(terse.args.sources, vec!["a/b", "c"]);
+        assert_eq!(terse.args.flags, vec!["--mode=700", "-p"]);
+
+        let verbose = parse_command("create directory a/b with parents").unwrap();
+        assert_eq!(verbose.intent, Intent::MakeDirectory);
+        assert_eq!(verbose.args.sources, vec!["a/b"]);
+        assert_eq!(verbose.args.flags, vec!["--parents"]);
+
+        let named = parse_command("make-directory path=a/b parents=true mode=755").unwrap();
+        assert_eq!(named.args.path, Some("a/b".to_string()));
+        assert_eq!(named.args.flags, vec!["--parents", "--mode=755"]);
+
+        // Quoted, it's a program called `create` with an argument
+        let quoted = parse_command("create \"directory\"").unwrap();
+        assert_eq!(quoted.intent, Intent::External("create".to_string()));
+    }
+
+    #[test]
+    fn test_parse_background_jobs() {
+        let list = parse_line("make all & sleep 5 in background; jobs").unwrap();
+        let background: Vec<_> = list.items.iter().map(|(_, p)| p.background).collect();
+        assert_eq!(background, vec![true, true, false]);
+        assert_eq!(list.items[1].1.stages[0].tokens.len(), 2);
+
+        // Quoted, it's just a word
+        let quoted = parse_pipeline("search x in \"background\"").unwrap();
+        assert!(!quoted.background);
+
+        let kill = parse_command("kill -9 %1").unwrap();
+        assert_eq!(kill.intent, Intent::Kill);
+        assert_eq!(kill.args.extra, vec!["-9", "%1"]);
+    }
+
+    #[test]
+    fn test_directory_navigation() {
+        let back = parse_command("go back").unwrap();
+        assert_eq!(back.intent, Intent::ChangeDirectory);
+        assert_eq!(back.args.path, Some("-".to_string()));
+
+        let up = parse_command("go up").unwrap();
+        assert_eq!(up.args.path, Some("..".to_string()));
+
+        // With nowhere to go, `cd` goes home
+        let home = parse_command("cd").unwrap();
+        assert_eq!(home.args.path, None);
+
+        let push = parse_command("pushd src").unwrap();
+        assert_eq!(push.intent, Intent::PushDirectory);
+        assert_eq!(push.args.path, Some("src".to_string()));
+
+        let dirs = parse_command("dirs -v").unwrap();
+        assert_eq!(dirs.intent, Intent::Directories);
+        assert_eq!(dirs.args.extra, vec!["-v"]);
+    }
 }
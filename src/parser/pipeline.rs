@@ -0,0 +1,84 @@
+use crate::error::{Result, VshError};
+use crate::parser::tokenizer::Token;
+
+/// Check if a token connects two pipeline stages (`|`, `->` or `then`)
+pub fn is_pipe_operator(token: &Token) -> bool {
+    token.is_operator("|") || token.is_operator("->") || token.is_operator("then")
+}
+
+/// Take a trailing `&` or `in background` off a pipeline, saying whether
+/// there was one
+pub fn split_background(tokens: &[Token]) -> (bool, &[Token]) {
+    match tokens {
+        [rest @ .., last] if last.is_operator("&") => (true, rest),
+        [rest @ .., word, last]
+            if !rest.is_empty() && word.is_operator("in") && last.is_operator("background") =>
+        {
+            (true, rest)
+        }
+        _ => (false, tokens),
+    }
+}
+
+/// Split tokens into pipeline stages at every pipe operator
+pub fn split_stages(tokens: &[Token]) -> Result<Vec<&[Token]>> {
+    let mut stages = Vec::new();
+    let mut start = 0;
+
+    for (i, token) in tokens.iter().enumerate() {
+        if is_pipe_operator(token) {
+            if i == start {
+                return Err(VshError::InvalidSyntax(format!(
+                    "Missing command before '{}'",
+                    token.value
+                )));
+            }
+            stages.push(&tokens[start..i]);
+            start = i + 1;
+        }
+    }
+
+    if start == tokens.len() {
+        // Either empty input or a trailing pipe operator
+        return Err(match tokens.last() {
+            Some(last) => {
+                VshError::InvalidSyntax(format!("Missing command after '{}'", last.value))
+            }
+            None => VshError::InvalidSyntax("Empty command".to_string()),
+        });
+    }
+    stages.push(&tokens[start..]);
+
+    Ok(stages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tokenizer::tokenize;
+
+    #[test]
+    fn test_split_all_pipe_styles() {
+        let tokens = tokenize("list | grep a -> sort then head").unwrap();
+        let stages = split_stages(&tokens).unwrap();
+        assert_eq!(stages.len(), 4);
+        assert_eq!(stages[1][0].value, "grep");
+        assert_eq!(stages[3][0].value, "head");
+    }
+
+    #[test]
+    fn test_quoted_operator_is_a_word() {
+        let tokens = tokenize("grep \"then\" file.txt").unwrap();
+        let stages = split_stages(&tokens).unwrap();
+        assert_eq!(stages.len(), 1);
+    }
+
+    #[test]
+    fn test_dangling_pipe() {
+        let tokens = tokenize("list |").unwrap();
+        assert!(split_stages(&tokens).is_err());
+
+        let tokens = tokenize("| grep a").unwrap();
+        assert!(split_stages(&tokens).is_err());
+    }
+}
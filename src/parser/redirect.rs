@@ -0,0 +1,171 @@
+use super::Redirect;
+use crate::error::{Result, VshError};
+use crate::parser::tokenizer::Token;
+
+/// Separate redirections (`> file`, `append-to file`, ...) from a command's words
+pub fn extract_redirects(tokens: &[Token]) -> Result<(Vec<Token>, Vec<Redirect>)> {
+    let mut words = Vec::new();
+    let mut redirects = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let token = &tokens[i];
+
+        if token.is_operator("2>&1") || token.is_operator("errors-to-output") {
+            redirects.push(Redirect::ErrorsToOutput);
+            i += 1;
+            continue;
+        }
+
+        // `with input` is the one redirection written as two words
+        let with_input =
+            token.is_operator("with") && tokens.get(i + 1).is_some_and(|t| t.is_operator("input"));
+        let make: Option<fn(String) -> Redirect> = if with_input {
+            Some(line_input)
+        } else if token.is_quoted {
+            None
+        } else {
+            match token.value.as_str() {
+                ">" | "output-to" | "redirect-output" => Some(Redirect::Output),
+                ">>" | "append-to" => Some(Redirect::Append),
+                "<" | "input-from" => Some(Redirect::Input),
+                "2>" | "errors-to" => Some(Redirect::Errors),
+                "2>>" | "append-errors-to" => Some(Redirect::ErrorsAppend),
+                "<<" | "<<-" => Some(Redirect::Text),
+                "<<<" => Some(line_input),
+                _ => None,
+            }
+        };
+
+        match make {
+            Some(make) => {
+                let operator = if with_input {
+                    "with input"
+                } else {
+                    &token.value
+                };
+                i += usize::from(with_input);
+                let target = tokens
+                    .get(i + 1)
+                    .filter(|t| t.is_quoted || !is_redirect_word(&t.value))
+                    .ok_or_else(|| {
+                        let expected = match operator {
+                            "<<" | "<<-" => "a delimiter",
+                            "<<<" | "with input" => "text",
+                            _ => "a file",
+                        };
+                        VshError::InvalidSyntax(format!(
+                            "Expected {} after '{}'",
+                            expected, operator
+                        ))
+                    })?;
+                redirects.push(make(target.value.clone()));
+                i += 2;
+            }
+            None => {
+                words.push(token.clone());
+                i += 1;
+            }
+        }
+    }
+
+    Ok((words, redirects))
+}
+
+/// Text given as input ends with a newline, like any line typed in
+fn line_input(text: String) -> Redirect {
+    match text.is_empty() || text.ends_with('\n') {
+        true => Redirect::Text(text),
+        false => Redirect::Text(text + "\n"),
+    }
+}
+
+/// Check if an unquoted word is a redirection operator
+fn is_redirect_word(word: &str) -> bool {
+    matches!(
+        word,
+        ">" | ">>"
+            | "<"
+            | "2>"
+            | "2>>"
+            | "2>&1"
+            | "<<"
+            | "<<-"
+            | "<<<"
+            | "output-to"
+            | "redirect-output"
+            | "append-to"
+            | "input-from"
+            | "errors-to"
+            | "append-errors-to"
+            | "errors-to-output"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tokenizer::tokenize;
+
+    #[test]
+    fn test_symbolic_redirects() {
+        let tokens = tokenize("sort < in.txt > out.txt 2>> err.log").unwrap();
+        let (words, redirects) = extract_redirects(&tokens).unwrap();
+        assert_eq!(words.len(), 1);
+        assert_eq!(
+            redirects,
+            vec![
+                Redirect::Input("in.txt".to_string()),
+                Redirect::Output("out.txt".to_string()),
+                Redirect::ErrorsAppend("err.log".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_word_redirects() {
+        let tokens = tokenize("list backup/ append-to log.txt errors-to \"err file\"").unwrap();
+        let (words, redirects) = extract_redirects(&tokens).unwrap();
+        assert_eq!(words.len(), 2);
+        assert_eq!(
+            redirects,
+            vec![
+                Redirect::Append("log.txt".to_string()),
+                Redirect::Errors("err file".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text_redirects() {
+        let tokens = tokenize("wc -l <<< \"a b\" with input x <<EOF\nline\nEOF").unwrap();
+        let (words, redirects) = extract_redirects(&tokens).unwrap();
+        // The newline that ends the `<<EOF` line stays
+        assert_eq!(words.len(), 3);
+        assert_eq!(
+            redirects,
+            vec![
+                Redirect::Text("a b\n".to_string()),
+                Redirect::Text("x\n".to_string()),
+                Redirect::Text("line\n".to_string()),
+            ]
+        );
+
+        let tokens = tokenize("sort with input").unwrap();
+        let error = extract_redirects(&tokens).unwrap_err().to_string();
+        assert!(
+            error.contains("Expected text after 'with input'"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_missing_redirect_target() {
+        let tokens = tokenize("list >").unwrap();
+        assert!(extract_redirects(&tokens).is_err());
+
+        let tokens = tokenize("list > >> x").unwrap();
+        assert!(extract_redirects(&tokens).is_err());
+    }
+}
@@ -6,7 +6,7 @@ use crate::parser::tokenizer::Token;
 pub fn detect_syntax(tokens: &[Token]) -> SyntaxStyle {
     // Check for named parameters (key=value)
     for token in tokens {
-        if token.value.contains('=') && !token.is_quoted {
+        if token.key_value().is_some() {
             return SyntaxStyle::Named;
         }
     }
@@ -22,14 +22,60 @@ pub fn detect_syntax(tokens: &[Token]) -> SyntaxStyle {
     SyntaxStyle::Terse
 }
 
+/// Check if an intent takes its arguments exactly as written, never as
+/// named or verbose syntax
+pub fn takes_plain_args(intent: &Intent) -> bool {
+    matches!(
+        intent,
+        Intent::External(_)
+            | Intent::Export
+            | Intent::Unset
+            | Intent::FileExists
+            | Intent::DirectoryExists
+            | Intent::IsEmpty
+            | Intent::Compare
+            | Intent::Return
+            | Intent::Function(_)
+            | Intent::Echo
+            | Intent::PopDirectory
+            | Intent::Directories
+            | Intent::Jobs
+            | Intent::Foreground
+            | Intent::Background
+            | Intent::Wait
+            | Intent::Kill
+    )
+}
+
 /// Check if a word is a connector in verbose syntax
 fn is_connector_word(word: &str) -> bool {
     matches!(
         word,
-        "to" | "from" | "in" | "into" | "with" | "without" | "at" | "on"
+        "to" | "from" | "in" | "into" | "with" | "without" | "at" | "on" | "and"
     )
 }
 
+/// The words that stand for a two-word command like `create directory` or
+/// `go back`
+pub fn command_phrase(tokens: &[Token]) -> Option<&'static [&'static str]> {
+    let bare = |i: usize| {
+        tokens
+            .get(i)
+            .filter(|t| !t.is_quoted)
+            .map(|t| t.value.as_str())
+    };
+    match (bare(0)?, bare(1)?) {
+        ("create", "directory" | "folder" | "dir") => Some(&["make-directory"]),
+        ("create", "file") => Some(&["create-file"]),
+        // `go` alone is the Go toolchain, so only these
+        ("go", "back") => Some(&["cd", "-"]),
+        ("go", "home") => Some(&["cd"]),
+        ("go", "up") => Some(&["cd", ".."]),
+        ("go", "to") => Some(&["cd"]),
+        _ => None,
+    }
+}
+
 /// Identify the intent from the command word
 pub fn identify_intent(command: &str) -> Result<Intent> {
     match command.to_lowercase().as_str() {
@@ -47,6 +93,50 @@ pub fn identify_intent(command: &str) -> Result<Intent> {
 
         // Change directory
         "cd" | "change-directory" | "goto" => Ok(Intent::ChangeDirectory),
+        "pushd" | "push-directory" => Ok(Intent::PushDirectory),
+        "popd" | "pop-directory" => Ok(Intent::PopDirectory),
+        "dirs" | "directory-stack" => Ok(Intent::Directories),
+
+        // Make directory
+        "mkdir" | "make-directory" | "create-directory" => Ok(Intent::MakeDirectory),
+
+        // Search for files
+        "find" => Ok(Intent::Find),
+        "grep" | "search" => Ok(Intent::Search),
+
+        // File contents
+        "cat" | "show" => Ok(Intent::Show),
+        "head" => Ok(Intent::Head),
+        "tail" => Ok(Intent::Tail),
+        "touch" | "create-file" => Ok(Intent::Touch),
+        "echo" | "print" => Ok(Intent::Echo),
+
+        // Jobs
+        "jobs" => Ok(Intent::Jobs),
+        "fg" | "foreground" => Ok(Intent::Foreground),
+        "bg" | "background" => Ok(Intent::Background),
+        "wait" => Ok(Intent::Wait),
+        "kill" => Ok(Intent::Kill),
+
+        // Leave the shell or script
+        "exit" | "quit" => Ok(Intent::Exit),
+
+        // Environment variables
+        "export" => Ok(Intent::Export),
+        "unset" => Ok(Intent::Unset),
+        "env" | "environment" => Ok(Intent::Environment),
+        "last-status" => Ok(Intent::LastStatus),
+
+        // Predicates for `if` and `while`
+        "file-exists" => Ok(Intent::FileExists),
+        "directory-exists" | "dir-exists" => Ok(Intent::DirectoryExists),
+        "is-empty" => Ok(Intent::IsEmpty),
+        "compare" => Ok(Intent::Compare),
+
+        // Loop control
+        "break" => Ok(Intent::Break),
+        "continue" => Ok(Intent::Continue),
+        "return" => Ok(Intent::Return),
 
         _ => Err(VshError::UnknownCommand(command.to_string())),
     }
@@ -58,38 +148,17 @@ mod tests {
 
     #[test]
     fn test_detect_named_syntax() {
-        let tokens = vec![
-            Token {
-                value: "copy".to_string(),
-                is_quoted: false,
-            },
-            Token {
-                value: "source=file.txt".to_string(),
-                is_quoted: false,
-            },
-        ];
+        let tokens = vec![Token::word("copy"), Token::word("source=file.txt")];
         assert_eq!(detect_syntax(&tokens), SyntaxStyle::Named);
     }
 
     #[test]
     fn test_detect_verbose_syntax() {
         let tokens = vec![
-            Token {
-                value: "copy".to_string(),
-                is_quoted: false,
-            },
-            Token {
-                value: "file.txt".to_string(),
-                is_quoted: false,
-            },
-            Token {
-                value: "to".to_string(),
-                is_quoted: false,
-            },
-            Token {
-                value: "backup/".to_string(),
-                is_quoted: false,
-            },
+            Token::word("copy"),
+            Token::word("file.txt"),
+            Token::word("to"),
+            Token::word("backup/"),
         ];
         assert_eq!(detect_syntax(&tokens), SyntaxStyle::Verbose);
     }
@@ -97,18 +166,9 @@ mod tests {
     #[test]
     fn test_detect_terse_syntax() {
         let tokens = vec![
-            Token {
-                value: "cp".to_string(),
-                is_quoted: false,
-            },
-            Token {
-                value: "file.txt".to_string(),
-                is_quoted: false,
-            },
-            Token {
-                value: "backup/".to_string(),
-                is_quoted: false,
-            },
+            Token::word("cp"),
+            Token::word("file.txt"),
+            Token::word("backup/"),
         ];
         assert_eq!(detect_syntax(&tokens), SyntaxStyle::Terse);
     }
@@ -1,89 +1,429 @@
 use crate::error::{Result, VshError};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// How a piece of a word was quoted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quote {
+    /// Bare text: expanded and split into words
+    Bare,
+    /// `"double quotes"`: expanded, but always one word
+    Double,
+    /// `'single quotes'` or a `\` escape: taken literally
+    Single,
+}
 
 /// A token in the command
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub value: String,
     pub is_quoted: bool,
+    /// The pieces the word was written in, e.g. `name="my file"` is `name=`
+    /// (bare) followed by `my file` (double quoted)
+    pub parts: Vec<(String, Quote)>,
+}
+
+impl Token {
+    /// A bare, unquoted word
+    pub fn word(value: &str) -> Self {
+        Self::from_parts(vec![(value.to_string(), Quote::Bare)])
+    }
+
+    /// A word written entirely inside double quotes
+    pub fn quoted(value: &str) -> Self {
+        Self::from_parts(vec![(value.to_string(), Quote::Double)])
+    }
+
+    /// Build a word from its pieces, merging neighbours quoted the same way
+    pub fn from_parts(pieces: Vec<(String, Quote)>) -> Self {
+        let mut parts: Vec<(String, Quote)> = Vec::with_capacity(pieces.len());
+        for (text, quote) in pieces {
+            match parts.last_mut() {
+                Some((last, last_quote)) if *last_quote == quote => last.push_str(&text),
+                _ => parts.push((text, quote)),
+            }
+        }
+
+        Self {
+            value: parts.iter().map(|(text, _)| text.as_str()).collect(),
+            is_quoted: parts.iter().any(|(_, quote)| *quote != Quote::Bare),
+            parts,
+        }
+    }
+
+    /// Check if this token is the given operator rather than a quoted word
+    pub fn is_operator(&self, operator: &str) -> bool {
+        !self.is_quoted && self.value == operator
+    }
+
+    /// Split a word written as `key=value` with a bare key, like
+    /// `dest=backup/` or `name="my file"`. The key must look like a name,
+    /// so `--mode=700` and `^level=` are not named arguments.
+    pub fn key_value(&self) -> Option<(&str, Token)> {
+        let (first, quote) = self.parts.first()?;
+        if *quote != Quote::Bare {
+            return None;
+        }
+
+        let (key, rest) = first.split_once('=')?;
+        let mut chars = key.chars();
+        if !chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return None;
+        }
+
+        let mut value = vec![(rest.to_string(), Quote::Bare)];
+        value.extend(self.parts[1..].iter().cloned());
+        Some((key, Token::from_parts(value)))
+    }
 }
 
 /// Tokenize input string into individual tokens
-/// Handles quotes, escapes, and basic splitting
+/// Handles quotes, escapes, operators, and basic splitting
 pub fn tokenize(input: &str) -> Result<Vec<Token>> {
     let mut tokens = Vec::new();
-    let mut current_token = String::new();
-    let mut in_quotes = false;
-    let mut quote_char = ' ';
-    let mut escape_next = false;
+    // Pieces of the word being read; adjacent quoted and bare text join up
+    let mut word = Word::default();
+    // `<<` operators whose documents start after the next newline
+    let mut heredocs = Vec::new();
 
-    for ch in input.chars() {
-        if escape_next {
-            current_token.push(ch);
-            escape_next = false;
-            continue;
-        }
+    let mut chars = input.chars().peekable();
 
+    while let Some(ch) = chars.next() {
         match ch {
             '\\' => {
-                escape_next = true;
+                if let Some(escaped) = chars.next() {
+                    word.push(escaped, Quote::Single);
+                }
+            }
+            // `"""` or `'''` starts a block of text that may span lines
+            '"' | '\'' if repeats(&chars, ch) => {
+                chars.nth(1);
+                let text = read_text_block(&mut chars, ch)?;
+                word.push_document(&text, ch == '"');
             }
             '"' | '\'' => {
-                if in_quotes && ch == quote_char {
-                    // End quote
-                    in_quotes = false;
-                    if !current_token.is_empty() {
-                        tokens.push(Token {
-                            value: current_token.clone(),
-                            is_quoted: true,
-                        });
-                        current_token.clear();
-                    }
-                } else if !in_quotes {
-                    // Start quote
-                    in_quotes = true;
-                    quote_char = ch;
-                    if !current_token.is_empty() {
-                        tokens.push(Token {
-                            value: current_token.clone(),
-                            is_quoted: false,
-                        });
-                        current_token.clear();
-                    }
+                let quote = if ch == '"' {
+                    Quote::Double
                 } else {
-                    // Quote inside different quote type
-                    current_token.push(ch);
+                    Quote::Single
+                };
+                word.start(quote);
+
+                let mut closed = false;
+                while let Some(c) = chars.next() {
+                    match c {
+                        _ if c == ch => {
+                            closed = true;
+                            break;
+                        }
+                        '$' if quote == Quote::Double && chars.peek() == Some(&'(') => {
+                            chars.next();
+                            word.push_substitution(read_substitution(&mut chars)?, quote);
+                        }
+                        // Inside double quotes a backslash only escapes what
+                        // would otherwise be special, as in bash, so
+                        // `echo -e "a\tb"` keeps its `\t`
+                        '\\' if quote == Quote::Double => match chars.next() {
+                            Some(escaped @ ('"' | '\\' | '$' | '`' | '\n')) => {
+                                word.push(escaped, Quote::Single)
+                            }
+                            Some(other) => {
+                                word.push('\\', quote);
+                                word.push(other, quote);
+                            }
+                            None => {}
+                        },
+                        _ => word.push(c, quote),
+                    }
+                }
+
+                if !closed {
+                    return Err(VshError::Incomplete("Unclosed quote".to_string()));
                 }
             }
+            // `$(command)` is one piece of a word, spaces and all
+            '$' if chars.peek() == Some(&'(') => {
+                chars.next();
+                word.push_substitution(read_substitution(&mut chars)?, Quote::Bare);
+            }
+            '#' if word.is_empty() => {
+                // A comment runs to the end of the line
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
             ' ' | '\t' => {
-                if in_quotes {
-                    current_token.push(ch);
-                } else if !current_token.is_empty() {
-                    tokens.push(Token {
-                        value: current_token.clone(),
-                        is_quoted: false,
-                    });
-                    current_token.clear();
-                }
+                word.finish(&mut tokens);
             }
-            _ => {
-                current_token.push(ch);
+            '\n' => {
+                // Newlines separate statements
+                word.finish(&mut tokens);
+                tokens.push(Token::word("\n"));
+                read_heredocs(&mut chars, &mut tokens, &mut heredocs)?;
             }
+            // '&' only ends a command (sending it to the background) at the
+            // end of a word, so `a&b` and URLs stay one word
+            '|' | '>' | '<' | ';' | '&'
+                if ch != '&'
+                    || matches!(chars.peek(), None | Some('&' | ' ' | '\t' | '\n' | ';')) =>
+            {
+                // A lone "2" right before '>' names the stderr stream, and a
+                // lone "-" makes the arrow pipe "->"
+                let prefix = match word.parts.as_slice() {
+                    [(text, Quote::Bare)] if ch == '>' && (text == "2" || text == "-") => {
+                        Some(text.clone())
+                    }
+                    _ => None,
+                };
+                let mut operator = match prefix {
+                    Some(prefix) => {
+                        word = Word::default();
+                        prefix
+                    }
+                    None => {
+                        word.finish(&mut tokens);
+                        String::new()
+                    }
+                };
+
+                // Operators always stand alone, even without surrounding spaces
+                operator.push(ch);
+                read_operator(&mut operator, &mut chars);
+                if operator == "<<" || operator == "<<-" {
+                    heredocs.push(tokens.len());
+                }
+                tokens.push(Token::word(&operator));
+            }
+            _ => word.push(ch, Quote::Bare),
         }
     }
 
     // Add final token
-    if !current_token.is_empty() {
-        tokens.push(Token {
-            value: current_token,
-            is_quoted: in_quotes,
+    word.finish(&mut tokens);
+
+    if let Some(&operator) = heredocs.first() {
+        return Err(match tokens.get(operator + 1) {
+            Some(_) => VshError::Incomplete("Unclosed here-document".to_string()),
+            None => VshError::InvalidSyntax(format!(
+                "Expected a delimiter after '{}'",
+                tokens[operator].value
+            )),
         });
     }
 
-    if in_quotes {
-        return Err(VshError::ParseError("Unclosed quote".to_string()));
+    Ok(tokens)
+}
+
+/// Read the documents of the `<<` operators on the line just ended, each up
+/// to the line holding only its delimiter, and put each in place of its
+/// delimiter. `<<-` strips leading tabs; a quoted delimiter turns off
+/// expansion in the document.
+fn read_heredocs(
+    chars: &mut Peekable<Chars>,
+    tokens: &mut [Token],
+    heredocs: &mut Vec<usize>,
+) -> Result<()> {
+    for operator in heredocs.drain(..) {
+        let strip_tabs = tokens[operator].value == "<<-";
+        let delimiter = match tokens.get(operator + 1) {
+            Some(token) if token.is_quoted || token.value != "\n" => token.clone(),
+            _ => {
+                return Err(VshError::InvalidSyntax(format!(
+                    "Expected a delimiter after '{}'",
+                    tokens[operator].value
+                )))
+            }
+        };
+
+        let mut text = String::new();
+        loop {
+            if chars.peek().is_none() {
+                return Err(VshError::Incomplete(format!(
+                    "Missing '{}' to end the here-document",
+                    delimiter.value
+                )));
+            }
+            let line: String = chars.by_ref().take_while(|&c| c != '\n').collect();
+            let line = match strip_tabs {
+                true => line.trim_start_matches('\t'),
+                false => line.as_str(),
+            };
+            if line == delimiter.value {
+                break;
+            }
+            text.push_str(line);
+            text.push('\n');
+        }
+
+        let mut document = Word::default();
+        document.push_document(&text, !delimiter.is_quoted);
+        tokens[operator + 1] = Token::from_parts(document.parts);
     }
+    Ok(())
+}
 
-    Ok(tokens)
+/// Read a `"""` block up to its closing quotes. A newline right after the
+/// opening quotes is dropped, and when the closing quotes sit on a line of
+/// their own, their indentation is taken off every line.
+fn read_text_block(chars: &mut Peekable<Chars>, quote: char) -> Result<String> {
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some(c) if c == quote && repeats(chars, quote) => {
+                chars.nth(1);
+                break;
+            }
+            Some(c) => text.push(c),
+            None => {
+                return Err(VshError::Incomplete(format!(
+                    "Missing {0}{0}{0} to end the text",
+                    quote
+                )))
+            }
+        }
+    }
+
+    let text = text.strip_prefix('\n').unwrap_or(&text);
+    let (body, indent) = text.rsplit_once('\n').unwrap_or(("", text));
+    if !text.contains('\n') || !indent.chars().all(|c| c == ' ' || c == '\t') {
+        return Ok(text.to_string());
+    }
+
+    Ok(body
+        .split('\n')
+        .map(|line| format!("{}\n", line.strip_prefix(indent).unwrap_or(line)))
+        .collect())
+}
+
+/// Read the command of a `$(command)`, just after its `$(`, up to the
+/// matching `)`. Parentheses nest, and quoted ones don't count.
+pub fn read_substitution(chars: &mut Peekable<Chars>) -> Result<String> {
+    let mut command = String::new();
+    let mut depth = 0;
+    let mut quote = None;
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => {
+                command.push(c);
+                if let Some(escaped) = chars.next() {
+                    command.push(escaped);
+                }
+                continue;
+            }
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') if depth == 0 => return Ok(command),
+            (None, ')') => depth -= 1,
+            _ => {}
+        }
+        command.push(c);
+    }
+
+    Err(VshError::Incomplete(
+        "Unclosed command substitution".to_string(),
+    ))
+}
+
+/// Check if the next two characters are both `quote`, closing or opening
+/// a `"""` block
+fn repeats(chars: &Peekable<Chars>, quote: char) -> bool {
+    let mut ahead = chars.clone();
+    ahead.next() == Some(quote) && ahead.next() == Some(quote)
+}
+
+/// Extend a single-character operator into the longest operator it starts
+/// (`||`, `&&`, `>>`, `2>>`, `2>&1`, `<<`, `<<-`, `<<<`)
+fn read_operator(operator: &mut String, chars: &mut Peekable<Chars>) {
+    match operator.as_str() {
+        "<" if chars.next_if_eq(&'<').is_some() => {
+            operator.push('<');
+            if let Some(c) = chars.next_if(|&c| c == '<' || c == '-') {
+                operator.push(c);
+            }
+        }
+        "|" if chars.peek() == Some(&'|') => {
+            operator.push('|');
+            chars.next();
+        }
+        "&" if chars.peek() == Some(&'&') => {
+            operator.push('&');
+            chars.next();
+        }
+        ">" | "2>" if chars.peek() == Some(&'>') => {
+            operator.push('>');
+            chars.next();
+        }
+        "2>" => {
+            let mut ahead = chars.clone();
+            if ahead.next() == Some('&') && ahead.next() == Some('1') {
+                operator.push_str("&1");
+                chars.nth(1);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The word being read by the tokenizer
+#[derive(Default)]
+struct Word {
+    parts: Vec<(String, Quote)>,
+}
+
+impl Word {
+    fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    /// Begin a quoted piece; even `""` makes a (empty) word
+    fn start(&mut self, quote: Quote) {
+        self.parts.push((String::new(), quote));
+    }
+
+    fn push(&mut self, ch: char, quote: Quote) {
+        match self.parts.last_mut() {
+            Some((text, last)) if *last == quote => text.push(ch),
+            _ => self.parts.push((ch.to_string(), quote)),
+        }
+    }
+
+    /// Put a `$(command)` back together as text, to be run when the word
+    /// is expanded
+    fn push_substitution(&mut self, command: String, quote: Quote) {
+        for ch in format!("$({})", command).chars() {
+            self.push(ch, quote);
+        }
+    }
+
+    /// Add the text of a document: expanded like double-quoted text, where
+    /// a backslash escapes `$`, `` ` `` and itself, or else taken literally
+    fn push_document(&mut self, text: &str, expand: bool) {
+        self.start(if expand { Quote::Double } else { Quote::Single });
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if expand => match chars.next_if(|c| matches!(c, '$' | '`' | '\\')) {
+                    Some(escaped) => self.push(escaped, Quote::Single),
+                    None => self.push(c, Quote::Double),
+                },
+                _ if expand => self.push(c, Quote::Double),
+                _ => self.push(c, Quote::Single),
+            }
+        }
+    }
+
+    /// Push the word collected so far (if any) as a token
+    fn finish(&mut self, tokens: &mut Vec<Token>) {
+        if !self.parts.is_empty() {
+            tokens.push(Token::from_parts(std::mem::take(&mut self.parts)));
+        }
+    }
 }
 
 #[cfg(test)]
@@ -112,6 +452,9 @@ mod tests {
         let tokens = tokenize("copy source=file.txt destination=backup/").unwrap();
         assert_eq!(tokens.len(), 3);
         assert_eq!(tokens[1].value, "source=file.txt");
+        assert!(tokens[1].key_value().is_some());
+        assert!(Token::word("--mode=700").key_value().is_none());
+        assert!(Token::word("^level=").key_value().is_none());
     }
 
     #[test]
@@ -119,4 +462,138 @@ mod tests {
         let result = tokenize("copy \"file.txt");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_pipe_operator() {
+        let tokens = tokenize("ls|grep \"a|b\" x\\|y").unwrap();
+        let values: Vec<_> = tokens.iter().map(|t| t.value.as_str()).collect();
+        assert_eq!(values, vec!["ls", "|", "grep", "a|b", "x|y"]);
+        assert!(tokens[1].is_operator("|"));
+        assert!(!tokens[3].is_operator("|"));
+        assert!(tokens[4].is_quoted);
+    }
+
+    #[test]
+    fn test_redirect_operators() {
+        let tokens = tokenize("cmd >out.txt >> log 2> err 2>&1 <in x2>y").unwrap();
+        let values: Vec<_> = tokens.iter().map(|t| t.value.as_str()).collect();
+        assert_eq!(
+            values,
+            vec![
+                "cmd", ">", "out.txt", ">>", "log", "2>", "err", "2>&1", "<", "in", "x2", ">", "y"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_list_operators() {
+        let tokens = tokenize("a;b && c||d e&f").unwrap();
+        let values: Vec<_> = tokens.iter().map(|t| t.value.as_str()).collect();
+        assert_eq!(values, vec!["a", ";", "b", "&&", "c", "||", "d", "e&f"]);
+
+        let tokens = tokenize("sleep 1& wait &").unwrap();
+        let values: Vec<_> = tokens.iter().map(|t| t.value.as_str()).collect();
+        assert_eq!(values, vec!["sleep", "1", "&", "wait", "&"]);
+    }
+
+    #[test]
+    fn test_comments() {
+        let tokens = tokenize("list backup/ # show backups").unwrap();
+        assert_eq!(tokens.len(), 2);
+
+        let tokens = tokenize("echo issue#42 \"#quoted\"").unwrap();
+        let values: Vec<_> = tokens.iter().map(|t| t.value.as_str()).collect();
+        assert_eq!(values, vec!["echo", "issue#42", "#quoted"]);
+
+        let tokens = tokenize("a # note\nb").unwrap();
+        let values: Vec<_> = tokens.iter().map(|t| t.value.as_str()).collect();
+        assert_eq!(values, vec!["a", "\n", "b"]);
+    }
+
+    #[test]
+    fn test_quoted_parts_join_into_one_word() {
+        let tokens = tokenize("name=\"my file\" '$HOME'/x \\$y \"\"").unwrap();
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[0].value, "name=my file");
+        assert_eq!(
+            tokens[0].parts,
+            vec![
+                ("name=".to_string(), Quote::Bare),
+                ("my file".to_string(), Quote::Double)
+            ]
+        );
+        assert_eq!(tokens[1].parts[0], ("$HOME".to_string(), Quote::Single));
+        assert_eq!(tokens[2].parts[0], ("$".to_string(), Quote::Single));
+        assert_eq!(tokens[3].value, "");
+        assert!(tokens[3].is_quoted);
+
+        let escaped = tokenize(r#""a\tb \"c\" \$d""#).unwrap();
+        assert_eq!(escaped[0].value, r#"a\tb "c" $d"#);
+    }
+
+    #[test]
+    fn test_command_substitution_is_one_word() {
+        let tokens = tokenize("echo $(list | grep \"a )\") x\"$(echo (b) 'c)')\"").unwrap();
+        let values: Vec<_> = tokens.iter().map(|t| t.value.as_str()).collect();
+        assert_eq!(
+            values,
+            vec!["echo", "$(list | grep \"a )\")", "x$(echo (b) 'c)')"]
+        );
+        assert_eq!(tokens[2].parts[1].1, Quote::Double);
+
+        assert!(matches!(
+            tokenize("echo $(list"),
+            Err(VshError::Incomplete(_))
+        ));
+
+        let literal = tokenize("echo '$(x)' 'cost $(5'").unwrap();
+        assert_eq!(literal[1].value, "$(x)");
+        assert_eq!(literal[1].parts, vec![("$(x)".to_string(), Quote::Single)]);
+        assert_eq!(literal[2].value, "cost $(5");
+    }
+
+    #[test]
+    fn test_heredocs() {
+        let input = "cat <<EOF | sort\n$name\n\\$x\nEOF\necho <<-'END'\n\t$y\n\tEND\n";
+        let tokens = tokenize(input).unwrap();
+        let values: Vec<_> = tokens.iter().map(|t| t.value.as_str()).collect();
+        assert_eq!(
+            values,
+            vec![
+                "cat",
+                "<<",
+                "$name\n$x\n",
+                "|",
+                "sort",
+                "\n",
+                "echo",
+                "<<-",
+                "$y\n",
+                "\n"
+            ]
+        );
+        assert_eq!(tokens[2].parts[0], ("$name\n".to_string(), Quote::Double));
+        assert_eq!(tokens[2].parts[1], ("$".to_string(), Quote::Single));
+        assert_eq!(tokens[8].parts, vec![("$y\n".to_string(), Quote::Single)]);
+
+        assert!(matches!(
+            tokenize("cat <<EOF\nno end\n"),
+            Err(VshError::Incomplete(_))
+        ));
+        assert!(matches!(
+            tokenize("cat <<"),
+            Err(VshError::InvalidSyntax(_))
+        ));
+    }
+
+    #[test]
+    fn test_text_blocks() {
+        let input = "sort with input \"\"\"\n    b $x\n    a\n    \"\"\" '''$y''' \"\"";
+        let tokens = tokenize(input).unwrap();
+        assert_eq!(tokens[3].value, "b $x\na\n");
+        assert_eq!(tokens[3].parts[0].1, Quote::Double);
+        assert_eq!(tokens[4].parts, vec![("$y".to_string(), Quote::Single)]);
+        assert_eq!(tokens[5].value, "");
+        assert_eq!(tokenize("x <<< y").unwrap()[1].value, "<<<");
+    }
 }
@@ -1,4 +1,4 @@
-use super::{CommandArgs, Intent, SyntaxStyle};
+use super::{literal, syntax, CommandArgs, Intent, SyntaxStyle};
 use crate::error::{Result, VshError};
 use crate::parser::tokenizer::Token;
 
@@ -8,31 +8,69 @@ pub fn extract_args(
     syntax: &SyntaxStyle,
     intent: &Intent,
 ) -> Result<CommandArgs> {
-    match syntax {
-        SyntaxStyle::Named => extract_named_args(tokens, intent),
-        SyntaxStyle::Verbose => extract_verbose_args(tokens, intent),
-        SyntaxStyle::Terse => extract_terse_args(tokens, intent),
+    if syntax::takes_plain_args(intent) {
+        return extract_terse_args(tokens, intent);
     }
+
+    // Option phrases like `sorted by size` work in any syntax
+    let mut flags = Vec::new();
+    let tokens = option_phrases(tokens, intent, &mut flags);
+
+    let mut args = match syntax {
+        SyntaxStyle::Named => extract_named_args(&tokens, intent),
+        SyntaxStyle::Verbose => extract_verbose_args(&tokens, intent),
+        SyntaxStyle::Terse => extract_terse_args(&tokens, intent),
+    }?;
+    args.flags.extend(flags);
+    Ok(args)
 }
 
 /// Extract arguments from named parameter syntax (key=value)
 fn extract_named_args(tokens: &[Token], intent: &Intent) -> Result<CommandArgs> {
     let mut args = CommandArgs::new();
+    let mut i = 1; // Skip command
 
-    for token in tokens.iter().skip(1) {
-        // Skip command itself
-        if let Some((key, value)) = token.value.split_once('=') {
-            match key {
-                "source" | "src" | "from" => args.source = Some(value.to_string()),
-                "destination" | "dest" | "to" | "target" => {
-                    args.destination = Some(value.to_string())
+    while i < tokens.len() {
+        let token = &tokens[i];
+        i += 1;
+
+        let Some((key, value)) = token.key_value() else {
+            // Not a key=value pair: a flag like `-f`, or else an extra arg
+            if is_flag(&token.value) {
+                args.flags.push(token.value.clone());
+            } else {
+                args.extra.push(token.value.clone());
+            }
+            continue;
+        };
+
+        match key {
+            "source" | "src" | "from" | "sources" | "file" | "files" => {
+                // A list may span words, as in `files=[a.txt, b.txt]`
+                let mut words = vec![value];
+                let items = loop {
+                    match literal::parse_list(&words) {
+                        Err(_) if i < tokens.len() => {
+                            words.push(tokens[i].clone());
+                            i += 1;
+                        }
+                        result => break result?,
+                    }
+                };
+                match items {
+                    Some(items) => args.sources.extend(items.into_iter().map(|t| t.value)),
+                    None => args.sources.push(words.remove(0).value),
                 }
-                "path" | "directory" | "dir" => args.path = Some(value.to_string()),
-                _ => args.extra.push(token.value.clone()),
             }
-        } else {
-            // Not a key=value pair, treat as extra arg
-            args.extra.push(token.value.clone());
+            "destination" | "dest" | "to" | "target" => args.destination = Some(value.value),
+            "path" | "directory" | "dir" => args.path = Some(value.value),
+            "pattern" | "text" => args.pattern = Some(value.value),
+            // Any other key is an option: `recursive=true` is `--recursive`
+            _ => match value.value.as_str() {
+                "true" | "yes" => args.flags.push(format!("--{}", key)),
+                "false" | "no" => {}
+                _ => args.flags.push(format!("--{}={}", key, value.value)),
+            },
         }
     }
 
@@ -63,7 +101,7 @@ fn extract_verbose_args(tokens: &[Token], intent: &Intent) -> Result<CommandArgs
             "from" => {
                 // Next token is source
                 if i + 1 < tokens.len() {
-                    args.source = Some(tokens[i + 1].value.clone());
+                    args.sources.push(tokens[i + 1].value.clone());
                     i += 2;
                 } else {
                     return Err(VshError::InvalidSyntax(
@@ -82,10 +120,21 @@ fn extract_verbose_args(tokens: &[Token], intent: &Intent) -> Result<CommandArgs
                     ));
                 }
             }
+            _ if is_flag(&token.value) => {
+                args.flags.push(token.value.clone());
+                i += 1;
+            }
+            // `copy a.txt b.txt and c.txt to backup/`
+            "and" if requires_source(intent) && !token.is_quoted => i += 1,
+            // `search "error" in log.txt`
+            _ if *intent == Intent::Search && args.pattern.is_none() => {
+                args.pattern = Some(token.value.clone());
+                i += 1;
+            }
             _ => {
-                // If we haven't found source yet, this is it
-                if args.source.is_none() && requires_source(intent) {
-                    args.source = Some(token.value.clone());
+                // Words before the destination are sources
+                if requires_source(intent) && args.destination.is_none() {
+                    args.sources.push(token.value.clone());
                 } else if args.path.is_none() && requires_path(intent) {
                     args.path = Some(token.value.clone());
                 } else {
@@ -106,58 +155,89 @@ fn extract_terse_args(tokens: &[Token], intent: &Intent) -> Result<CommandArgs>
 
     match intent {
         Intent::Copy | Intent::Move => {
-            // Format: cp source dest
-            if tokens.len() < 3 {
+            // Format: cp [flags] source... dest
+            let mut words = split_flags(&tokens[1..], &mut args.flags);
+            if words.len() < 2 {
                 return Err(VshError::InvalidSyntax(
                     "Expected: <command> <source> <destination>".to_string(),
                 ));
             }
-            args.source = Some(tokens[1].value.clone());
-            args.destination = Some(tokens[2].value.clone());
-
-            // Rest are flags or extra args
-            for token in tokens.iter().skip(3) {
-                if token.value.starts_with('-') {
-                    args.flags.push(token.value.clone());
-                } else {
-                    args.extra.push(token.value.clone());
-                }
+            args.destination = words.pop();
+            args.sources = words;
+        }
+        Intent::MakeDirectory
+        | Intent::Find
+        | Intent::Show
+        | Intent::Head
+        | Intent::Tail
+        | Intent::Touch => {
+            // Format: mkdir [-p] [-m mode] dir [dir2...] or
+            // find [path...] [-name pattern] [-type f] ...
+            // An option's value may be the next word
+            let mut words = Vec::new();
+            let mut rest = tokens[1..].iter();
+            while let Some(token) = rest.next() {
+                let Some((option, unit)) = value_option(intent, &token.value) else {
+                    words.push(token.clone());
+                    continue;
+                };
+                let value = rest.next().ok_or_else(|| {
+                    VshError::InvalidSyntax(format!("Expected a value after '{}'", token.value))
+                })?;
+                args.flags
+                    .push(format!("--{}={}{}", option, value.value, unit));
             }
+            args.sources = split_flags(&words, &mut args.flags);
+        }
+        Intent::Search => {
+            // Format: grep [flags] pattern [file...]
+            let mut words = split_flags(&tokens[1..], &mut args.flags).into_iter();
+            args.pattern = words.next();
+            args.sources = words.collect();
         }
         Intent::Remove => {
-            // Format: rm file [file2 file3...]
-            if tokens.len() < 2 {
+            // Format: rm [flags] file [file2 file3...]
+            args.sources = split_flags(&tokens[1..], &mut args.flags);
+            if args.sources.is_empty() {
                 return Err(VshError::InvalidSyntax(
                     "Expected: <command> <file>".to_string(),
                 ));
             }
-            args.source = Some(tokens[1].value.clone());
-
-            // Additional files or flags
-            for token in tokens.iter().skip(2) {
-                if token.value.starts_with('-') {
-                    args.flags.push(token.value.clone());
-                } else {
-                    args.extra.push(token.value.clone());
-                }
-            }
         }
-        Intent::List | Intent::ChangeDirectory => {
-            // Format: ls [path]
-            if tokens.len() > 1 {
-                args.path = Some(tokens[1].value.clone());
-            } else {
-                args.path = Some(".".to_string()); // Default to current dir
-            }
-
-            // Rest are flags
-            for token in tokens.iter().skip(2) {
-                if token.value.starts_with('-') {
-                    args.flags.push(token.value.clone());
-                } else {
-                    args.extra.push(token.value.clone());
-                }
+        Intent::List | Intent::ChangeDirectory | Intent::PushDirectory => {
+            // Format: ls [flags] [path]
+            let mut words = split_flags(&tokens[1..], &mut args.flags).into_iter();
+            args.path = words.next();
+            // `ls` lists the current dir; `cd` goes home
+            if *intent == Intent::List && args.path.is_none() {
+                args.path = Some(".".to_string());
             }
+            args.extra = words.collect();
+        }
+        Intent::Exit
+        | Intent::Export
+        | Intent::Unset
+        | Intent::Environment
+        | Intent::LastStatus
+        | Intent::FileExists
+        | Intent::DirectoryExists
+        | Intent::IsEmpty
+        | Intent::Compare
+        | Intent::Break
+        | Intent::Continue
+        | Intent::Return
+        | Intent::Function(_)
+        | Intent::Echo
+        | Intent::PopDirectory
+        | Intent::Directories
+        | Intent::Jobs
+        | Intent::Foreground
+        | Intent::Background
+        | Intent::Wait
+        | Intent::Kill
+        | Intent::External(_) => {
+            // Format: program [args...], passed through untouched as argv
+            args.extra = tokens.iter().skip(1).map(|t| t.value.clone()).collect();
         }
     }
 
@@ -165,21 +245,207 @@ fn extract_terse_args(tokens: &[Token], intent: &Intent) -> Result<CommandArgs>
     Ok(args)
 }
 
+/// Check if a word is a flag like `-r` or `--recursive` (a lone `-` is not)
+fn is_flag(word: &str) -> bool {
+    word.len() > 1 && word.starts_with('-')
+}
+
+/// Move the flags out of terse arguments, wherever they appear, and return
+/// the remaining words. Everything after `--` is a word.
+fn split_flags(tokens: &[Token], flags: &mut Vec<String>) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut tokens = tokens.iter();
+
+    while let Some(token) = tokens.next() {
+        if token.value == "--" {
+            words.extend(tokens.by_ref().map(|t| t.value.clone()));
+        } else if is_flag(&token.value) {
+            flags.push(token.value.clone());
+        } else {
+            words.push(token.value.clone());
+        }
+    }
+
+    words
+}
+
+/// Options whose value is the next word, as the long option they stand for
+/// and a unit to add to the value (`-mtime 2` is two days)
+fn value_option(intent: &Intent, word: &str) -> Option<(&'static str, &'static str)> {
+    match (intent, word) {
+        (Intent::MakeDirectory, "-m" | "--mode") => Some(("mode", "")),
+        (Intent::Head | Intent::Tail, "-n" | "--lines") => Some(("lines", "")),
+        (Intent::Find, "-name" | "--name") => Some(("name", "")),
+        (Intent::Find, "-iname" | "--iname") => Some(("iname", "")),
+        (Intent::Find, "-type" | "--type") => Some(("type", "")),
+        (Intent::Find, "-size" | "--size") => Some(("size", "")),
+        (Intent::Find, "--modified") => Some(("modified", "")),
+        (Intent::Find, "-mtime") => Some(("modified", "d")),
+        (Intent::Find, "-mmin") => Some(("modified", "m")),
+        (Intent::Find, "-maxdepth" | "--max-depth") => Some(("max-depth", "")),
+        (Intent::Find, "-mindepth" | "--min-depth") => Some(("min-depth", "")),
+        _ => None,
+    }
+}
+
+/// The text of an unquoted word, or "" for a quoted or missing one
+fn bare(tokens: &[Token], i: usize) -> &str {
+    tokens
+        .get(i)
+        .filter(|t| !t.is_quoted)
+        .map_or("", |t| t.value.as_str())
+}
+
+/// Take out the plain words that stand for a flag, as in `copy photos
+/// recursively to backup/` or `list sorted by size`, and return the rest
+fn option_phrases(tokens: &[Token], intent: &Intent, flags: &mut Vec<String>) -> Vec<Token> {
+    let bare = |i: usize| bare(tokens, i);
+    let listing = *intent == Intent::List;
+    let making = *intent == Intent::MakeDirectory;
+    let finding = *intent == Intent::Find;
+    let searching = *intent == Intent::Search;
+    let showing = *intent == Intent::Show;
+    let mut rest = vec![tokens[0].clone()];
+    let mut i = 1;
+
+    while i < tokens.len() {
+        if let Some((flag, words)) = finding.then(|| find_phrase(tokens, i)).flatten() {
+            flags.push(flag);
+            i += words;
+            continue;
+        }
+
+        let (flag, words) = match (bare(i), bare(i + 1)) {
+            ("recursively", _) => (Some("--recursive".to_string()), 1),
+            ("with", "parents") if making => (Some("--parents".to_string()), 2),
+            ("with", "mode") if making && !bare(i + 2).is_empty() => {
+                (Some(format!("--mode={}", bare(i + 2))), 3)
+            }
+            ("ignoring" | "ignore", "case") if searching => (Some("--ignore-case".to_string()), 2),
+            ("case-insensitive", _) if searching => (Some("--ignore-case".to_string()), 1),
+            ("as", "whole") if searching && matches!(bare(i + 2), "word" | "words") => {
+                (Some("--word".to_string()), 3)
+            }
+            ("whole-words" | "whole-word", _) if searching => (Some("--word".to_string()), 1),
+            ("with", "line") if searching && bare(i + 2) == "numbers" => {
+                (Some("--line-number".to_string()), 3)
+            }
+            ("literally", _) if searching => (Some("--literal".to_string()), 1),
+            ("count", "only") | ("only", "count") if searching => (Some("--count".to_string()), 2),
+            // show first 10 lines of log.txt
+            ("first" | "last", count)
+                if showing
+                    && count.parse::<usize>().is_ok()
+                    && matches!(bare(i + 2), "line" | "lines") =>
+            {
+                let of = usize::from(bare(i + 3) == "of");
+                (Some(format!("--{}={}", bare(i), count)), 3 + of)
+            }
+            ("all-details", _) if listing => (Some("--long".to_string()), 1),
+            ("including", "hidden") if listing || finding => (Some("--all".to_string()), 2),
+            ("sorted" | "sort", "by") if listing && !bare(i + 2).is_empty() => {
+                (Some(format!("--sort={}", bare(i + 2))), 3)
+            }
+            ("reversed", _) if listing => (Some("--reverse".to_string()), 1),
+            ("in", "reverse") if listing => (Some("--reverse".to_string()), 2),
+            ("without", "icons" | "emojis") if listing => (Some("--plain".to_string()), 2),
+            _ => (None, 1),
+        };
+
+        match flag {
+            Some(flag) => flags.push(flag),
+            None => rest.push(tokens[i].clone()),
+        }
+        i += words;
+    }
+
+    rest
+}
+
+/// A phrase of `find files named "*.txt" modified in last 2 days` at word
+/// `i`, as a flag and the number of words it takes up
+fn find_phrase(tokens: &[Token], i: usize) -> Option<(String, usize)> {
+    let bare = |i: usize| bare(tokens, i);
+    // The pattern after `named` may be quoted
+    let value = tokens.get(i + 1).map(|t| t.value.as_str());
+    let number = |i: usize| Some(bare(i)).filter(|w| w.parse::<u64>().is_ok());
+
+    let phrase = match (bare(i), bare(i + 1), bare(i + 2)) {
+        // Only straight after `find`, so `in files` is still a path
+        ("files" | "file", ..) if i == 1 => ("--type=file".to_string(), 1),
+        ("directories" | "directory" | "dirs" | "folders", ..) if i == 1 => {
+            ("--type=directory".to_string(), 1)
+        }
+        ("links" | "symlinks", ..) if i == 1 => ("--type=link".to_string(), 1),
+        ("named" | "with-name" | "called", ..) => (format!("--name={}", value?), 2),
+        ("with-extension", ..) => (format!("--name=*.{}", value?.trim_start_matches('.')), 2),
+        ("larger" | "bigger", "than", size) if !size.is_empty() => (format!("--size=+{}", size), 3),
+        ("smaller", "than", size) if !size.is_empty() => (format!("--size=-{}", size), 3),
+        ("between", low, "and") if !low.is_empty() && !bare(i + 3).is_empty() => {
+            (format!("--size={}..{}", low, bare(i + 3)), 4)
+        }
+        ("modified", "today", _) => ("--modified=-1d".to_string(), 2),
+        ("modified", "in", "last") => {
+            let age = number(i + 3)?;
+            (format!("--modified=-{}{}", age, time_unit(bare(i + 4))), 5)
+        }
+        ("modified", "within", _) => {
+            let age = number(i + 2)?;
+            (format!("--modified=-{}{}", age, time_unit(bare(i + 3))), 4)
+        }
+        // modified more than 2 days ago
+        ("modified", "more", "than") if bare(i + 5) == "ago" => {
+            let age = number(i + 3)?;
+            (format!("--modified=+{}{}", age, time_unit(bare(i + 4))), 6)
+        }
+        ("within", _, "levels" | "level") => (format!("--max-depth={}", number(i + 1)?), 3),
+        ("including", "ignored", _) => ("--no-ignore".to_string(), 2),
+        _ => return None,
+    };
+    Some(phrase)
+}
+
+/// `days` → `d`, `hours` → `h`; anything else is left for `find` to reject
+fn time_unit(word: &str) -> &str {
+    match word {
+        "second" | "seconds" => "s",
+        "minute" | "minutes" => "m",
+        "hour" | "hours" => "h",
+        "day" | "days" => "d",
+        "week" | "weeks" => "w",
+        _ => word,
+    }
+}
+
 /// Check if intent requires a source argument
 fn requires_source(intent: &Intent) -> bool {
-    matches!(intent, Intent::Copy | Intent::Move | Intent::Remove)
+    matches!(
+        intent,
+        Intent::Copy
+            | Intent::Move
+            | Intent::Remove
+            | Intent::MakeDirectory
+            | Intent::Search
+            | Intent::Show
+            | Intent::Head
+            | Intent::Tail
+            | Intent::Touch
+    )
 }
 
 /// Check if intent requires a path argument
 fn requires_path(intent: &Intent) -> bool {
-    matches!(intent, Intent::List | Intent::ChangeDirectory)
+    matches!(
+        intent,
+        Intent::List | Intent::ChangeDirectory | Intent::PushDirectory | Intent::Find
+    )
 }
 
 /// Validate that required arguments are present
 fn validate_args(args: &CommandArgs, intent: &Intent) -> Result<()> {
     match intent {
         Intent::Copy | Intent::Move => {
-            if args.source.is_none() {
+            if args.sources.is_empty() {
                 return Err(VshError::InvalidSyntax("Missing source".to_string()));
             }
             if args.destination.is_none() {
@@ -187,15 +453,63 @@ fn validate_args(args: &CommandArgs, intent: &Intent) -> Result<()> {
             }
         }
         Intent::Remove => {
-            if args.source.is_none() {
+            if args.sources.is_empty() {
                 return Err(VshError::InvalidSyntax(
                     "Missing file to remove".to_string(),
                 ));
             }
         }
-        Intent::List | Intent::ChangeDirectory => {
+        Intent::Search => {
+            if args.pattern.is_none() {
+                return Err(VshError::InvalidSyntax(
+                    "Missing pattern to search for".to_string(),
+                ));
+            }
+        }
+        Intent::Touch => {
+            if args.sources.is_empty() {
+                return Err(VshError::InvalidSyntax(
+                    "Missing file to create".to_string(),
+                ));
+            }
+        }
+        Intent::Show | Intent::Head | Intent::Tail => {
+            // No files means stdin
+        }
+        Intent::MakeDirectory => {
+            if args.sources.is_empty() && args.path.is_none() {
+                return Err(VshError::InvalidSyntax(
+                    "Missing directory to create".to_string(),
+                ));
+            }
+        }
+        Intent::List | Intent::ChangeDirectory | Intent::PushDirectory | Intent::Find => {
             // Path is optional, defaults handled elsewhere
         }
+        Intent::Exit
+        | Intent::Export
+        | Intent::Unset
+        | Intent::Environment
+        | Intent::LastStatus
+        | Intent::FileExists
+        | Intent::DirectoryExists
+        | Intent::IsEmpty
+        | Intent::Compare
+        | Intent::Break
+        | Intent::Continue
+        | Intent::Return
+        | Intent::Function(_)
+        | Intent::Echo
+        | Intent::PopDirectory
+        | Intent::Directories
+        | Intent::Jobs
+        | Intent::Foreground
+        | Intent::Background
+        | Intent::Wait
+        | Intent::Kill
+        | Intent::External(_) => {
+            // Arguments are the program's business
+        }
     }
     Ok(())
 }
@@ -205,10 +519,7 @@ mod tests {
     use super::*;
 
     fn make_token(s: &str) -> Token {
-        Token {
-            value: s.to_string(),
-            is_quoted: false,
-        }
+        Token::word(s)
     }
 
     #[test]
@@ -219,7 +530,7 @@ mod tests {
             make_token("backup/"),
         ];
         let args = extract_terse_args(&tokens, &Intent::Copy).unwrap();
-        assert_eq!(args.source, Some("file.txt".to_string()));
+        assert_eq!(args.sources, vec!["file.txt"]);
         assert_eq!(args.destination, Some("backup/".to_string()));
     }
 
@@ -232,7 +543,7 @@ mod tests {
             make_token("backup/"),
         ];
         let args = extract_verbose_args(&tokens, &Intent::Copy).unwrap();
-        assert_eq!(args.source, Some("file.txt".to_string()));
+        assert_eq!(args.sources, vec!["file.txt"]);
         assert_eq!(args.destination, Some("backup/".to_string()));
     }
 
@@ -244,7 +555,175 @@ mod tests {
             make_token("destination=backup/"),
         ];
         let args = extract_named_args(&tokens, &Intent::Copy).unwrap();
-        assert_eq!(args.source, Some("file.txt".to_string()));
+        assert_eq!(args.sources, vec!["file.txt"]);
         assert_eq!(args.destination, Some("backup/".to_string()));
     }
+
+    #[test]
+    fn test_flags_in_every_syntax() {
+        let tokens: Vec<Token> = ["cp", "photos", "-r", "--", "-odd"]
+            .iter()
+            .map(|w| make_token(w))
+            .collect();
+        let args = extract_terse_args(&tokens, &Intent::Copy).unwrap();
+        assert_eq!(args.flags, vec!["-r"]);
+        assert_eq!(args.sources, vec!["photos"]);
+        assert_eq!(args.destination, Some("-odd".to_string()));
+
+        let tokens: Vec<Token> = ["copy", "photos", "recursively", "to", "backup/"]
+            .iter()
+            .map(|w| make_token(w))
+            .collect();
+        let args = extract_args(&tokens, &SyntaxStyle::Verbose, &Intent::Copy).unwrap();
+        assert_eq!(args.flags, vec!["--recursive"]);
+        assert_eq!(args.sources, vec!["photos"]);
+
+        let tokens: Vec<Token> = [
+            "copy",
+            "src=a",
+            "dest=b",
+            "recursive=true",
+            "overwrite=skip",
+        ]
+        .iter()
+        .map(|w| make_token(w))
+        .collect();
+        let args = extract_named_args(&tokens, &Intent::Copy).unwrap();
+        assert_eq!(args.flags, vec!["--recursive", "--overwrite=skip"]);
+
+        let tokens: Vec<Token> = ["remove", "files=[a,b]", "-f"]
+            .iter()
+            .map(|w| make_token(w))
+            .collect();
+        let args = extract_named_args(&tokens, &Intent::Remove).unwrap();
+        assert_eq!(args.flags, vec!["-f"]);
+        assert_eq!(args.sources, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_list_options() {
+        let words =
+            |input: &[&str]| -> Vec<Token> { input.iter().map(|w| make_token(w)).collect() };
+
+        let args = extract_args(
+            &words(&["ls", "-la", "docs"]),
+            &SyntaxStyle::Terse,
+            &Intent::List,
+        )
+        .unwrap();
+        assert_eq!(args.flags, vec!["-la"]);
+        assert_eq!(args.path, Some("docs".to_string()));
+
+        let tokens = words(&[
+            "list",
+            "all-details",
+            "sorted",
+            "by",
+            "size",
+            "in",
+            "reverse",
+        ]);
+        let args = extract_args(&tokens, &SyntaxStyle::Verbose, &Intent::List).unwrap();
+        assert_eq!(args.flags, vec!["--long", "--sort=size", "--reverse"]);
+        assert_eq!(args.path, None);
+
+        let tokens = words(&["list", "path=docs", "hidden=true", "sort=time"]);
+        let args = extract_args(&tokens, &SyntaxStyle::Named, &Intent::List).unwrap();
+        assert_eq!(args.flags, vec!["--hidden", "--sort=time"]);
+        assert_eq!(args.path, Some("docs".to_string()));
+    }
+
+    #[test]
+    fn test_find_filters() {
+        let words =
+            |input: &[&str]| -> Vec<Token> { input.iter().map(|w| make_token(w)).collect() };
+
+        let tokens = words(&[
+            "find",
+            "src",
+            "-name",
+            "*.rs",
+            "-mtime",
+            "-2",
+            "-maxdepth",
+            "3",
+        ]);
+        let args = extract_args(&tokens, &SyntaxStyle::Terse, &Intent::Find).unwrap();
+        assert_eq!(args.sources, vec!["src"]);
+        assert_eq!(
+            args.flags,
+            vec!["--name=*.rs", "--modified=-2d", "--max-depth=3"]
+        );
+
+        let tokens = words(&[
+            "find", "files", "named", "*.txt", "larger", "than", "1M", "modified", "in", "last",
+            "2", "days", "in", "docs",
+        ]);
+        let args = extract_args(&tokens, &SyntaxStyle::Verbose, &Intent::Find).unwrap();
+        assert_eq!(args.path, Some("docs".to_string()));
+        assert_eq!(
+            args.flags,
+            vec![
+                "--type=file",
+                "--name=*.txt",
+                "--size=+1M",
+                "--modified=-2d"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search_in_every_syntax() {
+        let words =
+            |input: &[&str]| -> Vec<Token> { input.iter().map(|w| make_token(w)).collect() };
+
+        let args = extract_args(
+            &words(&["grep", "-in", "error", "a.log", "b.log"]),
+            &SyntaxStyle::Terse,
+            &Intent::Search,
+        )
+        .unwrap();
+        assert_eq!(args.pattern, Some("error".to_string()));
+        assert_eq!(args.sources, vec!["a.log", "b.log"]);
+
+        let tokens = words(&[
+            "search",
+            "todo",
+            "in",
+            "src",
+            "recursively",
+            "ignoring",
+            "case",
+        ]);
+        let args = extract_args(&tokens, &SyntaxStyle::Verbose, &Intent::Search).unwrap();
+        assert_eq!(args.pattern, Some("todo".to_string()));
+        assert_eq!(args.path, Some("src".to_string()));
+        assert_eq!(args.flags, vec!["--recursive", "--ignore-case"]);
+
+        let tokens = words(&["grep", "pattern=todo", "file=a.rs", "whole-word=true"]);
+        let args = extract_args(&tokens, &SyntaxStyle::Named, &Intent::Search).unwrap();
+        assert_eq!(args.pattern, Some("todo".to_string()));
+        assert_eq!(args.sources, vec!["a.rs"]);
+        assert_eq!(args.flags, vec!["--whole-word"]);
+    }
+
+    #[test]
+    fn test_several_sources() {
+        let words =
+            |input: &[&str]| -> Vec<Token> { input.iter().map(|w| make_token(w)).collect() };
+
+        let args =
+            extract_terse_args(&words(&["cp", "a", "b", "c", "dir/"]), &Intent::Copy).unwrap();
+        assert_eq!(args.sources, vec!["a", "b", "c"]);
+        assert_eq!(args.destination, Some("dir/".to_string()));
+
+        let tokens = words(&["copy", "a.txt", "b.txt", "and", "c.txt", "to", "backup/"]);
+        let args = extract_verbose_args(&tokens, &Intent::Copy).unwrap();
+        assert_eq!(args.sources, vec!["a.txt", "b.txt", "c.txt"]);
+
+        let tokens = crate::parser::tokenize("remove files=[a, b,c] force=true").unwrap();
+        let args = extract_named_args(&tokens, &Intent::Remove).unwrap();
+        assert_eq!(args.sources, vec!["a", "b", "c"]);
+        assert_eq!(args.flags, vec!["--force"]);
+    }
 }
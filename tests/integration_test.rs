@@ -81,3 +81,1062 @@ fn test_list_command() {
 
     cmd.assert().success();
 }
+
+#[test]
+fn test_list_details_hidden_and_sorting() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("small.txt"), "a").unwrap();
+    fs::write(dir.path().join("large.txt"), "a".repeat(3000)).unwrap();
+    fs::write(dir.path().join(".secret"), "").unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path())
+        .arg("-c")
+        .arg("ls -l --plain; list including hidden sorted by size without icons");
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output).unwrap();
+    let (long, sorted) = output.split_once("Contents of .:\n\n  large").unwrap();
+
+    assert!(long.contains("-rw"));
+    assert!(long.contains("2.9K  "));
+    assert!(!long.contains(".secret"));
+    assert!(sorted.find(".txt").unwrap() < sorted.find(".secret").unwrap());
+    assert!(sorted.contains("small.txt"));
+}
+
+#[test]
+fn test_make_directory() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("file.txt"), "data").unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path()).arg("-c").arg(
+        "mkdir nonexistent && copy file.txt to nonexistent/; \
+         create directory a/b/c with parents; make-directory path=private mode=700",
+    );
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("✓ Created directory a/b/c"));
+    assert!(dir.path().join("nonexistent/file.txt").exists());
+    assert!(dir.path().join("a/b/c").is_dir());
+    let mode = fs::metadata(dir.path().join("private"))
+        .unwrap()
+        .permissions();
+    assert_eq!(
+        std::os::unix::fs::PermissionsExt::mode(&mode) & 0o777,
+        0o700
+    );
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path()).arg("-c").arg("mkdir x/y");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("mkdir -p x/y"));
+}
+
+#[test]
+fn test_find_in_every_syntax() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("src/nested")).unwrap();
+    fs::create_dir_all(dir.path().join("build")).unwrap();
+    fs::write(dir.path().join(".gitignore"), "build/\n").unwrap();
+    fs::write(dir.path().join("src/a.txt"), "").unwrap();
+    fs::write(dir.path().join("src/nested/b.txt"), "").unwrap();
+    fs::write(dir.path().join("build/c.txt"), "").unwrap();
+    fs::write(dir.path().join("big.bin"), vec![0; 4096]).unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path()).arg("-c").arg(
+        "find . -name \"*.txt\"; find type=file name=\"*.txt\" path=. max-depth=2; \
+         find files larger than 1k; find files named \"*.txt\" in . | grep nested",
+    );
+
+    cmd.assert()
+        .success()
+        .stdout("src/a.txt\nsrc/nested/b.txt\nsrc/a.txt\nbig.bin\nsrc/nested/b.txt\n");
+}
+
+#[test]
+fn test_search_files_and_pipes() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("log.txt"), "Error one\nfine\nerrors two\n").unwrap();
+    fs::write(
+        dir.path().join("src/main.rs"),
+        "// TODO: tidy\nfn main() {}\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path()).arg("-c").arg(
+        "grep -n error log.txt; search \"error\" in log.txt ignoring case as whole words; \
+         grep pattern=todo path=. recursive=true ignore-case=true; \
+         grep -c e < log.txt; list | grep -F log.t",
+    );
+
+    cmd.assert()
+        .success()
+        .stdout("3:errors two\nError one\nsrc/main.rs:// TODO: tidy\n3\n  📄 log.txt\n");
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path())
+        .arg("-c")
+        .arg("grep missing log.txt || echo none");
+    cmd.assert().success().stdout("none\n");
+}
+
+#[test]
+fn test_file_content_builtins() {
+    let dir = tempdir().unwrap();
+    let numbers: String = (1..=20).map(|n| format!("{}\n", n)).collect();
+    fs::write(dir.path().join("log.txt"), numbers).unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path())
+        // Only the built-ins can be found
+        .env("PATH", "/nonexistent")
+        .arg("-c")
+        .arg(
+            "show first 2 lines of log.txt; tail -n 1 log.txt; \
+             create file notes.md > created.txt; echo -n \"hello \" >> notes.md; \
+             echo world >> notes.md; cat notes.md; cat log.txt | head -3 | tail -1; \
+             head -n 1 < notes.md",
+        );
+
+    cmd.assert()
+        .success()
+        .stdout("1\n2\n20\nhello world\n3\nhello world\n");
+    let created = fs::read_to_string(dir.path().join("created.txt")).unwrap();
+    assert!(created.contains("Created file notes.md"));
+}
+
+#[test]
+fn test_directory_history_and_stack() {
+    let dir = tempdir().unwrap();
+    let home = dir.path().canonicalize().unwrap();
+    fs::create_dir_all(home.join("a/b")).unwrap();
+    fs::create_dir_all(home.join("projects/vsh")).unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(&home)
+        .env("HOME", &home)
+        .env("CDPATH", home.join("projects"))
+        .arg("-c")
+        .arg(
+            "cd a/b > /dev/null; go back > /dev/null; pwd; cd vsh > /dev/null; pwd; \
+             cd > /dev/null; pushd a > /dev/null; pushd b > /dev/null; dirs -v; \
+             popd > /dev/null; popd > /dev/null; pwd; popd",
+        );
+
+    cmd.assert()
+        .failure()
+        .stdout(format!(
+            "{0}\n{0}/projects/vsh\n 0  ~/a/b\n 1  ~/a\n 2  ~\n{0}\n",
+            home.display()
+        ))
+        .stderr(predicate::str::contains("directory stack is empty"));
+}
+
+#[test]
+fn test_background_jobs() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c").arg(
+        "sleep 0.3 & sleep 0.1 in background; jobs; wait %2 && echo second done; \
+         sh -c 'exit 3' & wait %sh || echo third failed; wait; jobs; echo all done",
+    );
+
+    cmd.assert().success().stdout(
+        "[1]- Running      sleep 0.3 &\n[2]+ Running      sleep 0.1 &\n\
+         second done\nthird failed\nall done\n",
+    );
+}
+
+#[test]
+fn test_kill_and_resume_jobs() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c").arg(
+        "sleep 10 & kill -STOP %1; sleep 0.1; jobs; bg %1; kill %1; wait %1; \
+         sleep 0.1 & fg; kill %9",
+    );
+
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "[1]+ Stopped      sleep 10\n[1] sleep 10 &\nsleep 0.1\n",
+        ))
+        .stderr(predicate::str::contains("There are no jobs"));
+}
+
+#[test]
+fn test_external_command() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c").arg("echo hello from outside");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("hello from outside"));
+}
+
+#[test]
+fn test_external_command_failure_status() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c").arg("false");
+
+    cmd.assert().code(1);
+}
+
+#[test]
+fn test_exit_status_tracking() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c").arg(
+        "false; echo $?\n\
+         list /no/such/dir-vsh 2> /dev/null; last-status\n\
+         no-such-program-vsh 2> /dev/null; echo \"status $?\"\n\
+         echo $((1 + \"a\")) 2> /dev/null; echo $(( $? * 2 ))",
+    );
+    cmd.assert().success().stdout("1\n4\nstatus 127\n14\n");
+
+    for (command, code) in [
+        ("list /no/such/dir-vsh", 4),
+        ("definitely-not-a-program-vsh", 127),
+        ("echo \"unclosed", 2),
+        ("exit with 9", 9),
+    ] {
+        let mut cmd = Command::cargo_bin("vsh").unwrap();
+        cmd.arg("-c").arg(command);
+        cmd.assert().code(code);
+    }
+}
+
+#[test]
+fn test_cancelled_remove_fails() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("keep.txt"), "").unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path())
+        .arg("-c")
+        .arg("remove keep.txt && echo removed")
+        .write_stdin("n\n");
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("Cancelled"))
+        .stdout(predicate::str::contains("removed").not());
+    assert!(dir.path().join("keep.txt").exists());
+}
+
+#[test]
+fn test_unknown_program() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c").arg("definitely-not-a-real-program-vsh");
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown command"));
+}
+
+#[test]
+fn test_pipeline_builtin_into_external() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("notes.txt"), "").unwrap();
+    fs::write(dir.path().join("image.png"), "").unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c")
+        .arg(format!("list {} | grep \".txt\"", dir.path().display()));
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("notes.txt"))
+        .stdout(predicate::str::contains("image.png").not());
+}
+
+#[test]
+fn test_pipeline_operator_styles() {
+    for op in ["|", "->", "then"] {
+        let mut cmd = Command::cargo_bin("vsh").unwrap();
+        cmd.arg("-c")
+            .arg(format!("echo hello {} tr a-z A-Z {} cat", op, op));
+
+        cmd.assert().success().stdout("HELLO\n");
+    }
+}
+
+#[test]
+fn test_redirect_builtin_output() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("notes.txt"), "").unwrap();
+    let listing = dir.path().join("listing.txt");
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c").arg(format!(
+        "list {} output-to {}",
+        dir.path().display(),
+        listing.display()
+    ));
+    cmd.assert().success().stdout("");
+
+    let contents = fs::read_to_string(&listing).unwrap();
+    assert!(contents.contains("notes.txt"));
+    assert!(!contents.contains('\u{1b}'));
+}
+
+#[test]
+fn test_redirect_external_append_and_input() {
+    let dir = tempdir().unwrap();
+    let out = dir.path().join("out.txt");
+
+    for word in ["first", "second"] {
+        let mut cmd = Command::cargo_bin("vsh").unwrap();
+        cmd.arg("-c")
+            .arg(format!("echo {} >> {}", word, out.display()));
+        cmd.assert().success();
+    }
+    assert_eq!(fs::read_to_string(&out).unwrap(), "first\nsecond\n");
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c")
+        .arg(format!("tr a-z A-Z input-from {}", out.display()));
+    cmd.assert().success().stdout("FIRST\nSECOND\n");
+}
+
+#[test]
+fn test_heredocs_and_here_strings() {
+    let dir = tempdir().unwrap();
+    fs::write(
+        dir.path().join("deploy.vsh"),
+        "env = prod\n\
+         cat <<EOF > app.conf\n\
+         [server]\n\
+         mode = $env\n\
+         \\$HOME stays\n\
+         EOF\n\
+         if file-exists app.conf then\n\
+         \ttr a-z A-Z <<-'END'\n\
+         \t\tno $env here\n\
+         \n\
+         \tEND\n\
+         end\n\
+         grep -c o <<< \"one two\"\n\
+         sort with input \"\"\"\n\
+         \x20   pear\n\
+         \x20   $env\n\
+         \x20   \"\"\"\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path()).arg("deploy.vsh");
+    cmd.assert()
+        .success()
+        .stdout("NO $ENV HERE\n\n1\npear\nprod\n");
+    assert_eq!(
+        fs::read_to_string(dir.path().join("app.conf")).unwrap(),
+        "[server]\nmode = prod\n$HOME stays\n"
+    );
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c").arg("cat <<EOF\nnever closed");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Missing 'EOF'"));
+}
+
+#[test]
+fn test_redirect_errors() {
+    let dir = tempdir().unwrap();
+    let err = dir.path().join("err.txt");
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c")
+        .arg(format!("list /no/such/dir 2> {}", err.display()));
+    cmd.assert().stderr("");
+
+    let contents = fs::read_to_string(&err).unwrap();
+    assert!(contents.contains("File not found"));
+}
+
+#[test]
+fn test_redirect_errors_of_each_source() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("a.txt"), "a").unwrap();
+    fs::create_dir(dir.path().join("backup")).unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path()).arg("-c").arg(
+        "cp -f a.txt missing.txt backup/ 2> err.txt\n\
+         echo \"status $?\"",
+    );
+    cmd.assert()
+        .stdout(predicate::str::contains("status 1"))
+        .stderr("");
+
+    let contents = fs::read_to_string(dir.path().join("err.txt")).unwrap();
+    assert!(contents.contains("File not found: missing.txt"));
+    assert!(contents.contains("1 of 2 items failed"));
+    assert!(dir.path().join("backup/a.txt").exists());
+}
+
+#[test]
+fn test_command_sequencing() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c")
+        .arg("echo one; false && echo skipped || echo recovered and then echo two");
+
+    cmd.assert().success().stdout("one\nrecovered\ntwo\n");
+}
+
+#[test]
+fn test_sequencing_stops_after_builtin_failure() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c")
+        .arg("list /no/such/dir and then echo unreachable");
+
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("unreachable").not())
+        .stderr(predicate::str::contains("File not found"));
+}
+
+#[test]
+fn test_exit_status_of_last_command() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c").arg("true; sh -c \"exit 3\"");
+
+    cmd.assert().code(3);
+}
+
+#[test]
+fn test_script_with_positional_arguments() {
+    let dir = tempdir().unwrap();
+    let script = dir.path().join("greet.vsh");
+    fs::write(
+        &script,
+        "#!/usr/bin/env vsh\n\n# Say hello\necho \"hello $1\" # inline comment\necho $# args: $@\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg(&script).arg("world").arg("--loud");
+
+    cmd.assert()
+        .success()
+        .stdout("hello world\n2 args: world --loud\n");
+}
+
+#[test]
+fn test_script_flag_and_exit_status() {
+    let dir = tempdir().unwrap();
+    let script = dir.path().join("fail.vsh");
+    fs::write(&script, "echo before\nexit 7\necho after\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("--script").arg(&script);
+
+    cmd.assert().code(7).stdout("before\n");
+}
+
+#[test]
+fn test_script_status_is_last_command() {
+    let dir = tempdir().unwrap();
+    let script = dir.path().join("last.vsh");
+    fs::write(&script, "list /no/such/dir\nfalse\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg(&script);
+
+    cmd.assert()
+        .code(1)
+        .stderr(predicate::str::contains("File not found"));
+}
+
+#[test]
+fn test_shebang_script() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempdir().unwrap();
+    let script = dir.path().join("hello.vsh");
+    let vsh = assert_cmd::cargo::cargo_bin("vsh");
+    fs::write(
+        &script,
+        format!("#!{}\necho shebang works with $1\n", vsh.display()),
+    )
+    .unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = std::process::Command::new(&script)
+        .arg("args")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "shebang works with args\n"
+    );
+}
+
+#[test]
+fn test_missing_script() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("no-such-script.vsh");
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("File not found"));
+}
+
+#[test]
+fn test_variables() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c").arg(
+        "name = \"World\"; set count = 3; let file=\"my notes\"; \
+         echo \"Hello $name\" ${count} {name}; echo '$name' \"$file.txt\"",
+    );
+
+    cmd.assert()
+        .success()
+        .stdout("Hello World 3 World\n$name my notes.txt\n");
+}
+
+#[test]
+fn test_variables_in_builtin_arguments() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("a.txt"), "a").unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path())
+        .arg("-c")
+        .arg("var target = \"b.txt\"; copy source=a.txt destination=$target");
+
+    cmd.assert().success();
+    assert!(dir.path().join("b.txt").exists());
+}
+
+#[test]
+fn test_export_and_unset() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.env_remove("VSH_GREETING").arg("-c").arg(
+        "export VSH_GREETING as hello; sh -c 'echo $VSH_GREETING'; \
+         VSH_GREETING = bye; printenv VSH_GREETING; \
+         unset VSH_GREETING; sh -c 'echo \"[$VSH_GREETING]\"'",
+    );
+
+    cmd.assert().success().stdout("hello\nbye\n[]\n");
+}
+
+#[test]
+fn test_export_shell_variable() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c")
+        .arg("level = debug; export level; env | grep ^level=");
+
+    cmd.assert().success().stdout("level=debug\n");
+}
+
+#[test]
+fn test_env_prefix_is_scoped_to_one_command() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.env_remove("VSH_LOG")
+        .arg("-c")
+        .arg("VSH_LOG=debug sh -c 'echo $VSH_LOG'; sh -c 'echo \"[$VSH_LOG]\"'");
+
+    cmd.assert().success().stdout("debug\n[]\n");
+}
+
+#[test]
+fn test_env_prefix_on_builtins_and_functions() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.env_remove("VSH_LOG").arg("-c").arg(
+        "VSH_LOG=1 env | grep VSH_LOG\n\
+         VSH_LOG=2 env\n\
+         function show\n\
+         \x20   sh -c 'echo \"[$VSH_LOG]\"'\n\
+         end\n\
+         VSH_LOG=3 show\n\
+         show",
+    );
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("VSH_LOG=1\n"))
+        .stdout(predicate::str::contains("VSH_LOG=2\n"))
+        .stdout(predicate::str::ends_with("[3]\n[]\n"));
+}
+
+#[test]
+fn test_one_line_if() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("test.txt"), "x").unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path()).arg("-c").arg(
+        "if file-exists \"test.txt\" then echo \"Found!\"; \
+         if directory-exists test.txt then echo dir else echo \"not a dir\"",
+    );
+
+    cmd.assert().success().stdout("Found!\nnot a dir\n");
+}
+
+#[test]
+fn test_if_block_in_script() {
+    let dir = tempdir().unwrap();
+    let script = dir.path().join("check.vsh");
+    fs::write(
+        &script,
+        "count = $1\n\
+         if $count > 10 then\n\
+         \x20   echo large\n\
+         else if $count >= 5 then\n\
+         \x20   echo medium\n\
+         else\n\
+         \x20   # nothing much\n\
+         \x20   echo small\n\
+         end\n\
+         if not is-empty \"$2\" then echo \"name is $2\"\n",
+    )
+    .unwrap();
+
+    for (args, expected) in [
+        (vec!["42"], "large\n"),
+        (vec!["5", "vic"], "medium\nname is vic\n"),
+        (vec!["1"], "small\n"),
+    ] {
+        let mut cmd = Command::cargo_bin("vsh").unwrap();
+        cmd.arg(&script).args(args);
+        cmd.assert().success().stdout(expected);
+    }
+}
+
+#[test]
+fn test_if_condition_uses_exit_status() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c")
+        .arg("if sh -c 'exit 1' then echo yes else echo no end; if true && true then echo both");
+
+    cmd.assert().success().stdout("no\nboth\n");
+}
+
+#[test]
+fn test_unclosed_if_block() {
+    let dir = tempdir().unwrap();
+    let script = dir.path().join("broken.vsh");
+    fs::write(&script, "echo start\nif true then\n    echo never\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg(&script);
+
+    cmd.assert()
+        .failure()
+        .stdout("start\n")
+        .stderr(predicate::str::contains("broken.vsh:2"))
+        .stderr(predicate::str::contains("Missing 'end'"));
+}
+
+#[test]
+fn test_for_loop_over_glob() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("a.txt"), "a").unwrap();
+    fs::write(dir.path().join("b.txt"), "b").unwrap();
+    fs::write(dir.path().join("c.log"), "c").unwrap();
+    fs::create_dir(dir.path().join("backup")).unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path()).arg("-c").arg(
+        "backup_dir = backup\n\
+         for file in *.txt do\n\
+         \x20   copy $file to $backup_dir\n\
+         \x20   echo \"Backed up: $file\" >> log.txt\n\
+         end\n\
+         for file in *.none do echo never",
+    );
+
+    cmd.assert().success();
+    assert!(dir.path().join("backup/a.txt").exists());
+    assert!(dir.path().join("backup/b.txt").exists());
+    assert!(!dir.path().join("backup/c.log").exists());
+    assert_eq!(
+        fs::read_to_string(dir.path().join("log.txt")).unwrap(),
+        "Backed up: a.txt\nBacked up: b.txt\n"
+    );
+}
+
+#[test]
+fn test_for_loop_over_range_and_list() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c").arg(
+        "for i in 1..3 do echo $i\n\
+         for name in [\"a.txt\", \"my notes.txt\"] do echo \"<$name>\"\n\
+         for i in 5..1 do if $i == 4 then continue else if $i < 3 then break else echo $i",
+    );
+
+    cmd.assert()
+        .success()
+        .stdout("1\n2\n3\n<a.txt>\n<my notes.txt>\n5\n3\n");
+}
+
+#[test]
+fn test_while_loop() {
+    let dir = tempdir().unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path()).arg("-c").arg(
+        "while not file-exists done.txt do\n\
+         \x20   echo working\n\
+         \x20   echo finished > done.txt\n\
+         end\n\
+         while true do break",
+    );
+
+    cmd.assert().success().stdout("working\n");
+}
+
+#[test]
+fn test_functions_with_parameters() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("notes.txt"), "notes").unwrap();
+    fs::create_dir(dir.path().join("backups")).unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path()).arg("-c").arg(
+        "function greet name\n\
+         \x20   echo \"Hello, $name!\"\n\
+         end\n\
+         function quick-backup\n\
+         \x20   copy $1 to backups/\n\
+         end\n\
+         function pick x y\n\
+         \x20   if $x > $y then return $x\n\
+         \x20   return $y\n\
+         end\n\
+         greet World\n\
+         quick-backup notes.txt\n\
+         result = pick 3 8\n\
+         echo \"max: $result, name: {name}\"\n\
+         greet Files > greeting.txt",
+    );
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Hello, World!\n"))
+        .stdout(predicate::str::contains("max: 8, name: {name}\n"));
+    assert!(dir.path().join("backups/notes.txt").exists());
+    assert_eq!(
+        fs::read_to_string(dir.path().join("greeting.txt")).unwrap(),
+        "Hello, Files!\n"
+    );
+}
+
+#[test]
+fn test_command_substitution() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("a.txt"), "").unwrap();
+    fs::write(dir.path().join("b.txt"), "").unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path()).arg("-c").arg(
+        "function greet name\n\
+         \x20   echo \"Hello, $name!\"\n\
+         end\n\
+         message = \"$(greet World) $(echo \"(nested $(echo deep))\")\"\n\
+         echo $message\n\
+         for file in $(find . -name '*.txt' | sort) do echo \"found $file\"; end\n\
+         spaced = \"$(printf 'a  b\\n\\n\\n')\"\n\
+         echo \"[$spaced]\" [$(echo one   two)]\n\
+         echo \"$(cd / > /dev/null; pwd)\"; pwd",
+    );
+
+    cmd.assert().success().stdout(format!(
+        "Hello, World! (nested deep)\n\
+         found a.txt\nfound b.txt\n\
+         [a  b] [one two]\n\
+         /\n{}\n",
+        dir.path().canonicalize().unwrap().display()
+    ));
+}
+
+#[test]
+fn test_arithmetic_and_typed_values() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c").arg(
+        "function add a b\n\
+         \x20   return $(($a + $b))\n\
+         end\n\
+         x = 7\n\
+         total = add $x 3\n\
+         echo $total $((x / 2)) $((2 * (x - 1) % 5)) \"$(( x > 5 and not false ))\"\n\
+         files = [\"a b.txt\", \"c.txt\"]\n\
+         echo $((len(files))) \"$((files[-1] + '!'))\"\n\
+         for file in $files do echo \"- $file\"; end\n\
+         set count: number = 42\n\
+         count = many || echo \"still $count\"",
+    );
+
+    cmd.assert()
+        .success()
+        .stdout("10 3.5 2 true\n2 c.txt!\n- a b.txt\n- c.txt\nstill 42\n")
+        .stderr(predicate::str::contains(
+            "count is declared as a number, so it cannot hold string 'many'",
+        ));
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c")
+        .arg("echo $((3 + \"abc\")) && echo unreachable");
+    cmd.assert()
+        .failure()
+        .stdout("")
+        .stderr(predicate::str::contains(
+            "Type error: cannot add string 'abc' to number 3",
+        ));
+}
+
+#[test]
+fn test_runaway_recursion_is_an_error() {
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.arg("-c")
+        .arg("function down n\n  down $n\nend\ndown 1\necho still here");
+
+    cmd.assert()
+        .success()
+        .stdout("still here\n")
+        .stderr(predicate::str::contains("more than 200 deep"));
+}
+
+#[test]
+fn test_recursive_copy() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("photos/2024")).unwrap();
+    fs::write(dir.path().join("photos/2024/beach.jpg"), "beach").unwrap();
+    fs::write(dir.path().join("photos/cover.jpg"), "cover").unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path()).arg("-c").arg(
+        "cp -r photos terse\n\
+         copy photos recursively to verbose\n\
+         copy source=photos destination=named recursive=true",
+    );
+
+    cmd.assert().success();
+    for copy in ["terse", "verbose", "named"] {
+        assert_eq!(
+            fs::read_to_string(dir.path().join(copy).join("2024/beach.jpg")).unwrap(),
+            "beach"
+        );
+        assert!(dir.path().join(copy).join("cover.jpg").exists());
+    }
+}
+
+#[test]
+fn test_copy_directory_needs_recursive() {
+    let dir = tempdir().unwrap();
+    fs::create_dir(dir.path().join("photos")).unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path())
+        .arg("-c")
+        .arg("copy photos to backup");
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("is a directory"));
+    assert!(!dir.path().join("backup").exists());
+}
+
+#[test]
+fn test_copy_overwrite_policies() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("new.txt"), "new").unwrap();
+    fs::write(dir.path().join("old.txt"), "old").unwrap();
+
+    // With no answer on stdin, asking keeps the existing file
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path())
+        .arg("-c")
+        .arg("copy new.txt to old.txt; cp -n new.txt old.txt")
+        .write_stdin("");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("Overwrite"))
+        .stdout(predicate::str::contains("Skipped new.txt (already exists)"));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("old.txt")).unwrap(),
+        "old"
+    );
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path())
+        .arg("-c")
+        .arg("copy source=new.txt dest=old.txt overwrite=overwrite");
+    cmd.assert().success();
+    assert_eq!(
+        fs::read_to_string(dir.path().join("old.txt")).unwrap(),
+        "new"
+    );
+}
+
+#[test]
+fn test_move_follows_overwrite_policy() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("draft.txt"), "draft").unwrap();
+    fs::write(dir.path().join("final.txt"), "final").unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path())
+        .arg("-c")
+        .arg("move draft.txt to final.txt")
+        .write_stdin("n\n");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("Overwrite 'final.txt'?"));
+    assert_eq!(
+        fs::read_to_string(dir.path().join("final.txt")).unwrap(),
+        "final"
+    );
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path())
+        .arg("-c")
+        .arg("mv -f draft.txt final.txt");
+    cmd.assert().success();
+    assert!(!dir.path().join("draft.txt").exists());
+    assert_eq!(
+        fs::read_to_string(dir.path().join("final.txt")).unwrap(),
+        "draft"
+    );
+}
+
+#[test]
+fn test_several_sources() {
+    let dir = tempdir().unwrap();
+    for name in ["a.txt", "b.txt", "c.txt", "d.txt"] {
+        fs::write(dir.path().join(name), name).unwrap();
+    }
+    fs::create_dir(dir.path().join("backup")).unwrap();
+    fs::create_dir(dir.path().join("archive")).unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path()).arg("-c").arg(
+        "copy a.txt b.txt and c.txt to backup/\n\
+         cp a.txt d.txt archive/\n\
+         remove files=[a.txt, b.txt] force=true",
+    );
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("3 items copied"))
+        .stdout(predicate::str::contains("2 items removed"));
+
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        assert!(dir.path().join("backup").join(name).exists());
+    }
+    assert!(dir.path().join("archive/d.txt").exists());
+    assert!(!dir.path().join("a.txt").exists());
+    assert!(!dir.path().join("b.txt").exists());
+}
+
+#[test]
+fn test_several_sources_with_a_failure() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("a.txt"), "a").unwrap();
+    fs::write(dir.path().join("c.txt"), "c").unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path())
+        .arg("-c")
+        .arg("rm a.txt missing.txt c.txt")
+        .write_stdin("y\n");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("Removed a.txt"))
+        .stdout(predicate::str::contains("Removed c.txt"))
+        .stderr(predicate::str::contains("File not found: missing.txt"))
+        .stderr(predicate::str::contains("1 of 3 items failed"));
+    assert!(!dir.path().join("c.txt").exists());
+}
+
+#[test]
+fn test_forced_remove() {
+    let dir = tempdir().unwrap();
+    for name in ["a.txt", "b.txt", "c.txt", "keep.txt"] {
+        fs::write(dir.path().join(name), name).unwrap();
+    }
+
+    // No input: any question would cancel
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path()).arg("-c").arg(
+        "remove missing.txt -f
+         rm -f a.txt missing.txt
+         remove files=[b.txt, c.txt] -f",
+    );
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Removed a.txt"))
+        .stdout(predicate::str::contains("2 items removed"));
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        assert!(!dir.path().join(name).exists());
+    }
+
+    // Only the exact force flags skip the question
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path())
+        .arg("-c")
+        .arg("rm -lf keep.txt")
+        .write_stdin("n\n");
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("Cancelled"));
+    assert!(dir.path().join("keep.txt").exists());
+}
+
+#[test]
+fn test_globs_and_braces_in_arguments() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("src/nested")).unwrap();
+    for name in [
+        "a.txt",
+        "b.txt",
+        "keep.log",
+        "src/main.rs",
+        "src/nested/lib.rs",
+    ] {
+        fs::write(dir.path().join(name), name).unwrap();
+    }
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path()).arg("-c").arg(
+        "echo **/*.rs\n\
+         echo '*.txt' \"src/*\" note{1,2}.md v{1..3}\n\
+         remove -f *.txt",
+    );
+    cmd.assert().success().stdout(predicate::str::contains(
+        "src/main.rs src/nested/lib.rs\n*.txt src/* note1.md note2.md v1 v2 v3\n",
+    ));
+    assert!(!dir.path().join("a.txt").exists());
+    assert!(!dir.path().join("b.txt").exists());
+    assert!(dir.path().join("keep.log").exists());
+}
+
+#[test]
+fn test_glob_without_matches() {
+    let dir = tempdir().unwrap();
+
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path())
+        .env("HOME", dir.path())
+        .arg("-c")
+        .arg("echo *.missing");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No files match '*.missing'"));
+
+    // Like bash, once configured to
+    fs::write(dir.path().join(".vshrc"), "glob_no_match = \"pass\"\n").unwrap();
+    let mut cmd = Command::cargo_bin("vsh").unwrap();
+    cmd.current_dir(dir.path())
+        .env("HOME", dir.path())
+        .arg("-c")
+        .arg("echo *.missing ~/x");
+    cmd.assert()
+        .success()
+        .stdout(format!("*.missing {}/x\n", dir.path().display()));
+}